@@ -0,0 +1,99 @@
+use std::io::{Result, Write};
+
+use clap::{App, Arg, ArgMatches, SubCommand};
+use grass::{
+    algorithm::{AssumeSorted, SortedIntersect},
+    high_level_api::InvertExt,
+    properties::WithRegionCore,
+    records::Bed3,
+};
+
+use crate::{
+    input::{chrom_args, chrom_set, load_genome, open_input},
+    output::write_record,
+    with_bed_records,
+};
+
+pub fn subcommand<'a, 'b>() -> App<'a, 'b> {
+    chrom_args(
+        SubCommand::with_name("complement")
+            .about("Report the regions of the genome that are not covered by the input"),
+    )
+    .arg(
+        Arg::with_name("input")
+            .short("i")
+            .takes_value(true)
+            .required(true)
+            .help("The input file, use - for the standard input"),
+    )
+    .arg(
+        Arg::with_name("genome")
+            .short("g")
+            .takes_value(true)
+            .required(true)
+            .help("The genome file"),
+    )
+}
+
+pub fn run<W: Write>(matches: &ArgMatches, mut out: W) -> Result<()> {
+    let chroms = chrom_set(matches)?;
+    let genome = load_genome(matches.value_of("genome").unwrap(), &chroms)?;
+    let input = open_input(matches.value_of("input").unwrap())?;
+
+    // The chromosomes that doesn't have any input interval are entirely uncovered,
+    // but the inverted stream doesn't know about them.
+    let mut uncovered_chroms = genome.clone().into_iter().peekable();
+
+    with_bed_records!(input, &chroms, |input| {
+        let gaps = input
+            .invert()
+            .assume_sorted()
+            .sorted_intersect(genome.into_iter().assume_sorted());
+        for gap in gaps {
+            let gap = Bed3::new(&gap);
+            while let Some(chrom) = uncovered_chroms.peek() {
+                if chrom.chrom < gap.chrom {
+                    write_record(&mut out, chrom)?;
+                }
+                if chrom.chrom <= gap.chrom {
+                    uncovered_chroms.next();
+                } else {
+                    break;
+                }
+            }
+            if !gap.empty() {
+                write_record(&mut out, &gap)?;
+            }
+        }
+        for chrom in uncovered_chroms {
+            write_record(&mut out, &chrom)?;
+        }
+        Ok(())
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::commands::test_utils::{run_command, FILE_B};
+
+    #[test]
+    fn test_complement() {
+        let genome = "chr1\t300\nchr2\t100\nchr3\t50\n";
+        let output = run_command(
+            subcommand(),
+            |m, out| run(m, out),
+            &["-i", "$0", "-g", "$1"],
+            &[FILE_B, genome],
+        );
+        assert_eq!(
+            output.unwrap(),
+            "chr1\t0\t15\n\
+             chr1\t35\t150\n\
+             chr1\t160\t170\n\
+             chr1\t180\t300\n\
+             chr2\t0\t100\n\
+             chr3\t10\t50\n"
+        );
+    }
+}
@@ -0,0 +1,89 @@
+use std::io::{Error, ErrorKind, Result, Write};
+
+use clap::{App, Arg, ArgMatches, SubCommand};
+use grass::tabix::{TabixConfig, TabixIndex};
+
+pub fn subcommand<'a, 'b>() -> App<'a, 'b> {
+    SubCommand::with_name("index")
+        .about("Build the tabix index of a bgzipped file, like tabix")
+        .arg(
+            Arg::with_name("preset")
+                .short("p")
+                .takes_value(true)
+                .possible_values(&["bed", "gff", "vcf"])
+                .default_value("bed")
+                .help("The format of the input file"),
+        )
+        .arg(
+            Arg::with_name("force")
+                .short("f")
+                .help("Overwrite the existing index"),
+        )
+        .arg(
+            Arg::with_name("input")
+                .required(true)
+                .help("The bgzipped file, which must be sorted"),
+        )
+}
+
+pub fn run<W: Write>(matches: &ArgMatches, _out: W) -> Result<()> {
+    let input = matches.value_of("input").unwrap();
+    let config = match matches.value_of("preset").unwrap() {
+        "gff" => TabixConfig::gff(),
+        "vcf" => TabixConfig::vcf(),
+        _ => TabixConfig::bed(),
+    };
+    let index_path = TabixIndex::index_path(input);
+    if index_path.exists() && !matches.is_present("force") {
+        return Err(Error::new(
+            ErrorKind::AlreadyExists,
+            format!("{} exists, use -f to overwrite", index_path.display()),
+        ));
+    }
+    TabixIndex::build_for(input, config)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use grass::tabix::TabixReader;
+    use std::io::Read;
+
+    #[test]
+    fn test_index() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("a.bed.gz");
+        std::fs::copy("../data/a.bed.gz", &path).unwrap();
+        let path = path.to_str().unwrap();
+
+        let matches = subcommand().get_matches_from(["grass", path]);
+        run(&matches, std::io::sink()).unwrap();
+        // The index exists now, so it's not overwritten without -f
+        assert!(run(&matches, std::io::sink()).is_err());
+        let matches = subcommand().get_matches_from(["grass", "-f", path]);
+        run(&matches, std::io::sink()).unwrap();
+
+        // The same lines as tabix a.bed.gz 1:1000-5000
+        let mut fetched = String::new();
+        TabixReader::open(path)
+            .unwrap()
+            .fetch("1:1000-5000")
+            .unwrap()
+            .read_to_string(&mut fetched)
+            .unwrap();
+        let expected: String = std::fs::read_to_string("../data/a.bed")
+            .unwrap()
+            .lines()
+            .filter(|line| {
+                let fields: Vec<_> = line.split('\t').collect();
+                let (begin, end): (u32, u32) =
+                    (fields[1].parse().unwrap(), fields[2].parse().unwrap());
+                fields[0] == "1" && begin < 5000 && end > 999
+            })
+            .map(|line| format!("{}\n", line))
+            .collect();
+        assert!(!expected.is_empty());
+        assert_eq!(fetched, expected);
+    }
+}
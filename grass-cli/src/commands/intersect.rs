@@ -0,0 +1,312 @@
+use std::io::{Result, Write};
+
+use clap::{App, Arg, ArgMatches, SubCommand};
+use grass::{
+    algorithm::{AssumeSorted, Sorted, SortedIntersect},
+    chromset::LexicalChromRef,
+    properties::{Serializable, WithRegion, WithRegionCore},
+};
+
+use crate::{
+    input::{chrom_args, chrom_set, open_input},
+    output::{dump_with_region, write_record},
+    with_bed_records,
+};
+
+/// A record of the A file, along with the region we use to find overlaps
+#[derive(Clone)]
+pub struct Query<T> {
+    pub id: usize,
+    pub record: T,
+    pub begin: u32,
+    pub end: u32,
+}
+
+impl<T: WithRegion<LexicalChromRef>> WithRegionCore<LexicalChromRef> for Query<T> {
+    fn begin(&self) -> u32 {
+        self.begin
+    }
+    fn end(&self) -> u32 {
+        self.end
+    }
+    fn chrom(&self) -> &LexicalChromRef {
+        self.record.chrom()
+    }
+}
+
+/// Turns a sorted record stream to the query stream, the window is added to both sides
+pub fn make_queries<I>(iter: I, window: (u32, u32)) -> impl Sorted<Item = Query<I::Item>>
+where
+    I: Iterator + Sorted,
+    I::Item: WithRegion<LexicalChromRef>,
+{
+    iter.enumerate()
+        .map(move |(id, record)| Query {
+            id,
+            begin: record.begin().saturating_sub(window.0),
+            end: record.end().saturating_add(window.1),
+            record,
+        })
+        .assume_sorted()
+}
+
+pub struct OverlapOptions {
+    pub unique: bool,
+    pub invert: bool,
+    pub fraction: Option<f64>,
+    pub write_a: bool,
+    pub write_b: bool,
+}
+
+impl OverlapOptions {
+    pub fn from_matches(matches: &ArgMatches) -> Result<Self> {
+        let fraction = match matches.value_of("fraction") {
+            Some(value) => Some(value.parse().map_err(|_| {
+                std::io::Error::new(
+                    std::io::ErrorKind::InvalidInput,
+                    format!("Invalid overlap fraction: {}", value),
+                )
+            })?),
+            None => None,
+        };
+        Ok(Self {
+            unique: matches.is_present("unique"),
+            invert: matches.is_present("invert"),
+            fraction,
+            write_a: matches.is_present("write-a"),
+            write_b: matches.is_present("write-b"),
+        })
+    }
+
+    fn is_qualified<A, B>(&self, a: &A, b: &B) -> bool
+    where
+        A: WithRegion<LexicalChromRef>,
+        B: WithRegion<LexicalChromRef>,
+    {
+        if !a.overlaps(b) {
+            return false;
+        }
+        if let Some(fraction) = self.fraction {
+            let overlap = (a, b).length() as f64;
+            return a.length() > 0 && overlap / a.length() as f64 >= fraction;
+        }
+        true
+    }
+}
+
+/// Report the overlaps between the queries and the B records in the way bedtools does
+pub fn report_overlaps<A, B, R, W>(a: A, b: B, opts: &OverlapOptions, mut out: W) -> Result<()>
+where
+    A: Iterator<Item = Query<R>> + Sorted,
+    B: Iterator + Sorted,
+    R: WithRegion<LexicalChromRef> + Serializable + Clone,
+    B::Item: WithRegion<LexicalChromRef> + Serializable + Clone,
+    W: Write,
+{
+    let flush = |record: Option<&R>, has_overlap: bool, out: &mut W| -> Result<()> {
+        if let Some(record) = record {
+            if (opts.invert && !has_overlap) || (opts.unique && !opts.invert && has_overlap) {
+                write_record(out, record)?;
+            }
+        }
+        Ok(())
+    };
+
+    let mut last_id = None;
+    let mut last_record = None;
+    let mut has_overlap = false;
+
+    for (query, b) in a.sorted_left_outer_intersect(b) {
+        if last_id != Some(query.id) {
+            flush(last_record.as_ref(), has_overlap, &mut out)?;
+            last_id = Some(query.id);
+            last_record = Some(query.record.clone());
+            has_overlap = false;
+        }
+
+        let b = match b {
+            Some(b) if opts.is_qualified(&query, &b) => b,
+            _ => continue,
+        };
+
+        has_overlap = true;
+
+        if opts.unique || opts.invert {
+            continue;
+        }
+
+        if opts.write_a {
+            query.record.dump(&mut out)?;
+        } else {
+            let overlap = (&query.record, &b);
+            dump_with_region(
+                &mut out,
+                &query.record,
+                query.chrom(),
+                overlap.begin(),
+                overlap.end(),
+            )?;
+        }
+
+        if opts.write_b {
+            out.write_all(b"\t")?;
+            b.dump(&mut out)?;
+        }
+
+        out.write_all(b"\n")?;
+    }
+
+    flush(last_record.as_ref(), has_overlap, &mut out)
+}
+
+pub fn overlap_args<'a, 'b>(app: App<'a, 'b>) -> App<'a, 'b> {
+    app.arg(
+        Arg::with_name("file-a")
+            .short("a")
+            .takes_value(true)
+            .required(true)
+            .help("The A file, use - for the standard input"),
+    )
+    .arg(
+        Arg::with_name("file-b")
+            .short("b")
+            .takes_value(true)
+            .required(true)
+            .help("The B file"),
+    )
+    .arg(
+        Arg::with_name("unique")
+            .short("u")
+            .help("Write the original A entry once if any overlap is found"),
+    )
+    .arg(
+        Arg::with_name("invert")
+            .short("v")
+            .help("Only report the A entries that have no overlap"),
+    )
+    .arg(
+        Arg::with_name("write-a")
+            .long("wa")
+            .help("Write the original A entry for each overlap"),
+    )
+    .arg(
+        Arg::with_name("write-b")
+            .long("wb")
+            .help("Write the original B entry for each overlap"),
+    )
+}
+
+pub fn subcommand<'a, 'b>() -> App<'a, 'b> {
+    chrom_args(overlap_args(
+        SubCommand::with_name("intersect").about("Report overlaps between two files"),
+    ))
+    .arg(
+        Arg::with_name("fraction")
+            .short("f")
+            .takes_value(true)
+            .help("Minimum overlap required as a fraction of A"),
+    )
+}
+
+pub fn run<W: Write>(matches: &ArgMatches, out: W) -> Result<()> {
+    let chroms = chrom_set(matches)?;
+    let opts = OverlapOptions::from_matches(matches)?;
+
+    let a = open_input(matches.value_of("file-a").unwrap())?;
+    let b = open_input(matches.value_of("file-b").unwrap())?;
+
+    with_bed_records!(a, &chroms, |a| {
+        with_bed_records!(b, &chroms, |b| {
+            report_overlaps(make_queries(a, (0, 0)), b, &opts, out)
+        })
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::commands::test_utils::{run_command, FILE_A, FILE_B};
+
+    fn intersect(args: &[&str]) -> String {
+        let mut full_args = vec!["-a", "$0", "-b", "$1"];
+        full_args.extend_from_slice(args);
+        run_command(
+            subcommand(),
+            |m, out| run(m, out),
+            &full_args,
+            &[FILE_A, FILE_B],
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_intersect() {
+        assert_eq!(
+            intersect(&[]),
+            "chr1\t15\t20\ta1\nchr1\t30\t35\ta2\nchr1\t150\t160\ta3\nchr1\t170\t180\ta3\n"
+        );
+        assert_eq!(
+            intersect(&["--wa", "--wb"]),
+            "chr1\t10\t20\ta1\tchr1\t15\t35\tb1\n\
+             chr1\t30\t40\ta2\tchr1\t15\t35\tb1\n\
+             chr1\t100\t200\ta3\tchr1\t150\t160\tb2\n\
+             chr1\t100\t200\ta3\tchr1\t170\t180\tb3\n"
+        );
+        assert_eq!(
+            intersect(&["-u"]),
+            "chr1\t10\t20\ta1\nchr1\t30\t40\ta2\nchr1\t100\t200\ta3\n"
+        );
+        assert_eq!(intersect(&["-v"]), "chr2\t5\t15\ta4\n");
+        assert_eq!(
+            intersect(&["-f", "0.5"]),
+            "chr1\t15\t20\ta1\nchr1\t30\t35\ta2\n"
+        );
+    }
+
+    #[test]
+    fn test_intersect_chrom_naming() {
+        let ensembl = "1\t12\t18\te1\n2\t0\t10\te2\n";
+        let args = ["-a", "$0", "-b", "$1", "-u"];
+        // Without the aliases, chr1 and 1 are different chromosomes
+        let output = run_command(
+            subcommand(),
+            |m, out| run(m, out),
+            &args,
+            &[FILE_A, ensembl],
+        );
+        assert_eq!(output.unwrap(), "");
+
+        let args = [
+            "-a",
+            "$0",
+            "-b",
+            "$1",
+            "--wa",
+            "--wb",
+            "--chrom-naming",
+            "ucsc",
+        ];
+        let output = run_command(
+            subcommand(),
+            |m, out| run(m, out),
+            &args,
+            &[FILE_A, ensembl],
+        );
+        assert_eq!(
+            output.unwrap(),
+            "chr1\t10\t20\ta1\tchr1\t12\t18\te1\nchr2\t5\t15\ta4\tchr2\t0\t10\te2\n"
+        );
+    }
+
+    #[test]
+    fn test_intersect_unsorted() {
+        let unsorted = "chr1\t100\t200\ta3\nchr1\t10\t20\ta1\n";
+        let result = run_command(
+            subcommand(),
+            |m, out| run(m, out),
+            &["-a", "$0", "-b", "$1"],
+            &[unsorted, FILE_B],
+        );
+        assert!(result.unwrap_err().to_string().contains("not sorted"));
+    }
+}
@@ -0,0 +1,45 @@
+use std::io::{Result, Write};
+
+use clap::{App, Arg, ArgMatches, SubCommand};
+use grass::high_level_api::MergeExt;
+
+use crate::{
+    input::{chrom_args, chrom_set, open_input},
+    output::write_record,
+    with_bed_records,
+};
+
+pub fn subcommand<'a, 'b>() -> App<'a, 'b> {
+    chrom_args(SubCommand::with_name("merge").about("Merge overlapping intervals")).arg(
+        Arg::with_name("input")
+            .short("i")
+            .takes_value(true)
+            .required(true)
+            .help("The input file, use - for the standard input"),
+    )
+}
+
+pub fn run<W: Write>(matches: &ArgMatches, mut out: W) -> Result<()> {
+    let chroms = chrom_set(matches)?;
+    let input = open_input(matches.value_of("input").unwrap())?;
+
+    with_bed_records!(input, &chroms, |input| {
+        for merged in input.merge_overlaps() {
+            write_record(&mut out, &merged)?;
+        }
+        Ok(())
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::commands::test_utils::run_command;
+
+    #[test]
+    fn test_merge() {
+        let input = "chr1\t10\t20\nchr1\t15\t35\nchr1\t35\t40\nchr1\t50\t60\nchr2\t1\t5\n";
+        let output = run_command(subcommand(), |m, out| run(m, out), &["-i", "$0"], &[input]);
+        assert_eq!(output.unwrap(), "chr1\t10\t40\nchr1\t50\t60\nchr2\t1\t5\n");
+    }
+}
@@ -0,0 +1,49 @@
+pub mod complement;
+pub mod index;
+pub mod intersect;
+pub mod merge;
+pub mod multiinter;
+pub mod query;
+pub mod shift;
+pub mod subtract;
+pub mod window;
+
+#[cfg(test)]
+pub(crate) mod test_utils {
+    use std::io::Result;
+
+    use clap::{App, ArgMatches};
+    use tempfile::TempDir;
+
+    /// The BED files shared by the command tests, the expected outputs are what bedtools reports
+    /// for them
+    pub const FILE_A: &str =
+        "chr1\t10\t20\ta1\nchr1\t30\t40\ta2\nchr1\t100\t200\ta3\nchr2\t5\t15\ta4\n";
+    pub const FILE_B: &str =
+        "chr1\t15\t35\tb1\nchr1\t150\t160\tb2\nchr1\t170\t180\tb3\nchr3\t0\t10\tb4\n";
+
+    /// Write the inputs to a temporary directory and run the command, each `$n` in the arguments
+    /// is replaced by the path of the n-th input
+    pub fn run_command<F>(app: App, run: F, args: &[&str], inputs: &[&str]) -> Result<String>
+    where
+        F: FnOnce(&ArgMatches, &mut Vec<u8>) -> Result<()>,
+    {
+        let dir = TempDir::new()?;
+        let mut paths = vec![];
+        for (idx, content) in inputs.iter().enumerate() {
+            let path = dir.path().join(format!("{}.bed", idx));
+            std::fs::write(&path, content)?;
+            paths.push(path.to_str().unwrap().to_string());
+        }
+
+        let args = args.iter().map(|arg| match arg.strip_prefix('$') {
+            Some(idx) => paths[idx.parse::<usize>().unwrap()].clone(),
+            None => arg.to_string(),
+        });
+        let matches = app.get_matches_from(std::iter::once("grass".to_string()).chain(args));
+
+        let mut out = vec![];
+        run(&matches, &mut out)?;
+        Ok(String::from_utf8(out).unwrap())
+    }
+}
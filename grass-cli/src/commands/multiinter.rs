@@ -0,0 +1,120 @@
+use std::io::{Result, Write};
+
+use clap::{App, Arg, ArgMatches, SubCommand};
+use grass::{
+    algorithm::Components, chromset::LexicalChromRef, properties::WithRegionCore, records::Bed3,
+    ChromName,
+};
+use itertools::{kmerge, Itertools};
+
+use crate::{
+    input::{chrom_args, chrom_set, open_input, InputErrors},
+    with_bed_records,
+};
+
+pub fn subcommand<'a, 'b>() -> App<'a, 'b> {
+    chrom_args(
+        SubCommand::with_name("multiinter")
+            .about("Identify the common intervals among multiple files"),
+    )
+    .arg(
+        Arg::with_name("input")
+            .short("i")
+            .takes_value(true)
+            .multiple(true)
+            .required(true)
+            .help("The input files"),
+    )
+}
+
+pub fn run<W: Write>(matches: &ArgMatches, mut out: W) -> Result<()> {
+    let chroms = chrom_set(matches)?;
+
+    let mut inputs = vec![];
+    let mut errors = vec![];
+    for (file_id, path) in matches.values_of("input").unwrap().enumerate() {
+        let input = open_input(path)?;
+        let iter: Box<dyn Iterator<Item = Bed3<LexicalChromRef>>> =
+            with_bed_records!(input, &chroms, |input, input_errors| {
+                errors.push(input_errors);
+                Box::new(input.map(|x| x.to_bed3()))
+            });
+        inputs.push(iter.components().map(move |x| (x, file_id)));
+    }
+
+    let mut current_depth = vec![0; inputs.len()];
+    let mut active_count = 0;
+    let mut last_pos = None;
+
+    for ((chr, pos), group) in kmerge(inputs)
+        .group_by(|(comp, _)| comp.position())
+        .into_iter()
+    {
+        if let Some((left_chr, left_pos)) = last_pos {
+            if left_chr == chr && active_count > 0 && left_pos < pos {
+                chr.write(&mut out)?;
+                let active: Vec<_> = current_depth
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, &depth)| depth > 0)
+                    .map(|(idx, _)| (idx + 1).to_string())
+                    .collect();
+                write!(
+                    out,
+                    "\t{}\t{}\t{}\t{}",
+                    left_pos,
+                    pos,
+                    active_count,
+                    active.join(",")
+                )?;
+                for &depth in current_depth.iter() {
+                    write!(out, "\t{}", if depth > 0 { 1 } else { 0 })?;
+                }
+                out.write_all(b"\n")?;
+            }
+        }
+
+        for (comp, file_idx) in group {
+            match (current_depth[file_idx], comp.depth) {
+                (0, new) if new > 0 => active_count += 1,
+                (old, 0) if old > 0 => active_count -= 1,
+                _ => (),
+            }
+            current_depth[file_idx] = comp.depth;
+        }
+
+        last_pos = Some((chr, pos));
+    }
+    errors.iter().try_for_each(InputErrors::check)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::commands::test_utils::{run_command, FILE_A, FILE_B};
+
+    #[test]
+    fn test_multiinter() {
+        let output = run_command(
+            subcommand(),
+            |m, out| run(m, out),
+            &["-i", "$0", "$1"],
+            &[FILE_A, FILE_B],
+        );
+        assert_eq!(
+            output.unwrap(),
+            "chr1\t10\t15\t1\t1\t1\t0\n\
+             chr1\t15\t20\t2\t1,2\t1\t1\n\
+             chr1\t20\t30\t1\t2\t0\t1\n\
+             chr1\t30\t35\t2\t1,2\t1\t1\n\
+             chr1\t35\t40\t1\t1\t1\t0\n\
+             chr1\t100\t150\t1\t1\t1\t0\n\
+             chr1\t150\t160\t2\t1,2\t1\t1\n\
+             chr1\t160\t170\t1\t1\t1\t0\n\
+             chr1\t170\t180\t2\t1,2\t1\t1\n\
+             chr1\t180\t200\t1\t1\t1\t0\n\
+             chr2\t5\t15\t1\t1\t1\t0\n\
+             chr3\t0\t10\t1\t2\t0\t1\n"
+        );
+    }
+}
@@ -0,0 +1,85 @@
+use std::io::{Error, ErrorKind, Result, Write};
+
+use clap::{App, Arg, ArgMatches, SubCommand};
+use grass::query::{QueryError, QueryRuntime};
+
+pub fn subcommand<'a, 'b>() -> App<'a, 'b> {
+    SubCommand::with_name("query")
+        .about("Run a GRASS query, the input files are bound to a, b, c, ...")
+        .arg(
+            Arg::with_name("expr")
+                .short("e")
+                .takes_value(true)
+                .help("The query to run"),
+        )
+        .arg(
+            Arg::with_name("script")
+                .short("f")
+                .takes_value(true)
+                .conflicts_with("expr")
+                .required_unless("expr")
+                .help("The .grass script file to run"),
+        )
+        .arg(
+            Arg::with_name("inputs")
+                .multiple(true)
+                .help("The input files"),
+        )
+}
+
+fn query_error(e: QueryError) -> Error {
+    Error::other(e)
+}
+
+pub fn run<W: Write>(matches: &ArgMatches, out: W) -> Result<()> {
+    let mut runtime = QueryRuntime::new(out);
+
+    for (idx, path) in matches
+        .values_of("inputs")
+        .into_iter()
+        .flatten()
+        .enumerate()
+    {
+        if idx >= 26 {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                "At most 26 input files are allowed",
+            ));
+        }
+        let name = ((b'a' + idx as u8) as char).to_string();
+        runtime.bind_file(&name, path).map_err(query_error)?;
+    }
+
+    if let Some(query) = matches.value_of("expr") {
+        runtime.run(query).map_err(query_error)
+    } else {
+        let script = matches.value_of("script").unwrap();
+        runtime.run_file(script).map_err(query_error)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::commands::test_utils::{run_command, FILE_A, FILE_B};
+
+    #[test]
+    fn test_query() {
+        let output = run_command(
+            subcommand(),
+            |m, out| run(m, out),
+            &["-e", "intersect(a, b) | show_all();", "$0", "$1"],
+            &[FILE_A, FILE_B],
+        );
+        // The pairs are the rows of bedtools intersect -wa -wb
+        assert_eq!(
+            output.unwrap(),
+            "chr1\t10\t20\ta1\t|\tchr1\t15\t35\tb1\n\
+             chr1\t30\t40\ta2\t|\tchr1\t15\t35\tb1\n\
+             chr1\t100\t200\ta3\t|\tchr1\t150\t160\tb2\n\
+             chr1\t100\t200\ta3\t|\tchr1\t170\t180\tb3\n\
+             -------------------------------\n\
+             4 Rows Returned\n"
+        );
+    }
+}
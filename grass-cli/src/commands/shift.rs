@@ -0,0 +1,68 @@
+use std::io::{Result, Write};
+
+use clap::{App, Arg, ArgMatches, SubCommand};
+use grass::properties::WithRegionCore;
+
+use crate::{
+    input::{chrom_args, chrom_set, open_input},
+    output::write_with_region,
+    with_bed_records,
+};
+
+pub fn subcommand<'a, 'b>() -> App<'a, 'b> {
+    chrom_args(SubCommand::with_name("shift").about("Shift each interval by a number of bases"))
+        .arg(
+            Arg::with_name("input")
+                .short("i")
+                .takes_value(true)
+                .required(true)
+                .help("The input file, use - for the standard input"),
+        )
+        .arg(
+            Arg::with_name("shift")
+                .short("s")
+                .takes_value(true)
+                .required(true)
+                .allow_hyphen_values(true)
+                .help("The number of bases to shift, negative value shifts to the left"),
+        )
+}
+
+pub fn run<W: Write>(matches: &ArgMatches, mut out: W) -> Result<()> {
+    let chroms = chrom_set(matches)?;
+    let shift: i64 =
+        matches.value_of("shift").unwrap().parse().map_err(|_| {
+            std::io::Error::new(std::io::ErrorKind::InvalidInput, "Invalid shift size")
+        })?;
+    let input = open_input(matches.value_of("input").unwrap())?;
+
+    let apply = |pos: u32| (pos as i64 + shift).max(0).min(u32::MAX as i64) as u32;
+
+    with_bed_records!(input, &chroms, |input| {
+        for record in input {
+            let (begin, end) = (apply(record.begin()), apply(record.end()));
+            write_with_region(&mut out, &record, record.chrom(), begin, end)?;
+        }
+        Ok(())
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::commands::test_utils::{run_command, FILE_A};
+
+    #[test]
+    fn test_shift() {
+        let output = run_command(
+            subcommand(),
+            |m, out| run(m, out),
+            &["-i", "$0", "-s", "-12"],
+            &[FILE_A],
+        );
+        assert_eq!(
+            output.unwrap(),
+            "chr1\t0\t8\ta1\nchr1\t18\t28\ta2\nchr1\t88\t188\ta3\nchr2\t0\t3\ta4\n"
+        );
+    }
+}
@@ -0,0 +1,70 @@
+use std::io::{Result, Write};
+
+use clap::{App, Arg, ArgMatches, SubCommand};
+use grass::{high_level_api::SubtractExt, properties::WithRegionCore};
+
+use crate::{
+    input::{chrom_args, chrom_set, open_input},
+    output::write_with_region,
+    with_bed_records,
+};
+
+pub fn subcommand<'a, 'b>() -> App<'a, 'b> {
+    chrom_args(SubCommand::with_name("subtract").about("Remove the portion of A that overlaps B"))
+        .arg(
+            Arg::with_name("file-a")
+                .short("a")
+                .takes_value(true)
+                .required(true)
+                .help("The A file, use - for the standard input"),
+        )
+        .arg(
+            Arg::with_name("file-b")
+                .short("b")
+                .takes_value(true)
+                .required(true)
+                .help("The B file"),
+        )
+}
+
+pub fn run<W: Write>(matches: &ArgMatches, mut out: W) -> Result<()> {
+    let chroms = chrom_set(matches)?;
+
+    let a = open_input(matches.value_of("file-a").unwrap())?;
+    let b = open_input(matches.value_of("file-b").unwrap())?;
+
+    with_bed_records!(a, &chroms, |a| {
+        with_bed_records!(b, &chroms, |b| {
+            for remaining in a.subtract(b) {
+                let (begin, end) = (remaining.begin(), remaining.end());
+                write_with_region(&mut out, &remaining.0, remaining.chrom(), begin, end)?;
+            }
+            Ok(())
+        })
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::commands::test_utils::{run_command, FILE_A, FILE_B};
+
+    #[test]
+    fn test_subtract() {
+        let output = run_command(
+            subcommand(),
+            |m, out| run(m, out),
+            &["-a", "$0", "-b", "$1"],
+            &[FILE_A, FILE_B],
+        );
+        assert_eq!(
+            output.unwrap(),
+            "chr1\t10\t15\ta1\n\
+             chr1\t35\t40\ta2\n\
+             chr1\t100\t150\ta3\n\
+             chr1\t160\t170\ta3\n\
+             chr1\t180\t200\ta3\n\
+             chr2\t5\t15\ta4\n"
+        );
+    }
+}
@@ -0,0 +1,67 @@
+use std::io::{Result, Write};
+
+use clap::{App, Arg, ArgMatches, SubCommand};
+
+use super::intersect::{make_queries, overlap_args, report_overlaps, OverlapOptions};
+use crate::{
+    input::{chrom_args, chrom_set, open_input},
+    with_bed_records,
+};
+
+pub fn subcommand<'a, 'b>() -> App<'a, 'b> {
+    chrom_args(overlap_args(
+        SubCommand::with_name("window")
+            .about("Report the B entries within a window around each A entry"),
+    ))
+    .arg(
+        Arg::with_name("window")
+            .short("w")
+            .takes_value(true)
+            .default_value("1000")
+            .help("The number of bases added to both sides of each A entry"),
+    )
+}
+
+pub fn run<W: Write>(matches: &ArgMatches, out: W) -> Result<()> {
+    let chroms = chrom_set(matches)?;
+    let mut opts = OverlapOptions::from_matches(matches)?;
+    // Unlike intersect, window always reports both the A and B entries
+    opts.write_a = true;
+    opts.write_b = true;
+
+    let window: u32 = matches.value_of("window").unwrap().parse().map_err(|_| {
+        std::io::Error::new(std::io::ErrorKind::InvalidInput, "Invalid window size")
+    })?;
+
+    let a = open_input(matches.value_of("file-a").unwrap())?;
+    let b = open_input(matches.value_of("file-b").unwrap())?;
+
+    with_bed_records!(a, &chroms, |a| {
+        with_bed_records!(b, &chroms, |b| {
+            report_overlaps(make_queries(a, (window, window)), b, &opts, out)
+        })
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::commands::test_utils::{run_command, FILE_A, FILE_B};
+
+    #[test]
+    fn test_window() {
+        let output = run_command(
+            subcommand(),
+            |m, out| run(m, out),
+            &["-a", "$0", "-b", "$1", "-w", "10"],
+            &[FILE_A, FILE_B],
+        );
+        assert_eq!(
+            output.unwrap(),
+            "chr1\t10\t20\ta1\tchr1\t15\t35\tb1\n\
+             chr1\t30\t40\ta2\tchr1\t15\t35\tb1\n\
+             chr1\t100\t200\ta3\tchr1\t150\t160\tb2\n\
+             chr1\t100\t200\ta3\tchr1\t170\t180\tb3\n"
+        );
+    }
+}
@@ -0,0 +1,227 @@
+use std::{
+    collections::HashMap,
+    fs::File,
+    io::{stdin, BufRead, BufReader, Cursor, Error, ErrorKind, Read, Result},
+};
+
+use clap::{App, Arg, ArgMatches};
+use grass::{
+    algorithm::UnsortedErrorSlot,
+    chromset::{AliasedChromSet, ChromAliases, ChromNaming, LexicalChromRef},
+    records::Bed3,
+    ChromSet, ChromSetHandle, ErrorSlot, LexicalChromSet,
+};
+use libflate::gzip::MultiDecoder;
+
+/// An opened input file, with the number of BED columns detected from its first record
+pub struct Input {
+    pub reader: Box<dyn Read>,
+    pub columns: usize,
+}
+
+fn open_raw(path: &str) -> Result<Box<dyn Read>> {
+    let mut input: Box<dyn Read> = if path == "-" || path == "stdin" {
+        Box::new(stdin())
+    } else {
+        Box::new(File::open(path)?)
+    };
+
+    let mut magic_code = [0u8; 2];
+    let mut magic_size = 0;
+    while magic_size < 2 {
+        match input.read(&mut magic_code[magic_size..])? {
+            0 => break,
+            n => magic_size += n,
+        }
+    }
+
+    let rewind = Cursor::new(magic_code[..magic_size].to_vec()).chain(input);
+    if magic_size == 2 && magic_code == [0x1f, 0x8b] {
+        Ok(Box::new(MultiDecoder::new(rewind)?))
+    } else {
+        Ok(Box::new(rewind))
+    }
+}
+
+fn is_header_line(line: &str) -> bool {
+    line.starts_with('#') || line.starts_with("track") || line.starts_with("browser")
+}
+
+/// Open a (possibly gzipped) BED file, "-" means the standard input
+pub fn open_input(path: &str) -> Result<Input> {
+    let mut reader = BufReader::new(open_raw(path)?);
+    let mut seen = Vec::new();
+    let mut line = String::new();
+    let mut columns = 3;
+
+    loop {
+        line.clear();
+        if reader.read_line(&mut line)? == 0 {
+            break;
+        }
+        if is_header_line(&line) || line.trim().is_empty() {
+            continue;
+        }
+        columns = line.trim_end().split('\t').count();
+        seen.extend_from_slice(line.as_bytes());
+        break;
+    }
+
+    if columns < 3 {
+        return Err(Error::new(
+            ErrorKind::InvalidData,
+            format!(
+                "{}: expecting at least 3 columns, but {} found",
+                path, columns
+            ),
+        ));
+    }
+
+    let reader = Box::new(Cursor::new(seen).chain(reader));
+    Ok(Input { reader, columns })
+}
+
+/// The chromosome set shared by the inputs of a command
+pub type Chroms = AliasedChromSet<LexicalChromSet>;
+
+/// The arguments that control how the chromosome names of different inputs are matched
+pub fn chrom_args<'a, 'b>(app: App<'a, 'b>) -> App<'a, 'b> {
+    app.arg(
+        Arg::with_name("chrom-naming")
+            .long("chrom-naming")
+            .takes_value(true)
+            .possible_values(&["ucsc", "ensembl"])
+            .help("Match the equivalent chromosome names, such as chr1, 1 and NC_000001.11, and report them in the given naming"),
+    )
+    .arg(
+        Arg::with_name("chrom-aliases")
+            .long("chrom-aliases")
+            .takes_value(true)
+            .help("A tab separated file, each line has a chromosome name followed by its aliases"),
+    )
+}
+
+/// Create the chromosome set for the inputs, the chromosome names are matched with the aliases
+/// given by the `chrom_args`
+pub fn chrom_set(matches: &ArgMatches) -> Result<Chroms> {
+    let aliases = match matches.value_of("chrom-naming") {
+        Some("ensembl") => ChromAliases::builtin(ChromNaming::Ensembl),
+        Some(_) => ChromAliases::builtin(ChromNaming::Ucsc),
+        None => ChromAliases::empty(),
+    };
+    let aliases = match matches.value_of("chrom-aliases") {
+        Some(path) => aliases.load_table(open_raw(path)?)?,
+        None => aliases,
+    };
+    Ok(AliasedChromSet::new(LexicalChromSet::new(), aliases))
+}
+
+/// Load a genome file as a list of intervals covers each chromosome.
+/// Both the bedtools genome file format (chrom, size) and BED files are accepted.
+pub fn load_genome(path: &str, chroms: &Chroms) -> Result<Vec<Bed3<LexicalChromRef>>> {
+    let mut handle = chroms.get_handle();
+    let mut sizes = HashMap::new();
+    let mut order = Vec::new();
+
+    for line in BufReader::new(open_raw(path)?).lines() {
+        let line = line?;
+        if is_header_line(&line) || line.trim().is_empty() {
+            continue;
+        }
+        let fields: Vec<_> = line.trim_end().split('\t').collect();
+        let size_field = match fields.len() {
+            2 => fields[1],
+            n if n >= 3 => fields[2],
+            _ => {
+                return Err(Error::new(
+                    ErrorKind::InvalidData,
+                    format!("{}: malformed genome file line: {}", path, line),
+                ))
+            }
+        };
+        let size: u32 = size_field.parse().map_err(|_| {
+            Error::new(
+                ErrorKind::InvalidData,
+                format!("{}: invalid chromosome size: {}", path, size_field),
+            )
+        })?;
+        if sizes.insert(fields[0].to_string(), size).is_none() {
+            order.push(fields[0].to_string());
+        }
+    }
+
+    let mut ret: Vec<_> = order
+        .into_iter()
+        .map(|name| Bed3 {
+            begin: 0,
+            end: sizes[&name],
+            chrom: handle.query_or_insert(&name),
+        })
+        .collect();
+
+    ret.sort_by(|a, b| a.chrom.cmp(&b.chrom));
+
+    Ok(ret)
+}
+
+/// The errors that stop the record stream of an input.
+///
+/// The record streams just end on a malformed line or an out-of-order record, so the errors kept
+/// in the slots of the stream are checked after the stream is consumed.
+pub struct InputErrors {
+    pub parse: ErrorSlot,
+    pub unsorted: UnsortedErrorSlot,
+}
+
+impl InputErrors {
+    pub fn check(&self) -> Result<()> {
+        if let Some(e) = self.parse.borrow_mut().take() {
+            return Err(Error::new(ErrorKind::InvalidData, e.to_string()));
+        }
+        if let Some(e) = self.unsorted.borrow_mut().take() {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                format!("{}, please sort the input with sort -k1,1 -k2,2n", e),
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Expand the body with the record iterator for the detected BED flavor of the input, the
+/// records are checked to be sorted by chromosome name and start position.
+///
+/// The body returns a `Result<()>`, and the errors of the input are reported after it. Use
+/// the `|iter, errors|` form to get the `InputErrors` and check them when the records are
+/// consumed outside the body.
+#[macro_export]
+macro_rules! with_bed_records {
+    ($input:expr, $chroms:expr, |$iter:ident| $body:expr) => {
+        with_bed_records!($input, $chroms, |$iter, errors| {
+            let result: std::io::Result<()> = $body;
+            result.and_then(|_| errors.check())
+        })
+    };
+    ($input:expr, $chroms:expr, |$iter:ident, $errors:ident| $body:expr) => {{
+        let input: $crate::input::Input = $input;
+        match input.columns {
+            3 => with_bed_records!(@open input, $chroms, grass::records::Bed3<_>, |$iter, $errors| $body),
+            4 => with_bed_records!(@open input, $chroms, grass::records::Bed4<_>, |$iter, $errors| $body),
+            5 => with_bed_records!(@open input, $chroms, grass::records::Bed5<_, f64>, |$iter, $errors| $body),
+            6 => with_bed_records!(@open input, $chroms, grass::records::Bed6<_, f64>, |$iter, $errors| $body),
+            12 => with_bed_records!(@open input, $chroms, grass::records::Bed12<_, f64>, |$iter, $errors| $body),
+            _ => with_bed_records!(@open input, $chroms, grass::records::BedN<_>, |$iter, $errors| $body),
+        }
+    }};
+    (@open $input:ident, $chroms:expr, $rec_ty:ty, |$iter:ident, $errors:ident| $body:expr) => {{
+        let records: grass::LineRecordStream<_, _, $rec_ty> =
+            grass::LineRecordStreamExt::into_record_iter($input.reader, $chroms);
+        let parse = records.error_slot();
+        let $iter = grass::algorithm::CheckSorted::check_sorted::<grass::chromset::LexicalChromRef>(records);
+        let $errors = $crate::input::InputErrors {
+            parse,
+            unsorted: $iter.error_slot(),
+        };
+        $body
+    }};
+}
@@ -0,0 +1,60 @@
+use std::io::{stdout, BufWriter, ErrorKind};
+
+use clap::{App, AppSettings};
+
+mod commands;
+mod input;
+mod output;
+
+use commands::{complement, index, intersect, merge, multiinter, query, shift, subtract, window};
+
+/// The bedtools style flags that has more than one letter but a single dash
+const SINGLE_DASH_LONG_FLAGS: &[&str] = &["-wa", "-wb"];
+
+fn main() {
+    let args = std::env::args().map(|arg| {
+        if SINGLE_DASH_LONG_FLAGS.contains(&arg.as_str()) {
+            format!("-{}", arg)
+        } else {
+            arg
+        }
+    });
+
+    let matches = App::new("grass")
+        .about("Genomic Records AbStractionS - bedtools compatible command line interface")
+        .setting(AppSettings::SubcommandRequiredElseHelp)
+        .subcommand(intersect::subcommand())
+        .subcommand(merge::subcommand())
+        .subcommand(subtract::subcommand())
+        .subcommand(complement::subcommand())
+        .subcommand(window::subcommand())
+        .subcommand(shift::subcommand())
+        .subcommand(multiinter::subcommand())
+        .subcommand(query::subcommand())
+        .subcommand(index::subcommand())
+        .get_matches_from(args);
+
+    let out = BufWriter::new(stdout());
+
+    let result = match matches.subcommand() {
+        ("intersect", Some(matches)) => intersect::run(matches, out),
+        ("merge", Some(matches)) => merge::run(matches, out),
+        ("subtract", Some(matches)) => subtract::run(matches, out),
+        ("complement", Some(matches)) => complement::run(matches, out),
+        ("window", Some(matches)) => window::run(matches, out),
+        ("shift", Some(matches)) => shift::run(matches, out),
+        ("multiinter", Some(matches)) => multiinter::run(matches, out),
+        ("query", Some(matches)) => query::run(matches, out),
+        ("index", Some(matches)) => index::run(matches, out),
+        _ => unreachable!(),
+    };
+
+    match result {
+        Err(e) if e.kind() == ErrorKind::BrokenPipe => (),
+        Err(e) => {
+            eprintln!("grass: {}", e);
+            std::process::exit(1);
+        }
+        Ok(_) => (),
+    }
+}
@@ -0,0 +1,45 @@
+use std::io::{Result, Write};
+
+use grass::{properties::Serializable, ChromName};
+
+pub fn write_record<W: Write, T: Serializable>(mut out: W, record: &T) -> Result<()> {
+    record.dump(&mut out)?;
+    out.write_all(b"\n")
+}
+
+/// Dump the record, but with the first three columns replaced by the given region
+pub fn dump_with_region<W: Write, C: ChromName, T: Serializable>(
+    mut out: W,
+    record: &T,
+    chrom: &C,
+    begin: u32,
+    end: u32,
+) -> Result<()> {
+    let mut buf = Vec::new();
+    record.dump(&mut buf)?;
+
+    chrom.write(&mut out)?;
+    write!(out, "\t{}\t{}", begin, end)?;
+
+    if let Some(rest) = buf
+        .iter()
+        .enumerate()
+        .filter(|(_, &c)| c == b'\t')
+        .nth(2)
+        .map(|(idx, _)| &buf[idx..])
+    {
+        out.write_all(rest)?;
+    }
+    Ok(())
+}
+
+pub fn write_with_region<W: Write, C: ChromName, T: Serializable>(
+    mut out: W,
+    record: &T,
+    chrom: &C,
+    begin: u32,
+    end: u32,
+) -> Result<()> {
+    dump_with_region(&mut out, record, chrom, begin, end)?;
+    out.write_all(b"\n")
+}
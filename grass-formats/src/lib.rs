@@ -13,9 +13,22 @@ pub enum AlignmentFlavor {
     SAM,
 }
 
+#[derive(Debug, PartialEq)]
+pub enum GffFlavor {
+    GFF3,
+    GTF,
+}
+
 #[derive(Debug, PartialEq)]
 pub enum FileKind {
     Bed(usize),
+    /// A bedGraph file, which starts with a `track type=bedGraph` line
+    BedGraph,
+    Gff(GffFlavor),
+    /// An ENCODE narrowPeak file, which is BED6+4
+    NarrowPeak,
+    /// An ENCODE broadPeak file, which is BED6+3
+    BroadPeak,
     Vcf,
     Fasta,
     Alignment(AlignmentFlavor),
@@ -49,6 +62,24 @@ fn detect_bam_or_cram<R: Read>(mut input: R) -> Result<(Option<AlignmentFlavor>,
     }
 }
 
+/// Detect the kind of a tab separated data line, a GFF/GTF line has 9 columns with the
+/// positions in the 4th and 5th columns
+fn detect_data_line_kind(line: &str) -> FileKind {
+    let fields: Vec<_> = line.trim_end().split('\t').collect();
+    let is_position = |s: &str| s.parse::<u32>().is_ok();
+    if fields.len() == 9
+        && !is_position(fields[1])
+        && is_position(fields[3])
+        && is_position(fields[4])
+    {
+        if fields[8].contains('=') && !fields[8].contains('"') {
+            return FileKind::Gff(GffFlavor::GFF3);
+        }
+        return FileKind::Gff(GffFlavor::GTF);
+    }
+    FileKind::Bed(fields.len())
+}
+
 fn detect_uncompressed_text_file_kind<R: Read>(input: R) -> Result<(FileKind, impl Read)> {
     let mut reader = BufReader::new(input);
 
@@ -65,6 +96,12 @@ fn detect_uncompressed_text_file_kind<R: Read>(input: R) -> Result<(FileKind, im
             if line.starts_with("##fileformat=VCF") {
                 detect_type = Ok(FileKind::Vcf);
                 break;
+            } else if line.starts_with("##gff-version 3") {
+                detect_type = Ok(FileKind::Gff(GffFlavor::GFF3));
+                break;
+            } else if line.starts_with("track") && line.contains("type=bedGraph") {
+                detect_type = Ok(FileKind::BedGraph);
+                break;
             } else if line.starts_with("#") {
                 detect_type = Ok(FileKind::Bed(0));
             } else {
@@ -73,9 +110,7 @@ fn detect_uncompressed_text_file_kind<R: Read>(input: R) -> Result<(FileKind, im
                     "@" => detect_type = Ok(FileKind::Alignment(AlignmentFlavor::SAM)),
                     "#" => detect_type = Ok(FileKind::Bed(0)),
                     _ => {
-                        detect_type = Ok(FileKind::Bed(
-                            line.trim_end().chars().filter(|&c| c == '\t').count() + 1,
-                        ));
+                        detect_type = Ok(detect_data_line_kind(&line));
                         break;
                     }
                 }
@@ -84,9 +119,7 @@ fn detect_uncompressed_text_file_kind<R: Read>(input: R) -> Result<(FileKind, im
             match detect_type {
                 Ok(FileKind::Bed(0)) => {
                     if !line.starts_with("#") {
-                        detect_type = Ok(FileKind::Bed(
-                            line.trim_end().chars().filter(|&c| c == '\t').count() + 1,
-                        ));
+                        detect_type = Ok(detect_data_line_kind(&line));
                         break;
                     }
                 }
@@ -100,9 +133,32 @@ fn detect_uncompressed_text_file_kind<R: Read>(input: R) -> Result<(FileKind, im
     Ok((detect_type?, Cursor::new(seen).chain(reader)))
 }
 
+/// The peak files can't be told from the BED files by the content, thus the extension is used as
+/// a hint when the number of columns matches
+fn detect_peak_file(kind: FileKind, path: &Path) -> FileKind {
+    let name = path.file_name().map_or_else(Default::default, |name| {
+        name.to_string_lossy()
+            .trim_end_matches(".gz")
+            .to_lowercase()
+    });
+    match kind {
+        FileKind::Bed(10) if name.ends_with(".narrowpeak") => FileKind::NarrowPeak,
+        FileKind::Bed(9) if name.ends_with(".broadpeak") => FileKind::BroadPeak,
+        kind => kind,
+    }
+}
+
 impl FileFormat {
     pub fn detect_file<P: AsRef<Path>>(p: P) -> Result<FileFormat> {
-        let input = File::open(p.as_ref())?;
+        let format = Self::detect_content(p.as_ref())?;
+        Ok(FileFormat {
+            kind: detect_peak_file(format.kind, p.as_ref()),
+            deflated: format.deflated,
+        })
+    }
+
+    fn detect_content(p: &Path) -> Result<FileFormat> {
+        let input = File::open(p)?;
 
         match detect_gzip(input)? {
             Ok(stream) => match detect_bam_or_cram(stream)? {
@@ -174,4 +230,40 @@ mod test {
         );
         Ok(())
     }
+
+    #[test]
+    fn test_peak_detect() {
+        use FileKind::*;
+        let detect = |columns, path: &str| detect_peak_file(Bed(columns), Path::new(path));
+        assert_eq!(detect(10, "data/ENCFF001.narrowPeak"), NarrowPeak);
+        assert_eq!(detect(10, "ENCFF001.narrowPeak.gz"), NarrowPeak);
+        assert_eq!(detect(9, "ENCFF002.broadPeak"), BroadPeak);
+        assert_eq!(detect(9, "ENCFF002.BROADPEAK.gz"), BroadPeak);
+        // The columns don't match the extension, so the file is kept as a plain BED file
+        assert_eq!(detect(6, "ENCFF001.narrowPeak"), Bed(6));
+        assert_eq!(detect(9, "ENCFF001.narrowPeak"), Bed(9));
+        assert_eq!(detect(10, "ENCFF002.broadPeak"), Bed(10));
+        assert_eq!(detect(10, "peaks.bed"), Bed(10));
+        assert_eq!(detect_peak_file(Vcf, Path::new("calls.narrowPeak")), Vcf);
+    }
+
+    #[test]
+    fn test_gff_flavor_detect() {
+        use FileKind::*;
+        use GffFlavor::*;
+        assert_eq!(
+            detect_data_line_kind(
+                "chr1\tHAVANA\tgene\t11869\t14409\t.\t+\t.\tID=gene0;Name=DDX11L1\n"
+            ),
+            Gff(GFF3)
+        );
+        assert_eq!(
+            detect_data_line_kind("chr1\tensembl\tCDS\t100\t200\t.\t-\t0\tgene_id \"ENSG1\"; transcript_id \"ENST1\";\n"),
+            Gff(GTF)
+        );
+        assert_eq!(
+            detect_data_line_kind("chr1\t100\t200\tpeak\t0\t+\t100\t200\t0,0,0\n"),
+            Bed(9)
+        );
+    }
 }
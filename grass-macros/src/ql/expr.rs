@@ -1,4 +1,4 @@
-use grass_formats::{FileFormat, FileKind};
+use grass_formats::{FileFormat, FileKind, GffFlavor};
 use quote::quote;
 use std::fmt::{Debug, Formatter, Result as FmtResult};
 use syn::{
@@ -13,11 +13,14 @@ use super::{open_impl, CodeGenerator, CodeGeneratorContext, Operator};
 /// Argument for an open expression
 pub(crate) struct OpenArgument {
     path: LitStr,
+    /// The region to read with the index, like `chr1:1000-2000`
+    region: Option<LitStr>,
+    mode: SortMode,
 }
 
 impl Debug for OpenArgument {
     fn fmt(&self, f: &mut Formatter) -> FmtResult {
-        write!(f, "path = {}", self.path.value())
+        write!(f, "path = {}, mode = {:?}", self.path.value(), self.mode)
     }
 }
 
@@ -25,14 +28,51 @@ impl Parse for OpenArgument {
     fn parse(input: ParseStream) -> Result<Self> {
         let inner;
         let _ = parenthesized!(inner in input);
-        Ok(OpenArgument {
-            path: inner.parse()?,
-        })
+        let path = inner.parse()?;
+        let mut region = None;
+        let mut mode = SortMode::Check;
+        if inner.parse::<Token![,]>().is_ok() {
+            if inner.peek(LitStr) {
+                region = Some(inner.parse()?);
+                if inner.parse::<Token![,]>().is_ok() {
+                    mode = inner.parse()?;
+                }
+            } else {
+                mode = inner.parse()?;
+            }
+        }
+        Ok(OpenArgument { path, region, mode })
+    }
+}
+
+/// How an opened file is made sorted
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum SortMode {
+    /// Verify the order as the records stream through, panic on the first out-of-order record
+    Check,
+    /// Sort the file if it's not sorted
+    Sort,
+    /// Trust the file is sorted without checking
+    AssumeSorted,
+}
+
+impl Parse for SortMode {
+    fn parse(input: ParseStream) -> Result<Self> {
+        let mode = input.parse::<Ident>()?;
+        match mode.to_string().as_str() {
+            "check" => Ok(SortMode::Check),
+            "sort" => Ok(SortMode::Sort),
+            "assume_sorted" => Ok(SortMode::AssumeSorted),
+            _ => Err(Error::new(
+                mode.span(),
+                "Unknown open mode, expecting check, sort or assume_sorted",
+            )),
+        }
     }
 }
 
 /// QueryExpr :=
-///      open(<StrLit>)
+///      open(<StrLit> [, <StrLit>] [, check | sort | assume_sorted])
 ///     intersect(<QueryExpr>, *)
 ///     <id>
 ///     <QueryExpr> | <Operator>
@@ -88,16 +128,87 @@ impl Parse for QueryExpr {
 impl CodeGenerator for QueryExpr {
     fn generate(&self, ctx: &mut CodeGeneratorContext) -> Result<Option<Ident>> {
         match self {
-            QueryExpr::Open(OpenArgument { path }) => {
+            QueryExpr::Open(OpenArgument { path, region, mode }) => {
                 let id = ctx.fresh_id();
+                let region = region.as_ref();
                 let code = match FileFormat::detect_file(path.value()) {
                     Ok(format) => match format.kind {
-                        FileKind::Bed(size) => {
-                            open_impl::generate_bed_open_code(&id, path, size, format.deflated)
+                        FileKind::Fasta if region.is_some() => {
+                            return Err(Error::new(
+                                path.span(),
+                                "Region queries are only supported on BAM, CRAM and bgzipped files",
+                            ));
+                        }
+                        FileKind::Bed(_)
+                        | FileKind::Gff(_)
+                        | FileKind::NarrowPeak
+                        | FileKind::BroadPeak
+                        | FileKind::BedGraph
+                        | FileKind::Vcf
+                            if region.is_some() && !format.deflated =>
+                        {
+                            return Err(Error::new(
+                                path.span(),
+                                "Region queries need a bgzipped file with a tabix index",
+                            ));
+                        }
+                        FileKind::Bed(size) => open_impl::generate_bed_open_code(
+                            &id,
+                            path,
+                            &open_impl::bed_record_type(size),
+                            format.deflated,
+                            region,
+                            *mode,
+                        ),
+                        FileKind::Gff(flavor) => open_impl::generate_bed_open_code(
+                            &id,
+                            path,
+                            match flavor {
+                                GffFlavor::GFF3 => "Gff3Record",
+                                GffFlavor::GTF => "GtfRecord",
+                            },
+                            format.deflated,
+                            region,
+                            *mode,
+                        ),
+                        FileKind::NarrowPeak => open_impl::generate_bed_open_code(
+                            &id,
+                            path,
+                            "NarrowPeak",
+                            format.deflated,
+                            region,
+                            *mode,
+                        ),
+                        FileKind::BroadPeak => open_impl::generate_bed_open_code(
+                            &id,
+                            path,
+                            "BroadPeak",
+                            format.deflated,
+                            region,
+                            *mode,
+                        ),
+                        FileKind::BedGraph => open_impl::generate_bed_open_code(
+                            &id,
+                            path,
+                            "BedGraph",
+                            format.deflated,
+                            region,
+                            *mode,
+                        ),
+                        FileKind::Alignment(_) => {
+                            open_impl::generate_xam_open_code(&id, path, region, *mode)
                         }
-                        FileKind::Alignment(_) => open_impl::generate_xam_open_code(&id, path),
-                        FileKind::Vcf => open_impl::generate_vcf_open_code(&id, path),
-                        _ => panic!("Unsupported file format"),
+                        // Read by htslib, unless a region is queried with the tabix index
+                        FileKind::Vcf if region.is_some() => open_impl::generate_bed_open_code(
+                            &id,
+                            path,
+                            "VcfLine",
+                            format.deflated,
+                            region,
+                            *mode,
+                        ),
+                        FileKind::Vcf => open_impl::generate_vcf_open_code(&id, path, *mode),
+                        FileKind::Fasta => open_impl::generate_fasta_open_code(&id, path),
                     },
                     Err(err) => {
                         return Err(Error::new(
@@ -173,7 +284,7 @@ impl CodeGenerator for QueryExpr {
             }
             QueryExpr::OpChain((expr, operator)) => {
                 if let Some(upstream_id) = expr.generate(ctx)? {
-                    Ok(Some(operator.apply(upstream_id, ctx)))
+                    Ok(Some(operator.apply(upstream_id, ctx)?))
                 } else {
                     Ok(None)
                 }
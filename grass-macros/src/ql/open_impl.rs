@@ -2,60 +2,141 @@ use proc_macro2::TokenStream as TokenStream2;
 use quote::quote;
 use syn::{Ident, LitStr};
 
+use super::expr::SortMode;
+
+/// Make the record stream created by `open_expr` sorted, in the way the mode describes.
+/// The line based records are sorted with the external sort, so they can be larger than the
+/// memory, the other records are sorted in memory.
+fn make_sorted(open_expr: TokenStream2, mode: SortMode, line_records: bool) -> TokenStream2 {
+    match mode {
+        SortMode::Check => quote! {{
+            use grass::algorithm::CheckSorted;
+            let input = #open_expr;
+            let checked = input.check_sorted();
+            let error = checked.error_slot();
+            grass::high_level_api::report_error(checked, error, |e| {
+                eprintln!("Warning: stopped reading the input. {}", e)
+            })
+        }},
+        SortMode::Sort if line_records => quote! {{
+            use grass::algorithm::SortByRegion;
+            let input = #open_expr;
+            let sorted = input.sort_by_region(gcl).unwrap();
+            let error = sorted.error_slot();
+            grass::high_level_api::report_error(sorted, error, |e| {
+                eprintln!("Warning: the sorted stream is incomplete. {}", e)
+            })
+        }},
+        SortMode::Sort => quote! {{
+            use grass::algorithm::CheckSorted;
+            let input = #open_expr;
+            input.sort_if_unsorted()
+        }},
+        SortMode::AssumeSorted => quote! {{
+            use grass::algorithm::AssumeSorted;
+            let input = #open_expr;
+            input.assume_sorted()
+        }},
+    }
+}
+
+/// The record type for a BED file with the given number of columns
+pub(super) fn bed_record_type(size: usize) -> String {
+    match size {
+        0..=3 => "Bed3".to_string(),
+        4..=6 | 12 => format!("Bed{}", size),
+        _ => "BedN".to_string(),
+    }
+}
+
 pub(super) fn generate_bed_open_code(
     id: &Ident,
     path: &LitStr,
-    size: usize,
+    rec_type_name: &str,
     compressed: bool,
+    region: Option<&LitStr>,
+    mode: SortMode,
 ) -> TokenStream2 {
-    // TODO : At this point we just assume everything is sorted, but this
-    // is not actually the case
-    let rec_type_id = Ident::new(format!("Bed{}", size).as_str(), path.span());
-    if !compressed {
+    let rec_type_id = Ident::new(rec_type_name, path.span());
+    let open_expr = if let Some(region) = region {
         quote! {
-              let #id = grass::high_level_api::get_global_chrom_list().with(|gcl| {
-                  use grass::LineRecordStreamExt;
-                  use grass::algorithm::AssumeSorted;
-
-                 std::fs::File::open(#path).unwrap().into_record_iter::<grass::records::#rec_type_id, _>(gcl).assume_sorted()
-          });
+            grass::tabix::TabixReader::open(#path).unwrap().fetch(#region).unwrap().into_record_iter::<grass::records::#rec_type_id, _>(gcl)
+        }
+    } else if !compressed {
+        quote! {
+            std::fs::File::open(#path).unwrap().into_record_iter::<grass::records::#rec_type_id, _>(gcl)
         }
     } else {
         quote! {
-            let #id = grass::high_level_api::get_global_chrom_list().with(|gcl| {
-                use grass::LineRecordStreamExt;
-                use grass::algorithm::AssumeSorted;
-
-                libflate::gzip::Decoder::new(std::fs::File::open(#path).unwrap()).unwrap().into_record_iter::<grass::records::#rec_type_id, _>(gcl).assume_sorted()
-            });
+            grass::high_level_api::open_gzip(#path).unwrap().into_record_iter::<grass::records::#rec_type_id, _>(gcl)
         }
+    };
+    let open_expr = quote! {{
+        let records = #open_expr;
+        let error = records.error_slot();
+        grass::high_level_api::report_error(records, error, |e| {
+            eprintln!("Warning: stopped reading {} at {}", #path, e)
+        })
+    }};
+    let sorted = make_sorted(open_expr, mode, true);
+    quote! {
+        let #id = grass::high_level_api::get_global_chrom_list().with(|gcl| {
+            use grass::LineRecordStreamExt;
+            #sorted
+        });
     }
 }
 
-pub(super) fn generate_xam_open_code(id: &Ident, path: &LitStr) -> TokenStream2 {
-    // TODO : At this point we just assume everything is sorted, but this
-    // is not actually the case
+pub(super) fn generate_xam_open_code(
+    id: &Ident,
+    path: &LitStr,
+    region: Option<&LitStr>,
+    mode: SortMode,
+) -> TokenStream2 {
     let bam_file_id = Ident::new(format!("{}_owned_hts_instance", id).as_str(), id.span());
+    let open_expr = match region {
+        Some(region) => quote! {{
+            let reads = grass::records::BAMRecord::fetch::<grass::chromset::LexicalChromSet>(&#bam_file_id, gcl.get_handle(), #region).unwrap();
+            let error = reads.error_slot();
+            grass::high_level_api::report_error(reads, error, |e| {
+                eprintln!("Warning: stopped reading the alignments of {}. {}", #path, e)
+            })
+        }},
+        None => quote! {
+            grass::records::BAMRecord::iter_of::<grass::chromset::LexicalChromSet>(&#bam_file_id, gcl.get_handle())
+        },
+    };
+    let sorted = make_sorted(open_expr, mode, false);
     quote! {
         let #bam_file_id = grass::records::BamFile::open(#path).unwrap();
         let #id = grass::high_level_api::get_global_chrom_list().with(|gcl| {
             use grass::ChromSet;
-            use grass::algorithm::AssumeSorted;
-            grass::records::BAMRecord::iter_of::<grass::chromset::LexicalChromSet>(&#bam_file_id, gcl.get_handle()).assume_sorted()
+            #sorted
         });
     }
 }
 
-pub(super) fn generate_vcf_open_code(id: &Ident, path: &LitStr) -> TokenStream2 {
-    // TODO : At this point we just assume everything is sorted, but this
-    // is not actually the case
-    let bam_file_id = Ident::new(format!("{}_owned_hts_instance", id).as_str(), id.span());
+pub(super) fn generate_vcf_open_code(id: &Ident, path: &LitStr, mode: SortMode) -> TokenStream2 {
+    let vcf_file_id = Ident::new(format!("{}_owned_hts_instance", id).as_str(), id.span());
+    let sorted = make_sorted(
+        quote! {
+            grass::records::VcfRecord::iter_of::<grass::chromset::LexicalChromSet>(&#vcf_file_id, gcl.get_handle())
+        },
+        mode,
+        false,
+    );
     quote! {
-        let #bam_file_id = grass::records::VcfFile::open(#path).unwrap();
+        let #vcf_file_id = grass::records::VcfFile::open(#path).unwrap();
         let #id = grass::high_level_api::get_global_chrom_list().with(|gcl| {
             use grass::ChromSet;
-            use grass::algorithm::AssumeSorted;
-            grass::records::VcfRecord::iter_of::<grass::chromset::LexicalChromSet>(&#bam_file_id, gcl.get_handle()).assume_sorted()
+            #sorted
         });
     }
 }
+
+/// A FASTA file isn't a record stream, it's opened as an indexed sequence source
+pub(super) fn generate_fasta_open_code(id: &Ident, path: &LitStr) -> TokenStream2 {
+    quote! {
+        let #id = grass::records::IndexedFasta::open(#path).unwrap();
+    }
+}
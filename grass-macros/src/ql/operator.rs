@@ -1,4 +1,4 @@
-use super::CodeGeneratorContext;
+use super::{CodeGenerator, CodeGeneratorContext, QueryExpr};
 use quote::quote;
 use std::fmt::{Debug, Formatter, Result as FmtResult};
 use syn::{
@@ -6,13 +6,15 @@ use syn::{
     parse::{Parse, ParseStream},
     punctuated::Punctuated,
     visit_mut::VisitMut,
-    Expr, Ident, LitInt, Result, Token,
+    Error, Expr, Ident, LitInt, Result, Token,
 };
 
 pub(crate) enum Operator {
     Where(Expr),
     Map(Expr),
     Invoke(Ident, Punctuated<Expr, Token![,]>),
+    /// get_fasta(<QueryExpr> [, stranded] [, tab])
+    GetFasta(Box<QueryExpr>, Vec<Ident>),
 }
 
 impl Debug for Operator {
@@ -65,8 +67,8 @@ impl VisitMut for ClosureRewriter {
 }
 
 impl Operator {
-    pub(crate) fn apply(&self, upstream: Ident, ctx: &mut CodeGeneratorContext) -> Ident {
-        match self {
+    pub(crate) fn apply(&self, upstream: Ident, ctx: &mut CodeGeneratorContext) -> Result<Ident> {
+        Ok(match self {
             Operator::Map(code) => {
                 let id = ctx.fresh_id();
                 let mut code = code.clone();
@@ -106,7 +108,33 @@ impl Operator {
                 ctx.append(code);
                 id
             }
-        }
+            Operator::GetFasta(fasta, options) => {
+                // The reference is borrowed, so that it can be used by more than one query
+                let fasta = match fasta.as_ref() {
+                    QueryExpr::VarRef(id) => id.clone(),
+                    expr => expr.generate(ctx)?.unwrap(),
+                };
+                let options = options
+                    .iter()
+                    .map(|option| match option.to_string().as_str() {
+                        "stranded" => Ok(quote! { .stranded() }),
+                        "tab" => Ok(quote! { .tab_separated() }),
+                        _ => Err(Error::new(
+                            option.span(),
+                            "Unknown get_fasta option, expecting stranded or tab",
+                        )),
+                    })
+                    .collect::<Result<Vec<_>>>()?;
+                let id = ctx.fresh_id();
+                ctx.append(quote! {
+                    let #id = {
+                        use grass::high_level_api::*;
+                        #upstream . get_fasta(&#fasta) #(#options)*
+                    };
+                });
+                id
+            }
+        })
     }
 }
 
@@ -125,6 +153,17 @@ impl Parse for Operator {
                     let _ = parenthesized!(inner in input);
                     return Ok(Operator::Map(inner.parse()?));
                 }
+                "get_fasta" => {
+                    let _: Ident = input.parse()?;
+                    let inner;
+                    let _ = parenthesized!(inner in input);
+                    let fasta = inner.parse()?;
+                    let mut options = vec![];
+                    while inner.parse::<Token![,]>().is_ok() && !inner.is_empty() {
+                        options.push(inner.parse()?);
+                    }
+                    return Ok(Operator::GetFasta(Box::new(fasta), options));
+                }
                 _ => {
                     let id = input.parse()?;
                     let inner;
@@ -0,0 +1,42 @@
+#[cfg(feature = "hts")]
+fn main() -> std::io::Result<()> {
+    use std::env::args;
+    use std::io::{stdout, BufWriter, Write};
+
+    use grass::chromset::{ChromSet, LexicalChromSet};
+    use grass::properties::{WithName, WithRegionCore, WithStrand};
+    use grass::records::{BAMRecord, BamFile};
+
+    let args: Vec<_> = args().skip(1).take(2).collect();
+    let min_mapq: u8 = args.get(1).map_or(30, |mapq| mapq.parse().unwrap());
+
+    let chromset = LexicalChromSet::new();
+    let bam_file = BamFile::open(&args[0]).unwrap();
+
+    let mut out = BufWriter::new(stdout());
+    for read in BAMRecord::iter_of::<LexicalChromSet>(&bam_file, chromset.get_handle())
+        .filter(|read| read.mapq() >= min_mapq && !read.is_duplicate())
+    {
+        let nm = read.tag(b"NM").and_then(|value| value.get_int(0));
+        let cigar: String = read.cigar().map(|op| op.to_string()).collect();
+        writeln!(
+            out,
+            "{}\t{}\t{}\t{}\t{}\t{}\t{}\tNM={}",
+            read.chrom().to_string(),
+            read.begin(),
+            read.end(),
+            read.name(),
+            read.mapq(),
+            read.strand().map_or(".", |strand| strand.as_str()),
+            cigar,
+            nm.map_or_else(|| ".".to_string(), |nm| nm.to_string()),
+        )?;
+    }
+
+    Ok(())
+}
+
+#[cfg(not(feature = "hts"))]
+fn main() -> ! {
+    panic!("Please enable d4-hts feature to use HTSLIB support");
+}
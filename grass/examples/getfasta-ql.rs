@@ -0,0 +1,5 @@
+grass::grass_query! {
+    let genome = open("data/genome.fa");
+    let peaks = open("data/peaks.narrowPeak");
+    peaks | get_fasta(genome) | where(_0.sequence.to_string().contains("TATA")) | show_all();
+}
@@ -0,0 +1,43 @@
+#[cfg(feature = "hts")]
+fn main() -> std::io::Result<()> {
+    use std::env::args;
+    use std::fs::File;
+    use std::io::{BufWriter, Write};
+
+    use grass::algorithm::{AssumeSorted, SortedIntersect};
+    use grass::chromset::{ChromSet, LexicalChromRef, LexicalChromSet};
+    use grass::properties::Serializable;
+    use grass::records::{BAMRecord, BamFile, Bed3};
+    use grass::LineRecordStreamExt;
+    let args: Vec<_> = args().skip(1).take(3).collect();
+
+    let chromset = LexicalChromSet::new();
+
+    // The panel is small, so it's loaded into memory to be used both as the query regions of
+    // the index and as the left side of the intersection
+    let panel: Vec<_> = File::open(&args[0])?
+        .into_record_iter::<Bed3<LexicalChromRef>, _>(&chromset)
+        .collect();
+
+    let bam_file = BamFile::open(&args[1]).unwrap();
+    let reads = BAMRecord::query_regions::<LexicalChromSet, _, _>(
+        &bam_file,
+        chromset.get_handle(),
+        panel.iter().assume_sorted(),
+    )?;
+
+    let mut out_file = BufWriter::new(File::create(&args[2])?);
+
+    for pair in panel.iter().assume_sorted().sorted_intersect(reads) {
+        let result = Bed3::new(&pair);
+        result.dump(&mut out_file)?;
+        out_file.write_all(b"\n")?;
+    }
+
+    Ok(())
+}
+
+#[cfg(not(feature = "hts"))]
+fn main() -> ! {
+    panic!("Please enable d4-hts feature to use HTSLIB support");
+}
@@ -0,0 +1,4 @@
+grass::grass_query! {
+    let peaks = open("data/peaks.narrowPeak");
+    peaks | where(_0.q_value() > 2.0) | show_all();
+}
@@ -44,9 +44,9 @@ impl<C: ChromName, T: WithRegion<C>> RegionHeap<C, T> {
 
     fn adjust_up(&mut self, mut node: usize) {
         let node_key = self.data[node].end();
-        while node > 0 && self.data[node / 2].end() > node_key {
-            self.data.swap(node / 2, node);
-            node /= 2;
+        while node > 0 && self.data[(node - 1) / 2].end() > node_key {
+            self.data.swap((node - 1) / 2, node);
+            node = (node - 1) / 2;
         }
     }
 
@@ -76,3 +76,28 @@ impl<C: ChromName, T: WithRegion<C>> RegionHeap<C, T> {
         self.data.len()
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{chromset::LexicalChromRef, records::Bed3, ChromSet, ChromSetHandle};
+
+    #[test]
+    fn test_region_heap_order() {
+        let chroms = crate::LexicalChromSet::new();
+        let mut handle = chroms.get_handle();
+        let chrom = handle.query_or_insert("chr1");
+        let mut heap = RegionHeap::<LexicalChromRef, Bed3<LexicalChromRef>>::default();
+        // The parent of the node n is (n - 1) / 2, taking n / 2 as the parent breaks the order
+        // of this sequence
+        for end in [4, 2, 22, 3, 37, 10, 8, 17, 33, 32].iter() {
+            heap.push(Bed3 {
+                chrom: chrom.clone(),
+                begin: 0,
+                end: *end,
+            });
+        }
+        let ends: Vec<_> = std::iter::from_fn(|| heap.pop()).map(|r| r.end).collect();
+        assert_eq!(ends, vec![2, 3, 4, 8, 10, 17, 22, 32, 33, 37]);
+    }
+}
@@ -41,11 +41,31 @@ where
         }
     }
 
+    fn has_active_on(&self, chrom: &C) -> bool {
+        self.active_regions
+            .as_slice()
+            .iter()
+            .any(|region| region.chrom() == chrom)
+    }
+
     fn peek(&self) -> Option<&I::Item> {
         self.peek_buffer.as_ref()
     }
 
     fn remove_inactive_regions(&mut self, chrom: &C, active_limit: u32) {
+        // The heap is keyed by the end position, so the regions left on the previous chromosome
+        // aren't necessarily on the top
+        if self
+            .active_regions
+            .as_slice()
+            .iter()
+            .any(|r| r.chrom() < chrom)
+        {
+            let regions = std::mem::take(&mut self.active_regions.data);
+            for region in regions.into_iter().filter(|r| r.chrom() >= chrom) {
+                self.active_regions.push(region);
+            }
+        }
         while let Some(top) = self.active_regions.peek() {
             if top.chrom() < chrom || top.end() <= active_limit {
                 self.active_regions.pop();
@@ -215,15 +235,31 @@ where
                 };
 
                 match chrom_cmp {
+                    // The other side has moved to the next chromosome, but its active regions
+                    // may still overlap the regions remaining on this chromosome
+                    std::cmp::Ordering::Less
+                        if self
+                            .context_b
+                            .has_active_on(peek_a.as_ref().unwrap().chrom()) =>
+                    {
+                        break (peek_a.map(|x| x.begin()), None);
+                    }
+                    std::cmp::Ordering::Greater
+                        if self
+                            .context_a
+                            .has_active_on(peek_b.as_ref().unwrap().chrom()) =>
+                    {
+                        break (None, peek_b.map(|x| x.begin()));
+                    }
                     std::cmp::Ordering::Less => {
                         self.context_a
-                            .skip_util_chrom(&peek_b.as_ref().unwrap().chrom());
+                            .skip_util_chrom(peek_b.as_ref().unwrap().chrom());
                         self.context_a.frontier.clear();
                         self.context_a.active_regions.data.clear();
                     }
                     std::cmp::Ordering::Greater => {
                         self.context_b
-                            .skip_util_chrom(&peek_a.as_ref().unwrap().chrom());
+                            .skip_util_chrom(peek_a.as_ref().unwrap().chrom());
                         self.context_b.frontier.clear();
                         self.context_b.active_regions.data.clear();
                     }
@@ -233,18 +269,23 @@ where
                 }
             };
 
-            self.state =
-                if frontier_a.unwrap_or(std::u32::MAX) <= frontier_b.unwrap_or(std::u32::MAX) {
-                    let frontier = self.context_a.push_frontier()?;
-                    self.context_b
-                        .ingest_active_regions(&self.context_a.frontier[0].chrom(), frontier);
-                    State::FrontierA(0, 0, None)
-                } else {
-                    let frontier = self.context_b.push_frontier()?;
-                    self.context_a
-                        .ingest_active_regions(&self.context_b.frontier[0].chrom(), frontier);
-                    State::FrontierB(0, 0, None)
-                };
+            // The side without a frontier doesn't push, even if the other one begins at u32::MAX
+            let push_a = match (frontier_a, frontier_b) {
+                (Some(frontier_a), Some(frontier_b)) => frontier_a <= frontier_b,
+                (frontier_a, _) => frontier_a.is_some(),
+            };
+
+            self.state = if push_a {
+                let frontier = self.context_a.push_frontier()?;
+                self.context_b
+                    .ingest_active_regions(self.context_a.frontier[0].chrom(), frontier);
+                State::FrontierA(0, 0, None)
+            } else {
+                let frontier = self.context_b.push_frontier()?;
+                self.context_a
+                    .ingest_active_regions(self.context_b.frontier[0].chrom(), frontier);
+                State::FrontierB(0, 0, None)
+            };
         }
     }
 }
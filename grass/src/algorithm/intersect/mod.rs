@@ -2,13 +2,25 @@ mod heap;
 mod inner;
 mod outer;
 
-use crate::properties::WithRegion;
-use crate::{algorithm::markers::Sorted, ChromName};
+use crate::properties::{WithBlocks, WithRegion, WithStrand};
+use crate::{
+    algorithm::{
+        markers::Sorted,
+        strand::{StrandMode, StrandedIntersectIter},
+    },
+    ChromName,
+};
 
 use inner::{Context, State};
 
 pub use inner::SortedIntersectIter;
 
+/// The intersection that only keeps the pairs with overlapping blocks
+pub type BlockIntersectIter<C, A, B> = std::iter::Filter<
+    SortedIntersectIter<C, A, B>,
+    fn(&(<A as Iterator>::Item, <B as Iterator>::Item)) -> bool,
+>;
+
 pub trait SortedIntersect: Iterator + Sorted + Sized {
     fn sorted_intersect<
         C: ChromName,
@@ -28,6 +40,40 @@ pub trait SortedIntersect: Iterator + Sorted + Sized {
         }
     }
 
+    /// Intersect the records that are on the matching strands only, the pairs on the other
+    /// strands are never produced.
+    fn sorted_intersect_with_strand<
+        C: ChromName,
+        U: WithRegion<C> + WithStrand + Clone,
+        Other: Iterator<Item = U> + Sorted,
+    >(
+        self,
+        other: Other,
+        mode: StrandMode,
+    ) -> StrandedIntersectIter<C, Self, Other>
+    where
+        Self::Item: WithRegion<C> + WithStrand + Clone,
+    {
+        StrandedIntersectIter::new(self, other, mode)
+    }
+
+    /// Intersect the records by their blocks, the pairs that only overlap in the gaps between
+    /// the blocks, such as two transcripts that share an intron only, are dropped.
+    fn sorted_intersect_blocks<
+        C: ChromName,
+        U: WithRegion<C> + WithBlocks<C> + Clone,
+        Other: Iterator<Item = U> + Sorted,
+    >(
+        self,
+        other: Other,
+    ) -> BlockIntersectIter<C, Self, Other>
+    where
+        Self::Item: WithRegion<C> + WithBlocks<C> + Clone,
+    {
+        let overlaps: fn(&(Self::Item, U)) -> bool = |(a, b)| a.overlaps_blocks(b);
+        self.sorted_intersect(other).filter(overlaps)
+    }
+
     fn sorted_left_outer_intersect<
         C: ChromName,
         U: WithRegion<C> + Clone,
@@ -44,3 +90,115 @@ pub trait SortedIntersect: Iterator + Sorted + Sized {
 }
 
 impl<I: Iterator + Sorted> SortedIntersect for I {}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        algorithm::AssumeSorted,
+        properties::{WithName, WithRegionCore},
+        records::{Bed12, Bed3, Bed4},
+        LexicalChromSet, LineRecordStreamExt,
+    };
+
+    #[test]
+    fn test_intersect_across_chrom_boundary() {
+        let chroms = LexicalChromSet::new();
+        let a: &[u8] = b"chr1\t10\t20\nchr1\t30\t40\nchr2\t0\t10\n";
+        let b: &[u8] = b"chr1\t0\t100\nchr2\t5\t6\n";
+        let a = a.into_record_iter::<Bed3, _>(&chroms).assume_sorted();
+        let b = b.into_record_iter::<Bed3, _>(&chroms).assume_sorted();
+        let result: Vec<_> = a
+            .sorted_intersect(b)
+            .map(|(a, b)| (a.begin(), b.begin()))
+            .collect();
+        assert_eq!(result, vec![(10, 0), (30, 0), (0, 5)]);
+    }
+
+    #[test]
+    fn test_intersect_drops_regions_of_previous_chrom() {
+        let chroms = LexicalChromSet::new();
+        // Both sides move to chr2 at once, so chr1 0-600 is still active in B when the shorter
+        // chr2 10-50 becomes the top of the heap
+        let a: &[u8] = b"chr1\t0\t1000\nchr2\t10\t20\n";
+        let b: &[u8] = b"chr1\t0\t600\nchr2\t10\t50\n";
+        let a = a.into_record_iter::<Bed3, _>(&chroms).assume_sorted();
+        let b = b.into_record_iter::<Bed3, _>(&chroms).assume_sorted();
+        let result: Vec<_> = a
+            .sorted_intersect(b)
+            .map(|(a, b)| (a.begin(), b.begin(), b.end()))
+            .collect();
+        assert_eq!(result, vec![(0, 0, 600), (10, 10, 50)]);
+    }
+
+    #[test]
+    fn test_intersect_active_regions_of_previous_chrom() {
+        let chroms = LexicalChromSet::new();
+        // The next record of the long side is on chr2 already, while its chr1 0-100 still
+        // overlaps the rest of the short side
+        let long: &[u8] = b"chr1\t0\t100\nchr2\t0\t10\n";
+        let short: &[u8] = b"chr1\t10\t20\nchr1\t50\t60\n";
+
+        let a = long.into_record_iter::<Bed3, _>(&chroms).assume_sorted();
+        let b = short.into_record_iter::<Bed3, _>(&chroms).assume_sorted();
+        let result: Vec<_> = a
+            .sorted_intersect(b)
+            .map(|(a, b)| (a.begin(), b.begin()))
+            .collect();
+        assert_eq!(result, vec![(0, 10), (0, 50)]);
+
+        let a = short.into_record_iter::<Bed3, _>(&chroms).assume_sorted();
+        let b = long.into_record_iter::<Bed3, _>(&chroms).assume_sorted();
+        let result: Vec<_> = a
+            .sorted_intersect(b)
+            .map(|(a, b)| (a.begin(), b.begin()))
+            .collect();
+        assert_eq!(result, vec![(10, 0), (50, 0)]);
+    }
+
+    #[test]
+    fn test_intersect_blocks() {
+        let chroms = LexicalChromSet::new();
+        let a: &[u8] = b"chr1\t100\t200\ttx1\t0\t+\t100\t200\t0\t2\t20,30,\t0,70,\n";
+        let b: &[u8] = b"chr1\t120\t130\tin_intron\nchr1\t150\t180\tin_exon\n";
+        let a = a.into_record_iter::<Bed12, _>(&chroms).assume_sorted();
+        let b = b.into_record_iter::<Bed4, _>(&chroms).assume_sorted();
+        let result: Vec<_> = a
+            .sorted_intersect_blocks(b)
+            .map(|(_, b)| b.name().to_owned())
+            .collect();
+        assert_eq!(result, vec!["in_exon"]);
+    }
+
+    #[test]
+    fn test_left_outer_intersect() {
+        use crate::ChromName;
+
+        let chroms = LexicalChromSet::new();
+        let a: &[u8] = b"chr1\t0\t100\nchr1\t10\t20\nchr1\t150\t160\nchr2\t0\t10\n";
+        let b: &[u8] = b"chr1\t50\t200\nchr1\t90\t95\nchr2\t10\t20\n";
+        let a = a.into_record_iter::<Bed3, _>(&chroms).assume_sorted();
+        let b = b.into_record_iter::<Bed3, _>(&chroms).assume_sorted();
+        let mut result: Vec<_> = a
+            .sorted_left_outer_intersect(b)
+            .map(|(a, b)| {
+                let chrom = ChromName::to_string(a.chrom()).into_owned();
+                (chrom, a.begin(), b.map(|b| b.begin()))
+            })
+            .collect();
+        result.sort();
+        // The B records beginning after the end of A are not paired with it, even if an
+        // earlier and longer A record overlaps them. The touching regions don't overlap.
+        let chr = |name: &str| name.to_string();
+        assert_eq!(
+            result,
+            vec![
+                (chr("chr1"), 0, Some(50)),
+                (chr("chr1"), 0, Some(90)),
+                (chr("chr1"), 10, None),
+                (chr("chr1"), 150, Some(50)),
+                (chr("chr2"), 0, None),
+            ]
+        );
+    }
+}
@@ -21,6 +21,8 @@ where
     current_a: Option<IA::Item>,
     current_b: Option<IB::Item>,
     current_b_idx: usize,
+    /// If the current A record has been paired with any B record
+    has_overlap: bool,
 }
 
 impl<C, IA, IB> LeftOuterJoinIter<C, IA, IB>
@@ -42,6 +44,7 @@ where
             current_a: None,
             current_b,
             current_b_idx: 0,
+            has_overlap: false,
         };
         ret.read_next_a();
         ret
@@ -60,21 +63,24 @@ where
         self.limit = self.limit.max(cur_a.end());
 
         while let Some(ref b) = self.current_b {
-            if Some(b.chrom()) > self.current_chrom.as_ref() || self.limit <= b.end() {
+            if Some(b.chrom()) > self.current_chrom.as_ref() || self.limit <= b.begin() {
                 break;
             }
-            self.active_regions.push(self.current_b.take().unwrap());
+            if Some(b.chrom()) == self.current_chrom.as_ref() {
+                self.active_regions.push(self.current_b.take().unwrap());
+            }
             self.current_b = self.iter_b.next();
         }
 
         while let Some(top) = self.active_regions.peek() {
-            if top.end() < cur_a.begin() {
+            if top.end() <= cur_a.begin() {
                 self.active_regions.pop();
             } else {
                 break;
             }
         }
         self.current_b_idx = 0;
+        self.has_overlap = false;
         Some(())
     }
 }
@@ -92,14 +98,17 @@ where
     fn next(&mut self) -> Option<Self::Item> {
         loop {
             let cur_a = self.current_a.as_ref()?;
-            if self.current_b_idx < self.active_regions.data.len() {
-                self.current_b_idx += 1;
-                return Some((
-                    cur_a.clone(),
-                    Some(self.active_regions.data[self.current_b_idx - 1].clone()),
-                ));
-            } else if self.current_b_idx == 0 && self.active_regions.data.len() == 0 {
+            // The active regions end after the A record begins, but the ones read for a longer
+            // A record before may begin after this one ends
+            while let Some(b) = self.active_regions.data.get(self.current_b_idx) {
                 self.current_b_idx += 1;
+                if b.begin() < cur_a.end() {
+                    self.has_overlap = true;
+                    return Some((cur_a.clone(), Some(b.clone())));
+                }
+            }
+            if !self.has_overlap {
+                self.has_overlap = true;
                 return Some((cur_a.clone(), None));
             }
             self.read_next_a()?;
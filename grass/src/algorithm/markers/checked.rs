@@ -0,0 +1,173 @@
+use std::{
+    cell::RefCell,
+    error::Error,
+    fmt::{Display, Formatter, Result as FmtResult},
+    marker::PhantomData,
+    rc::Rc,
+};
+
+use super::{AssumeSorted, AssumingSortedIter, Sorted};
+use crate::properties::{WithRegion, WithRegionCore};
+use crate::ChromName;
+
+/// The error reported when a stream that should be sorted has an out-of-order record
+#[derive(Debug, Clone)]
+pub struct UnsortedError {
+    /// The 0-based index of the out-of-order record in the stream
+    pub index: usize,
+    /// The position (chrom, begin) of the record right before the out-of-order one
+    pub previous: (String, u32),
+    /// The position (chrom, begin) of the out-of-order record
+    pub current: (String, u32),
+}
+
+impl Display for UnsortedError {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        write!(
+            f,
+            "Input is not sorted: record #{} at {}:{} comes after {}:{}",
+            self.index, self.current.0, self.current.1, self.previous.0, self.previous.1
+        )
+    }
+}
+
+impl Error for UnsortedError {}
+
+/// The error that stops a checked stream, it's shared with the stream so that it can be checked
+/// after the stream is consumed
+pub type UnsortedErrorSlot = Rc<RefCell<Option<UnsortedError>>>;
+
+/// A sorted stream that validates the order against the `ChromSet` ordering as the records
+/// stream through.
+///
+/// Note: As an iterator, it ends at the first out-of-order record and keeps the error in the
+/// `error_slot()`, use `try_next` to handle the error instead.
+pub struct CheckedSortedIter<C: ChromName, I: Iterator>
+where
+    I::Item: WithRegion<C>,
+{
+    inner: I,
+    last: Option<(C, u32)>,
+    index: usize,
+    error: UnsortedErrorSlot,
+    failed: bool,
+    _p: PhantomData<C>,
+}
+
+impl<C: ChromName, I: Iterator> CheckedSortedIter<C, I>
+where
+    I::Item: WithRegion<C>,
+{
+    pub fn try_next(&mut self) -> Result<Option<I::Item>, UnsortedError> {
+        let item = match self.inner.next() {
+            Some(item) => item,
+            None => return Ok(None),
+        };
+
+        let pos = (item.chrom().clone(), item.begin());
+        if let Some(last) = self.last.as_ref() {
+            if &pos < last {
+                return Err(UnsortedError {
+                    index: self.index,
+                    previous: (last.0.to_string().into_owned(), last.1),
+                    current: (pos.0.to_string().into_owned(), pos.1),
+                });
+            }
+        }
+
+        self.last = Some(pos);
+        self.index += 1;
+        Ok(Some(item))
+    }
+
+    /// The slot of the error that stops the stream when it's used as an iterator
+    pub fn error_slot(&self) -> UnsortedErrorSlot {
+        self.error.clone()
+    }
+}
+
+impl<C: ChromName, I: Iterator> Iterator for CheckedSortedIter<C, I>
+where
+    I::Item: WithRegion<C>,
+{
+    type Item = I::Item;
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.failed {
+            return None;
+        }
+        match self.try_next() {
+            Ok(item) => item,
+            Err(e) => {
+                *self.error.borrow_mut() = Some(e);
+                self.failed = true;
+                None
+            }
+        }
+    }
+}
+
+impl<C: ChromName, I: Iterator> Sorted for CheckedSortedIter<C, I> where I::Item: WithRegion<C> {}
+
+pub trait CheckSorted: Iterator + Sized {
+    /// Verify the stream is sorted while iterating over it
+    fn check_sorted<C: ChromName>(self) -> CheckedSortedIter<C, Self>
+    where
+        Self::Item: WithRegion<C>,
+    {
+        CheckedSortedIter {
+            inner: self,
+            last: None,
+            index: 0,
+            error: Default::default(),
+            failed: false,
+            _p: PhantomData,
+        }
+    }
+
+    /// Load all the records and sort them in memory unless they are already sorted.
+    ///
+    /// This is for the records that can't be written to a temporary file, such as the BAM and
+    /// VCF records. The line based records should use `SortByRegion`, which spills to the disk
+    /// when the input doesn't fit in the memory.
+    fn sort_if_unsorted<C: ChromName>(self) -> AssumingSortedIter<std::vec::IntoIter<Self::Item>>
+    where
+        Self::Item: WithRegion<C>,
+    {
+        let mut records: Vec<_> = self.collect();
+        let is_sorted = records
+            .windows(2)
+            .all(|w| (w[0].chrom(), w[0].begin()) <= (w[1].chrom(), w[1].begin()));
+        if !is_sorted {
+            records.sort_by(|a, b| (a.chrom(), a.begin()).cmp(&(b.chrom(), b.begin())));
+        }
+        records.into_iter().assume_sorted()
+    }
+}
+
+impl<T: Iterator> CheckSorted for T {}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{records::Bed3, LexicalChromSet, LineRecordStreamExt};
+
+    #[test]
+    fn test_checked_sorted_iter() {
+        let chroms = LexicalChromSet::new();
+        let input: &[u8] = b"chr1\t10\t20\nchr1\t30\t40\nchr1\t5\t8\nchr2\t1\t2\n";
+        let mut checked = input.into_record_iter::<Bed3, _>(&chroms).check_sorted();
+        let error = checked.error_slot();
+        assert_eq!(checked.by_ref().count(), 2);
+        assert!(checked.next().is_none());
+
+        let error = error.borrow_mut().take().unwrap();
+        assert_eq!((error.index, error.current.1, error.previous.1), (2, 5, 30));
+
+        let sorted: Vec<_> = input
+            .into_record_iter::<Bed3, _>(&chroms)
+            .sort_if_unsorted()
+            .map(|r| r.begin())
+            .collect();
+        assert_eq!(sorted, vec![5, 10, 30, 1]);
+    }
+}
@@ -1,2 +1,5 @@
 mod sorted;
 pub use sorted::{AssumeSorted, AssumingSortedIter, Sorted};
+
+mod checked;
+pub use checked::{CheckSorted, CheckedSortedIter, UnsortedError, UnsortedErrorSlot};
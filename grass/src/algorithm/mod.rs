@@ -1,8 +1,27 @@
 mod intersect;
-pub use intersect::{SortedIntersect, SortedIntersectIter};
+pub use intersect::{BlockIntersectIter, SortedIntersect, SortedIntersectIter};
 
 mod markers;
-pub use markers::{AssumeSorted, AssumingSortedIter, Sorted};
+pub use markers::{
+    AssumeSorted, AssumingSortedIter, CheckSorted, CheckedSortedIter, Sorted, UnsortedError,
+    UnsortedErrorSlot,
+};
 
 mod components;
 pub use components::{Components, ComponentsIter, Point, TaggedComponent, TaggedComponentExt};
+
+mod sort;
+pub use sort::{RegionSortedIter, SortByRegion, SortConfig, SortErrorSlot};
+
+mod strand;
+pub(crate) use strand::{strand_slot, STRANDS};
+pub use strand::{StrandMode, StrandedIntersectIter};
+
+mod parallel;
+pub use parallel::{
+    run_by_chrom, split_by_chrom, zip_by_chrom, ByChromIter, ChromPartition, ChromPartitions,
+    PairedPartition, PairedPartitions, ParallelConfig, ZipByChrom,
+};
+
+mod split;
+pub use split::{SplitBlock, SplitBlocks, SplitBlocksIter};
@@ -0,0 +1,250 @@
+use std::{
+    cmp::Ordering,
+    collections::HashMap,
+    iter::Peekable,
+    marker::PhantomData,
+    panic::{self, AssertUnwindSafe},
+    sync::{mpsc, Arc, Mutex},
+    thread::{self, JoinHandle},
+    vec,
+};
+
+use super::Sorted;
+use crate::{
+    properties::{WithRegion, WithRegionCore},
+    ChromName,
+};
+
+/// The options of the per-chromosome parallel execution
+#[derive(Clone, Debug)]
+pub struct ParallelConfig {
+    /// The number of worker threads, the number of available cores is used by default
+    pub num_threads: usize,
+}
+
+impl Default for ParallelConfig {
+    fn default() -> Self {
+        Self {
+            num_threads: thread::available_parallelism().map_or(1, |n| n.get()),
+        }
+    }
+}
+
+/// The records of a single chromosome
+pub struct ChromPartition<C, T> {
+    pub chrom: C,
+    pub records: T,
+}
+
+/// The records of both inputs on a single chromosome
+pub type PairedPartition<C, A, B> = ChromPartition<C, (Vec<A>, Vec<B>)>;
+
+/// The partitions of a sorted stream split at the chromosome boundaries, which are read one
+/// chromosome at a time
+pub struct ChromPartitions<C, I: Iterator> {
+    iter: I,
+    /// The first record of the next chromosome
+    head: Option<I::Item>,
+    _p: PhantomData<C>,
+}
+
+impl<C, I> Iterator for ChromPartitions<C, I>
+where
+    C: ChromName,
+    I: Iterator,
+    I::Item: WithRegion<C>,
+{
+    type Item = ChromPartition<C, Vec<I::Item>>;
+    fn next(&mut self) -> Option<Self::Item> {
+        let first = self.head.take().or_else(|| self.iter.next())?;
+        let chrom = first.chrom().clone();
+        let mut records = vec![first];
+        for record in self.iter.by_ref() {
+            if record.chrom() != &chrom {
+                self.head = Some(record);
+                break;
+            }
+            records.push(record);
+        }
+        Some(ChromPartition { chrom, records })
+    }
+}
+
+/// Split a sorted stream at the chromosome boundaries, the partitions are in the `ChromSet`
+/// order.
+pub fn split_by_chrom<C, I>(iter: I) -> ChromPartitions<C, I>
+where
+    C: ChromName,
+    I: Iterator + Sorted,
+    I::Item: WithRegion<C>,
+{
+    ChromPartitions {
+        iter,
+        head: None,
+        _p: PhantomData,
+    }
+}
+
+/// The partitions of two inputs paired up by chromosome
+pub struct ZipByChrom<A: Iterator, B: Iterator> {
+    a: Peekable<A>,
+    b: Peekable<B>,
+}
+
+/// The partitions of two sorted streams paired up by chromosome
+pub type PairedPartitions<C, A, B> = ZipByChrom<ChromPartitions<C, A>, ChromPartitions<C, B>>;
+
+impl<C, A, B, RA, RB> Iterator for ZipByChrom<A, B>
+where
+    C: ChromName,
+    A: Iterator<Item = ChromPartition<C, Vec<RA>>>,
+    B: Iterator<Item = ChromPartition<C, Vec<RB>>>,
+{
+    type Item = PairedPartition<C, RA, RB>;
+    fn next(&mut self) -> Option<Self::Item> {
+        let ordering = match (self.a.peek(), self.b.peek()) {
+            (Some(pa), Some(pb)) => pa.chrom.cmp(&pb.chrom),
+            (Some(_), None) => Ordering::Less,
+            (None, Some(_)) => Ordering::Greater,
+            (None, None) => return None,
+        };
+        let (chrom, records) = match ordering {
+            Ordering::Less => {
+                let pa = self.a.next().unwrap();
+                (pa.chrom, (pa.records, vec![]))
+            }
+            Ordering::Greater => {
+                let pb = self.b.next().unwrap();
+                (pb.chrom, (vec![], pb.records))
+            }
+            Ordering::Equal => {
+                let (pa, pb) = (self.a.next().unwrap(), self.b.next().unwrap());
+                (pa.chrom, (pa.records, pb.records))
+            }
+        };
+        Some(ChromPartition { chrom, records })
+    }
+}
+
+/// Pair up the partitions of two inputs by chromosome, the chromosome that only presents in one
+/// of the inputs is paired with an empty partition.
+pub fn zip_by_chrom<A: Iterator, B: Iterator>(a: A, b: B) -> ZipByChrom<A, B> {
+    ZipByChrom {
+        a: a.peekable(),
+        b: b.peekable(),
+    }
+}
+
+/// The results of a per-chromosome parallel execution, concatenated in the order of the
+/// partitions.
+///
+/// The partitions are read as the results are consumed, and at most two partitions per worker
+/// thread are in flight, so the input isn't loaded into the memory at once.
+pub struct ByChromIter<P: Iterator, R> {
+    partitions: P,
+    jobs: Option<mpsc::Sender<(usize, P::Item)>>,
+    results: mpsc::Receiver<(usize, thread::Result<Vec<R>>)>,
+    /// The results that are finished before the results of the partitions before them
+    finished: HashMap<usize, Vec<R>>,
+    current: vec::IntoIter<R>,
+    submitted: usize,
+    emitted: usize,
+    max_in_flight: usize,
+    workers: Vec<JoinHandle<()>>,
+}
+
+impl<P: Iterator, R> Iterator for ByChromIter<P, R> {
+    type Item = R;
+    fn next(&mut self) -> Option<R> {
+        loop {
+            if let Some(item) = self.current.next() {
+                return Some(item);
+            }
+
+            while self.submitted - self.emitted < self.max_in_flight {
+                let partition = match self.partitions.next() {
+                    Some(partition) => partition,
+                    None => break,
+                };
+                let jobs = self.jobs.as_ref()?;
+                jobs.send((self.submitted, partition)).ok()?;
+                self.submitted += 1;
+            }
+
+            if self.emitted == self.submitted {
+                return None;
+            }
+
+            let result = loop {
+                if let Some(result) = self.finished.remove(&self.emitted) {
+                    break result;
+                }
+                match self.results.recv().ok()? {
+                    (idx, Ok(result)) => {
+                        self.finished.insert(idx, result);
+                    }
+                    (_, Err(panic)) => panic::resume_unwind(panic),
+                }
+            };
+            self.current = result.into_iter();
+            self.emitted += 1;
+        }
+    }
+}
+
+impl<P: Iterator, R> Sorted for ByChromIter<P, R> {}
+
+impl<P: Iterator, R> Drop for ByChromIter<P, R> {
+    fn drop(&mut self) {
+        // The workers stop once the queue is closed and the jobs in flight are done
+        self.jobs.take();
+        for worker in self.workers.drain(..) {
+            let _ = worker.join();
+        }
+    }
+}
+
+/// Process each partition on a pool of worker threads and concatenate the results in the order
+/// of the partitions.
+pub fn run_by_chrom<P, R, F>(partitions: P, config: &ParallelConfig, f: F) -> ByChromIter<P, R>
+where
+    P: Iterator,
+    P::Item: Send + 'static,
+    R: Send + 'static,
+    F: Fn(P::Item) -> Vec<R> + Send + Sync + 'static,
+{
+    let num_threads = config.num_threads.max(1);
+    let (jobs, queue) = mpsc::channel::<(usize, P::Item)>();
+    let (sender, results) = mpsc::channel();
+    let queue = Arc::new(Mutex::new(queue));
+    let f = Arc::new(f);
+
+    let workers = (0..num_threads)
+        .map(|_| {
+            let (queue, sender, f) = (queue.clone(), sender.clone(), f.clone());
+            thread::spawn(move || loop {
+                let job = queue.lock().unwrap().recv();
+                let (idx, partition) = match job {
+                    Ok(job) => job,
+                    Err(_) => break,
+                };
+                let result = panic::catch_unwind(AssertUnwindSafe(|| f(partition)));
+                if sender.send((idx, result)).is_err() {
+                    break;
+                }
+            })
+        })
+        .collect();
+
+    ByChromIter {
+        partitions,
+        jobs: Some(jobs),
+        results,
+        finished: HashMap::new(),
+        current: Vec::new().into_iter(),
+        submitted: 0,
+        emitted: 0,
+        max_in_flight: num_threads * 2,
+        workers,
+    }
+}
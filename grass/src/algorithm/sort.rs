@@ -0,0 +1,413 @@
+use std::{
+    cmp::Ordering,
+    collections::BinaryHeap,
+    fs::File,
+    io::{BufWriter, Error, ErrorKind, Result, Seek, SeekFrom, Write},
+    marker::PhantomData,
+    path::PathBuf,
+    rc::Rc,
+};
+
+use super::Sorted;
+use crate::{
+    file::{ErrorSlot, LineRecord, LineRecordStream},
+    properties::{Serializable, WithRegion},
+    ChromSet, LineRecordStreamExt,
+};
+
+/// The options of the external sort
+#[derive(Clone, Debug)]
+pub struct SortConfig {
+    /// The estimated number of bytes of the records held in memory, once it's exceeded the
+    /// buffered records are sorted and spilled to a temporary file
+    pub max_memory: usize,
+    /// The maximum number of spilled files merged at once, when there are this many files of the
+    /// same level, they are merged into a single file of the next level, so that the number of
+    /// the open files stays logarithmic
+    pub max_merge_width: usize,
+    /// The directory for the temporary files, the system temporary directory is used if it's
+    /// not specified
+    pub temp_dir: Option<PathBuf>,
+}
+
+impl Default for SortConfig {
+    fn default() -> Self {
+        Self {
+            max_memory: 256 << 20,
+            max_merge_width: 64,
+            temp_dir: None,
+        }
+    }
+}
+
+/// Counts the bytes written to it, this is how the size of a record is estimated
+#[derive(Default)]
+struct ByteCounter(usize);
+
+impl Write for ByteCounter {
+    fn write(&mut self, buf: &[u8]) -> Result<usize> {
+        self.0 += buf.len();
+        Ok(buf.len())
+    }
+    fn flush(&mut self) -> Result<()> {
+        Ok(())
+    }
+}
+
+/// The estimated memory used by a record: its own size plus the size of its text form, which
+/// covers the heap allocated fields like the name
+fn record_size<T: Serializable>(record: &T) -> usize {
+    let mut counter = ByteCounter::default();
+    record.dump(&mut counter).ok();
+    std::mem::size_of::<T>() + counter.0
+}
+
+/// A sorted run, either the last one that is still in memory or a spilled one
+enum Run<S: ChromSet, T> {
+    Memory(std::vec::IntoIter<T>),
+    Spilled(LineRecordStream<S, File, T>, ErrorSlot),
+}
+
+impl<S: ChromSet, T: LineRecord<S>> Run<S, T> {
+    fn spilled(file: File, chrom_set: &S) -> Self {
+        let records = file.into_record_iter(chrom_set);
+        let error = records.error_slot();
+        Self::Spilled(records, error)
+    }
+
+    fn next(&mut self) -> Option<T> {
+        match self {
+            Self::Memory(inner) => inner.next(),
+            Self::Spilled(inner, _) => inner.next(),
+        }
+    }
+
+    /// The error that stops the run, if there's one
+    fn take_error(&mut self) -> Option<Error> {
+        match self {
+            Self::Memory(_) => None,
+            Self::Spilled(_, error) => error.borrow_mut().take().map(|e| {
+                Error::new(
+                    ErrorKind::InvalidData,
+                    format!("Unable to read a spilled sort run: {}", e),
+                )
+            }),
+        }
+    }
+}
+
+/// The head record of a run in the merge heap, ordered by position and then the run index,
+/// so that the sort is stable
+struct HeapEntry<S: ChromSet, T> {
+    record: T,
+    run: usize,
+    _p: PhantomData<S>,
+}
+
+impl<S: ChromSet, T: WithRegion<S::RefType>> HeapEntry<S, T> {
+    fn key(&self) -> (&S::RefType, u32, usize) {
+        (self.record.chrom(), self.record.begin(), self.run)
+    }
+}
+
+impl<S: ChromSet, T: WithRegion<S::RefType>> PartialEq for HeapEntry<S, T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.key() == other.key()
+    }
+}
+
+impl<S: ChromSet, T: WithRegion<S::RefType>> Eq for HeapEntry<S, T> {}
+
+impl<S: ChromSet, T: WithRegion<S::RefType>> PartialOrd for HeapEntry<S, T> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<S: ChromSet, T: WithRegion<S::RefType>> Ord for HeapEntry<S, T> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        // BinaryHeap is a max heap
+        other.key().cmp(&self.key())
+    }
+}
+
+/// The slot of the I/O error that stops a `RegionSortedIter`, it's shared with the iterator so
+/// that it can be checked after the iterator is consumed
+pub type SortErrorSlot = Rc<std::cell::RefCell<Option<Error>>>;
+
+/// The records sorted by `sort_by_region`, which merges all the sorted runs.
+///
+/// Note: If a spilled run can't be read back, the iterator ends and the error is kept in the
+/// `error_slot()`.
+pub struct RegionSortedIter<S: ChromSet, T> {
+    runs: Vec<Run<S, T>>,
+    heap: BinaryHeap<HeapEntry<S, T>>,
+    /// The error of a run found after its last record is taken, it's returned on the next read
+    pending: Option<Error>,
+    error: SortErrorSlot,
+    failed: bool,
+}
+
+impl<S: ChromSet, T: LineRecord<S> + WithRegion<S::RefType>> RegionSortedIter<S, T> {
+    fn new(runs: Vec<Run<S, T>>) -> Result<Self> {
+        let mut ret = Self {
+            runs,
+            heap: BinaryHeap::new(),
+            pending: None,
+            error: Default::default(),
+            failed: false,
+        };
+        for run in 0..ret.runs.len() {
+            ret.fill(run)?;
+        }
+        Ok(ret)
+    }
+
+    fn fill(&mut self, run: usize) -> Result<()> {
+        match self.runs[run].next() {
+            Some(record) => self.heap.push(HeapEntry {
+                record,
+                run,
+                _p: PhantomData,
+            }),
+            None => {
+                if let Some(e) = self.runs[run].take_error() {
+                    return Err(e);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Take the next record, the error of the spilled runs is returned rather than kept
+    fn try_next(&mut self) -> Result<Option<T>> {
+        if let Some(e) = self.pending.take() {
+            return Err(e);
+        }
+        let HeapEntry { record, run, .. } = match self.heap.pop() {
+            Some(entry) => entry,
+            None => return Ok(None),
+        };
+        self.pending = self.fill(run).err();
+        Ok(Some(record))
+    }
+
+    /// The slot of the error that stops the iterator
+    pub fn error_slot(&self) -> SortErrorSlot {
+        self.error.clone()
+    }
+}
+
+impl<S: ChromSet, T: LineRecord<S> + WithRegion<S::RefType>> Iterator for RegionSortedIter<S, T> {
+    type Item = T;
+    fn next(&mut self) -> Option<T> {
+        if self.failed {
+            return None;
+        }
+        match self.try_next() {
+            Ok(record) => record,
+            Err(e) => {
+                *self.error.borrow_mut() = Some(e);
+                self.failed = true;
+                None
+            }
+        }
+    }
+}
+
+impl<S: ChromSet, T: LineRecord<S> + WithRegion<S::RefType>> Sorted for RegionSortedIter<S, T> {}
+
+fn sort_buffer<S: ChromSet, T: WithRegion<S::RefType>>(buffer: &mut [T]) {
+    buffer.sort_by(|a, b| (a.chrom(), a.begin()).cmp(&(b.chrom(), b.begin())));
+}
+
+fn temp_file(config: &SortConfig) -> Result<File> {
+    match config.temp_dir.as_ref() {
+        Some(dir) => tempfile::tempfile_in(dir),
+        None => tempfile::tempfile(),
+    }
+}
+
+/// Write the sorted records to a temporary file and open it as a run
+fn write_run<S, T, I>(records: I, chrom_set: &S, config: &SortConfig) -> Result<Run<S, T>>
+where
+    S: ChromSet,
+    T: LineRecord<S> + Serializable,
+    I: IntoIterator<Item = Result<T>>,
+{
+    let mut writer = BufWriter::new(temp_file(config)?);
+    for record in records {
+        record?.dump(&mut writer)?;
+        writer.write_all(b"\n")?;
+    }
+    let mut file = writer.into_inner().map_err(|e| e.into_error())?;
+    file.seek(SeekFrom::Start(0))?;
+
+    Ok(Run::spilled(file, chrom_set))
+}
+
+fn spill<S, T>(buffer: &mut Vec<T>, chrom_set: &S, config: &SortConfig) -> Result<Run<S, T>>
+where
+    S: ChromSet,
+    T: LineRecord<S> + WithRegion<S::RefType> + Serializable,
+{
+    sort_buffer::<S, T>(buffer);
+    write_run(buffer.drain(..).map(Ok), chrom_set, config)
+}
+
+/// Merge the spilled runs into a single one, so that we don't keep too many files open
+fn merge_runs<S, T>(runs: Vec<Run<S, T>>, chrom_set: &S, config: &SortConfig) -> Result<Run<S, T>>
+where
+    S: ChromSet,
+    T: LineRecord<S> + WithRegion<S::RefType> + Serializable,
+{
+    let mut merged = RegionSortedIter::new(runs)?;
+    write_run(
+        std::iter::from_fn(|| merged.try_next().transpose()),
+        chrom_set,
+        config,
+    )
+}
+
+pub trait SortByRegion: Iterator + Sized {
+    /// Sort the records in the `ChromSet` order with the default config. The records are read
+    /// from the spilled files with the given chrom set.
+    fn sort_by_region<S: ChromSet>(self, chrom_set: &S) -> Result<RegionSortedIter<S, Self::Item>>
+    where
+        Self::Item: LineRecord<S> + Serializable + WithRegion<S::RefType>,
+    {
+        self.sort_by_region_with(chrom_set, &SortConfig::default())
+    }
+
+    /// Sort the records in the `ChromSet` order, when the records take more memory than the
+    /// budget, the sorted runs are spilled to temporary files and merged afterwards.
+    fn sort_by_region_with<S: ChromSet>(
+        self,
+        chrom_set: &S,
+        config: &SortConfig,
+    ) -> Result<RegionSortedIter<S, Self::Item>>
+    where
+        Self::Item: LineRecord<S> + Serializable + WithRegion<S::RefType>,
+    {
+        let merge_width = config.max_merge_width.max(2);
+        // The spilled runs with their levels, a run of level n is merged from the runs of
+        // level n - 1, so the levels never increase from the oldest run to the newest one
+        let mut runs: Vec<(usize, Run<S, Self::Item>)> = vec![];
+        let mut buffer = vec![];
+        let mut buffer_size = 0;
+
+        for record in self {
+            buffer_size += record_size(&record);
+            buffer.push(record);
+            if buffer_size >= config.max_memory {
+                runs.push((0, spill(&mut buffer, chrom_set, config)?));
+                buffer_size = 0;
+                // Only the newest runs of the same level are merged, thus every record is
+                // rewritten once per level rather than on every merge
+                while runs.len() >= merge_width {
+                    let first = runs.len() - merge_width;
+                    let level = runs[first].0;
+                    if runs[runs.len() - 1].0 != level {
+                        break;
+                    }
+                    let batch = runs.drain(first..).map(|(_, run)| run).collect();
+                    runs.push((level + 1, merge_runs(batch, chrom_set, config)?));
+                }
+            }
+        }
+
+        sort_buffer::<S, _>(&mut buffer);
+        let runs = runs
+            .into_iter()
+            .map(|(_, run)| run)
+            .chain(std::iter::once(Run::Memory(buffer.into_iter())))
+            .collect();
+
+        RegionSortedIter::new(runs)
+    }
+}
+
+impl<T: Iterator> SortByRegion for T {}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        chromset::LexicalChromRef, properties::WithRegionCore, records::Bed3, ChromName,
+        LexicalChromSet,
+    };
+
+    #[test]
+    fn test_external_sort() -> Result<()> {
+        let chroms = LexicalChromSet::new();
+        let input: &[u8] = b"chr2\t5\t10\nchr1\t30\t40\nchr1\t10\t20\nchr2\t1\t3\nchr1\t10\t15\n";
+        let records: LineRecordStream<_, _, Bed3<LexicalChromRef>> =
+            input.into_record_iter(&chroms);
+        // Every record is spilled, and every two runs are merged into one
+        let config = SortConfig {
+            max_memory: 1,
+            max_merge_width: 2,
+            temp_dir: None,
+        };
+
+        let sorted: Vec<_> = records
+            .sort_by_region_with(&chroms, &config)?
+            .map(|r| {
+                (
+                    ChromName::to_string(r.chrom()).into_owned(),
+                    r.begin(),
+                    r.end(),
+                )
+            })
+            .collect();
+
+        assert_eq!(
+            sorted,
+            vec![
+                ("chr1".to_string(), 10, 20),
+                ("chr1".to_string(), 10, 15),
+                ("chr1".to_string(), 30, 40),
+                ("chr2".to_string(), 1, 3),
+                ("chr2".to_string(), 5, 10),
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_merge_levels() -> Result<()> {
+        let chroms = LexicalChromSet::new();
+        let input: String = (0..100u32)
+            .map(|idx| format!("chr1\t{}\t{}\n", (idx * 37) % 100, idx))
+            .collect();
+        let records: LineRecordStream<_, _, Bed3<LexicalChromRef>> =
+            input.as_bytes().into_record_iter(&chroms);
+        let config = SortConfig {
+            max_memory: 1,
+            max_merge_width: 3,
+            temp_dir: None,
+        };
+
+        let sorted = records.sort_by_region_with(&chroms, &config)?;
+        // 100 is 10201 in base 3, so a run is left for each nonzero digit, plus the memory run
+        assert_eq!(sorted.runs.len(), 5);
+        let begins: Vec<_> = sorted.map(|r| r.begin()).collect();
+        assert_eq!(begins, (0..100).collect::<Vec<_>>());
+        Ok(())
+    }
+
+    #[test]
+    fn test_spilled_run_error() -> Result<()> {
+        let chroms = LexicalChromSet::new();
+        let mut file = tempfile::tempfile()?;
+        file.write_all(b"chr1\t10\t20\nchr1\tx\t30\n")?;
+        file.seek(SeekFrom::Start(0))?;
+
+        let run = Run::<_, Bed3<LexicalChromRef>>::spilled(file, &chroms);
+        let mut sorted = RegionSortedIter::new(vec![run])?;
+        let error = sorted.error_slot();
+        assert_eq!(sorted.by_ref().count(), 1);
+        assert!(error.borrow().is_some());
+        Ok(())
+    }
+}
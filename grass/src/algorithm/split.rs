@@ -0,0 +1,225 @@
+use std::{
+    cmp::{Ordering, Reverse},
+    collections::BinaryHeap,
+    io::{Result, Write},
+    iter::Peekable,
+    marker::PhantomData,
+};
+
+use super::Sorted;
+use crate::{
+    properties::{
+        Serializable, Strand, StrandColumn, WithBlocks, WithName, WithRegion, WithRegionCore,
+        WithStrand,
+    },
+    records::Bed3,
+    ChromName,
+};
+
+/// A block of a record, such as an exon of a transcript or an aligned part of a spliced read,
+/// along with the record it comes from
+#[derive(Clone)]
+pub struct SplitBlock<C: ChromName, T> {
+    pub core: Bed3<C>,
+    pub parent: T,
+}
+
+impl<C: ChromName, T> WithRegionCore<C> for SplitBlock<C, T> {
+    fn begin(&self) -> u32 {
+        self.core.begin()
+    }
+
+    fn end(&self) -> u32 {
+        self.core.end()
+    }
+
+    fn chrom(&self) -> &C {
+        self.core.chrom()
+    }
+}
+
+impl<C: ChromName, T: WithName> WithName for SplitBlock<C, T> {
+    fn name(&self) -> &str {
+        self.parent.name()
+    }
+}
+
+impl<C: ChromName, T: WithStrand> WithStrand for SplitBlock<C, T> {
+    fn strand(&self) -> Option<Strand> {
+        self.parent.strand()
+    }
+}
+
+impl<C: ChromName, T> WithBlocks<C> for SplitBlock<C, T> {}
+
+/// The block is written as BED6, with the name and the strand of the parent
+impl<C: ChromName, T: WithName + WithStrand> Serializable for SplitBlock<C, T> {
+    fn dump<W: Write>(&self, mut fp: W) -> Result<()> {
+        self.core.dump(&mut fp)?;
+        let strand = StrandColumn::from(self.strand());
+        write!(fp, "\t{}\t.\t{}", self.name(), strand)
+    }
+}
+
+/// The blocks waiting to be emitted, ordered by the region and then the order they are made
+struct PendingBlock<C: ChromName, T> {
+    block: SplitBlock<C, T>,
+    seq: usize,
+}
+
+impl<C: ChromName, T> PendingBlock<C, T> {
+    fn key(&self) -> (u32, u32, usize) {
+        (self.block.begin(), self.block.end(), self.seq)
+    }
+}
+
+impl<C: ChromName, T> PartialEq for PendingBlock<C, T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.key() == other.key()
+    }
+}
+
+impl<C: ChromName, T> Eq for PendingBlock<C, T> {}
+
+impl<C: ChromName, T> PartialOrd for PendingBlock<C, T> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<C: ChromName, T> Ord for PendingBlock<C, T> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.key().cmp(&other.key())
+    }
+}
+
+/// The blocks of a sorted stream of records, which is also sorted.
+///
+/// The blocks of a record may begin after the records that follow it, such as the second exon
+/// of a long transcript, so the blocks are buffered until no later record can begin before them.
+pub struct SplitBlocksIter<C: ChromName, I: Iterator, F> {
+    iter: Peekable<I>,
+    blocks_of: F,
+    pending: BinaryHeap<Reverse<PendingBlock<C, I::Item>>>,
+    next_seq: usize,
+    _phantom: PhantomData<C>,
+}
+
+impl<C, I, F> Iterator for SplitBlocksIter<C, I, F>
+where
+    C: ChromName,
+    I: Iterator,
+    I::Item: WithRegionCore<C> + Clone,
+    F: FnMut(&I::Item) -> Vec<(u32, u32)>,
+{
+    type Item = SplitBlock<C, I::Item>;
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let ready = match (self.pending.peek(), self.iter.peek()) {
+                (None, None) => return None,
+                (Some(_), None) => true,
+                (None, Some(_)) => false,
+                (Some(Reverse(first)), Some(next)) => {
+                    first.block.chrom() != next.chrom() || first.block.begin() < next.begin()
+                }
+            };
+            if ready {
+                return self.pending.pop().map(|Reverse(pending)| pending.block);
+            }
+
+            let parent = self.iter.next().unwrap();
+            for (begin, end) in (self.blocks_of)(&parent) {
+                if begin >= end {
+                    continue;
+                }
+                let block = SplitBlock {
+                    core: Bed3 {
+                        chrom: parent.chrom().clone(),
+                        begin,
+                        end,
+                    },
+                    parent: parent.clone(),
+                };
+                self.pending.push(Reverse(PendingBlock {
+                    block,
+                    seq: self.next_seq,
+                }));
+                self.next_seq += 1;
+            }
+        }
+    }
+}
+
+impl<C, I, F> Sorted for SplitBlocksIter<C, I, F>
+where
+    C: ChromName,
+    I: Iterator + Sorted,
+    I::Item: WithRegionCore<C> + Clone,
+    F: FnMut(&I::Item) -> Vec<(u32, u32)>,
+{
+}
+
+type BlockRegionsFn<T> = fn(&T) -> Vec<(u32, u32)>;
+
+/// Expand the records into their blocks, like the `-split` option of bedtools
+pub trait SplitBlocks: Iterator + Sorted + Sized {
+    /// Split the records by `WithBlocks::block_regions`, such as the exons of BED12 records or
+    /// the aligned parts of the reads
+    fn split_blocks<C: ChromName>(self) -> SplitBlocksIter<C, Self, BlockRegionsFn<Self::Item>>
+    where
+        Self::Item: WithRegion<C> + WithBlocks<C> + Clone,
+    {
+        self.split_blocks_by(<Self::Item as WithBlocks<C>>::block_regions)
+    }
+
+    /// Split the records by the blocks that the function returns, the blocks of a record must
+    /// be sorted and inside the record
+    fn split_blocks_by<C: ChromName, F>(self, blocks_of: F) -> SplitBlocksIter<C, Self, F>
+    where
+        Self::Item: WithRegion<C> + Clone,
+        F: FnMut(&Self::Item) -> Vec<(u32, u32)>,
+    {
+        SplitBlocksIter {
+            iter: self.peekable(),
+            blocks_of,
+            pending: BinaryHeap::new(),
+            next_seq: 0,
+            _phantom: PhantomData,
+        }
+    }
+}
+
+impl<I: Iterator + Sorted> SplitBlocks for I {}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{algorithm::AssumeSorted, records::Bed12, LexicalChromSet, LineRecordStreamExt};
+
+    #[test]
+    fn test_split_blocks() {
+        let chroms = LexicalChromSet::new();
+        let input: &[u8] = b"chr1\t100\t1000\ttx1\t0\t-\t100\t1000\t0\t2\t100,100,\t0,800,\n\
+            chr1\t150\t400\ttx2\t0\t+\t150\t400\t0\t1\t250,\t0,\n\
+            chr2\t0\t10\ttx3\t0\t+\t0\t10\t0\t1\t10,\t0,\n";
+        let blocks: Vec<_> = input
+            .into_record_iter::<Bed12, _>(&chroms)
+            .assume_sorted()
+            .split_blocks()
+            .map(|block| {
+                let mut buf = vec![];
+                block.dump(&mut buf).unwrap();
+                String::from_utf8(buf).unwrap()
+            })
+            .collect();
+        assert_eq!(
+            blocks,
+            vec![
+                "chr1\t100\t200\ttx1\t.\t-",
+                "chr1\t150\t400\ttx2\t.\t+",
+                "chr1\t900\t1000\ttx1\t.\t-",
+                "chr2\t0\t10\ttx3\t.\t+",
+            ]
+        );
+    }
+}
@@ -0,0 +1,414 @@
+use std::{collections::VecDeque, iter::Peekable};
+
+use super::Sorted;
+use crate::{
+    properties::{Strand, WithRegion, WithRegionCore, WithStrand},
+    ChromName,
+};
+
+/// How the strands of two records are matched, like the `-s` and `-S` options of bedtools
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StrandMode {
+    /// Only the records on the same strand are matched, the records with unknown strand only
+    /// match each other
+    Same,
+    /// Only the records on the opposite strands are matched, the records with unknown strand
+    /// don't match anything
+    Opposite,
+}
+
+/// The slots of the strands in the sweeps, `None` is the unknown strand
+pub(crate) const STRANDS: [Option<Strand>; 3] = [Some(Strand::Pos), Some(Strand::Neg), None];
+
+pub(crate) fn strand_slot(strand: Option<Strand>) -> usize {
+    match strand {
+        Some(Strand::Pos) => 0,
+        Some(Strand::Neg) => 1,
+        None => 2,
+    }
+}
+
+impl StrandMode {
+    /// Check if a record of A on the strand matches a record of B on the other strand
+    pub fn matches(&self, strand: Option<Strand>, other: Option<Strand>) -> bool {
+        match self {
+            StrandMode::Same => strand == other,
+            StrandMode::Opposite => match (strand, other) {
+                (Some(strand), Some(other)) => strand != other,
+                _ => false,
+            },
+        }
+    }
+
+    /// The slot of the strand that matches the strand in the slot, the mode is symmetric so
+    /// this works from either side
+    pub(crate) fn matching_slot(&self, slot: usize) -> Option<usize> {
+        (0..STRANDS.len()).find(|other| self.matches(STRANDS[slot], STRANDS[*other]))
+    }
+}
+
+/// The intersection of two sorted streams that only pairs up the records on the matching
+/// strands.
+///
+/// The streams are swept together by the begin of the records, reading a single record ahead
+/// on each side. A record read is paired with the records of the other side that are still
+/// active on the matching strand, thus the pairs come out in the order of their overlap begin.
+pub struct StrandedIntersectIter<C: ChromName, A: Iterator, B: Iterator> {
+    iter_a: Peekable<A>,
+    iter_b: Peekable<B>,
+    mode: StrandMode,
+    chrom: Option<C>,
+    /// The records that may still overlap the records not read yet, by the strand slot
+    active_a: [Vec<A::Item>; 3],
+    active_b: [Vec<B::Item>; 3],
+    /// The pairs of the last record read
+    pairs: VecDeque<(A::Item, B::Item)>,
+}
+
+impl<C: ChromName, A: Iterator, B: Iterator> StrandedIntersectIter<C, A, B>
+where
+    A::Item: WithRegion<C> + WithStrand + Clone,
+    B::Item: WithRegion<C> + WithStrand + Clone,
+{
+    pub(crate) fn new(iter_a: A, iter_b: B, mode: StrandMode) -> Self {
+        Self {
+            iter_a: iter_a.peekable(),
+            iter_b: iter_b.peekable(),
+            mode,
+            chrom: None,
+            active_a: Default::default(),
+            active_b: Default::default(),
+            pairs: VecDeque::new(),
+        }
+    }
+
+    /// Move the sweep to the record, the active records that end before it are dropped
+    fn enter(&mut self, chrom: &C, pos: u32) {
+        if self.chrom.as_ref() != Some(chrom) {
+            self.chrom = Some(chrom.clone());
+            self.active_a.iter_mut().for_each(Vec::clear);
+            self.active_b.iter_mut().for_each(Vec::clear);
+        }
+        for active in self.active_a.iter_mut() {
+            active.retain(|item| item.end() > pos);
+        }
+        for active in self.active_b.iter_mut() {
+            active.retain(|item| item.end() > pos);
+        }
+    }
+}
+
+impl<C: ChromName, A: Iterator, B: Iterator> Iterator for StrandedIntersectIter<C, A, B>
+where
+    A::Item: WithRegion<C> + WithStrand + Clone,
+    B::Item: WithRegion<C> + WithStrand + Clone,
+{
+    type Item = (A::Item, B::Item);
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(pair) = self.pairs.pop_front() {
+                return Some(pair);
+            }
+            let take_a = match (self.iter_a.peek(), self.iter_b.peek()) {
+                (Some(a), Some(b)) => (a.chrom(), a.begin()) <= (b.chrom(), b.begin()),
+                (Some(_), None) => true,
+                (None, Some(_)) => false,
+                (None, None) => return None,
+            };
+            if take_a {
+                let item = self.iter_a.next().unwrap();
+                self.enter(item.chrom(), item.begin());
+                let slot = strand_slot(item.strand());
+                if let Some(other) = self.mode.matching_slot(slot) {
+                    for b in self.active_b[other].iter() {
+                        if item.overlaps(b) {
+                            self.pairs.push_back((item.clone(), b.clone()));
+                        }
+                    }
+                    self.active_a[slot].push(item);
+                }
+            } else {
+                let item = self.iter_b.next().unwrap();
+                self.enter(item.chrom(), item.begin());
+                let slot = strand_slot(item.strand());
+                if let Some(other) = self.mode.matching_slot(slot) {
+                    for a in self.active_a[other].iter() {
+                        if a.overlaps(&item) {
+                            self.pairs.push_back((a.clone(), item.clone()));
+                        }
+                    }
+                    self.active_b[slot].push(item);
+                }
+            }
+        }
+    }
+}
+
+impl<C: ChromName, A: Iterator, B: Iterator> Sorted for StrandedIntersectIter<C, A, B>
+where
+    A::Item: WithRegion<C> + WithStrand + Clone,
+    B::Item: WithRegion<C> + WithStrand + Clone,
+{
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        algorithm::{AssumeSorted, SortedIntersect},
+        properties::WithName,
+        records::Bed6,
+        LexicalChromSet, LineRecordStreamExt,
+    };
+
+    const A: &[u8] = b"chr1\t10\t20\ta1\t0\t+\nchr1\t15\t30\ta2\t0\t-\nchr1\t40\t50\ta3\t0\t.\n";
+    const B: &[u8] = b"chr1\t12\t45\tb1\t0\t-\nchr1\t18\t19\tb2\t0\t+\nchr1\t40\t41\tb3\t0\t.\n";
+
+    #[test]
+    fn test_stranded_intersect() {
+        let chroms = LexicalChromSet::new();
+        let intersect = |mode| -> Vec<(String, String)> {
+            let a = A.into_record_iter::<Bed6, _>(&chroms).assume_sorted();
+            let b = B.into_record_iter::<Bed6, _>(&chroms).assume_sorted();
+            a.sorted_intersect_with_strand(b, mode)
+                .map(|(a, b)| (a.name().to_owned(), b.name().to_owned()))
+                .collect()
+        };
+
+        let pairs = |pairs: &[(&str, &str)]| -> Vec<(String, String)> {
+            pairs
+                .iter()
+                .map(|(a, b)| (String::from(*a), String::from(*b)))
+                .collect()
+        };
+
+        assert_eq!(
+            intersect(StrandMode::Same),
+            pairs(&[("a2", "b1"), ("a1", "b2"), ("a3", "b3")])
+        );
+        assert_eq!(
+            intersect(StrandMode::Opposite),
+            pairs(&[("a1", "b1"), ("a2", "b2")])
+        );
+    }
+
+    #[test]
+    fn test_stranded_merge_and_subtract() {
+        use crate::high_level_api::{MergeExt, SubtractExt};
+
+        let chroms = LexicalChromSet::new();
+        let a = || A.into_record_iter::<Bed6, _>(&chroms).assume_sorted();
+        let b = || B.into_record_iter::<Bed6, _>(&chroms).assume_sorted();
+
+        let merged: Vec<_> = a()
+            .merge_overlaps_by_strand()
+            .map(|r| (r.begin(), r.end(), r.strand))
+            .collect();
+        assert_eq!(
+            merged,
+            vec![
+                (10, 20, Some(Strand::Pos)),
+                (15, 30, Some(Strand::Neg)),
+                (40, 50, None)
+            ]
+        );
+
+        let subtract = |mode| -> Vec<(u32, u32)> {
+            a().subtract_with_strand(b(), mode)
+                .map(|r| (r.begin(), r.end()))
+                .collect()
+        };
+        assert_eq!(
+            subtract(StrandMode::Same),
+            vec![(10, 18), (19, 20), (41, 50)]
+        );
+        assert_eq!(
+            subtract(StrandMode::Opposite),
+            vec![(10, 12), (15, 18), (19, 30), (40, 50)]
+        );
+    }
+
+    #[test]
+    fn test_stranded_streaming() {
+        use crate::high_level_api::{MergeExt, SubtractExt};
+        use std::cell::Cell;
+
+        // Nothing is on the unknown strand, so no strand may wait for the end of the input
+        let text: String = (0..1000)
+            .map(|idx| {
+                let strand = if idx % 2 == 0 { '+' } else { '-' };
+                format!("chr1\t{}\t{}\tr\t0\t{}\n", idx * 10, idx * 10 + 5, strand)
+            })
+            .collect();
+        let chroms = LexicalChromSet::new();
+        let read = Cell::new(0);
+        let input = || {
+            read.set(0);
+            text.as_bytes()
+                .into_record_iter::<Bed6, _>(&chroms)
+                .inspect(|_| read.set(read.get() + 1))
+                .assume_sorted()
+        };
+        let b = || B.into_record_iter::<Bed6, _>(&chroms).assume_sorted();
+
+        let mut intersect = input().sorted_intersect_with_strand(b(), StrandMode::Same);
+        assert_eq!(intersect.next().unwrap().0.begin(), 10);
+        assert!(read.get() < 10);
+        assert_eq!(intersect.count(), 1);
+        assert_eq!(read.get(), 1000);
+
+        let mut merged = input().merge_overlaps_by_strand();
+        assert_eq!(merged.next().unwrap().begin(), 0);
+        assert!(read.get() < 10);
+        assert_eq!(merged.count(), 999);
+
+        let mut subtract = input().subtract_with_strand(b(), StrandMode::Opposite);
+        assert_eq!(subtract.next().unwrap().1.begin, 0);
+        assert!(read.get() < 10);
+        assert_eq!(subtract.count(), 997);
+        assert_eq!(read.get(), 1000);
+    }
+
+    #[test]
+    fn test_stranded_random_regions() {
+        use crate::high_level_api::{MergeExt, SubtractExt};
+
+        let mut seed = 0x2545_f491_4f6c_dd1d_u64;
+        let mut rand = move |limit: u32| {
+            seed ^= seed << 13;
+            seed ^= seed >> 7;
+            seed ^= seed << 17;
+            (seed % limit as u64) as u32
+        };
+        let mut random_regions = |chroms: &[&'static str]| {
+            let mut regions = vec![];
+            for &chrom in chroms {
+                for _ in 0..rand(20) {
+                    let begin = rand(200);
+                    let strand = STRANDS[rand(3) as usize];
+                    regions.push((chrom, begin, begin + 1 + rand(30), strand));
+                }
+            }
+            regions.sort();
+            regions
+        };
+        let to_text = |regions: &[(&str, u32, u32, Option<Strand>)]| -> String {
+            regions
+                .iter()
+                .map(|(chrom, begin, end, strand)| {
+                    let strand = match strand {
+                        Some(Strand::Pos) => '+',
+                        Some(Strand::Neg) => '-',
+                        None => '.',
+                    };
+                    format!("{}\t{}\t{}\tr\t0\t{}\n", chrom, begin, end, strand)
+                })
+                .collect()
+        };
+        let key = |r: &Bed6| {
+            (
+                ChromName::to_string(r.chrom()).into_owned(),
+                r.begin(),
+                r.end(),
+                r.strand,
+            )
+        };
+
+        let chroms = LexicalChromSet::new();
+        for _ in 0..50 {
+            let a = random_regions(&["chr1", "chr2", "chr3"]);
+            let b = random_regions(&["chr2", "chr3", "chr4"]);
+            let (text_a, text_b) = (to_text(&a), to_text(&b));
+            let iter_a = || {
+                text_a
+                    .as_bytes()
+                    .into_record_iter::<Bed6, _>(&chroms)
+                    .assume_sorted()
+            };
+            let iter_b = || {
+                text_b
+                    .as_bytes()
+                    .into_record_iter::<Bed6, _>(&chroms)
+                    .assume_sorted()
+            };
+
+            for &mode in [StrandMode::Same, StrandMode::Opposite].iter() {
+                let result: Vec<_> = iter_a()
+                    .sorted_intersect_with_strand(iter_b(), mode)
+                    .map(|(a, b)| (key(&a), key(&b)))
+                    .collect();
+                assert!(result.windows(2).all(|w| {
+                    let ((chrom, a0, _, _), (_, b0, _, _)) = &w[0];
+                    let ((next_chrom, a1, _, _), (_, b1, _, _)) = &w[1];
+                    (chrom, a0.max(b0)) <= (next_chrom, a1.max(b1))
+                }));
+                let mut result = result;
+                result.sort();
+                let mut expected = vec![];
+                for a in iter_a() {
+                    for b in iter_b() {
+                        if a.overlaps(&b) && mode.matches(a.strand, b.strand) {
+                            expected.push((key(&a), key(&b)));
+                        }
+                    }
+                }
+                expected.sort();
+                assert_eq!(result, expected, "a = {:?}, b = {:?}", a, b);
+
+                let result: Vec<_> = iter_a()
+                    .subtract_with_strand(iter_b(), mode)
+                    .map(|(a, piece)| (key(&a), piece.begin, piece.end))
+                    .collect();
+                assert!(result
+                    .windows(2)
+                    .all(|w| (&w[0].0 .0, w[0].1) <= (&w[1].0 .0, w[1].1)));
+                let mut result = result;
+                result.sort();
+                let mut expected = vec![];
+                for a in iter_a() {
+                    let covered = |pos: u32| {
+                        iter_b().any(|b| {
+                            b.chrom() == a.chrom()
+                                && b.begin() <= pos
+                                && pos < b.end()
+                                && mode.matches(a.strand, b.strand)
+                        })
+                    };
+                    let mut pos = a.begin();
+                    while pos < a.end() {
+                        let piece_begin = pos;
+                        while pos < a.end() && !covered(pos) {
+                            pos += 1;
+                        }
+                        if piece_begin < pos {
+                            expected.push((key(&a), piece_begin, pos));
+                        }
+                        pos += 1;
+                    }
+                }
+                expected.sort();
+                assert_eq!(result, expected, "a = {:?}, b = {:?}", a, b);
+            }
+
+            let result: Vec<_> = iter_a()
+                .merge_overlaps_by_strand()
+                .map(|r| key(&r))
+                .collect();
+            assert!(result
+                .windows(2)
+                .all(|w| (&w[0].0, w[0].1) <= (&w[1].0, w[1].1)));
+            let mut result = result;
+            result.sort();
+            let mut expected = vec![];
+            for &strand in STRANDS.iter() {
+                let records: Vec<_> = iter_a().filter(|r| r.strand == strand).collect();
+                for merged in records.into_iter().assume_sorted().merge_overlaps() {
+                    let chrom = ChromName::to_string(&merged.chrom).into_owned();
+                    expected.push((chrom, merged.begin, merged.end, strand));
+                }
+            }
+            expected.sort();
+            assert_eq!(result, expected, "a = {:?}", a);
+        }
+    }
+}
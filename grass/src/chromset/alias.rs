@@ -0,0 +1,226 @@
+use std::{
+    collections::HashMap,
+    io::{BufRead, BufReader, Error, ErrorKind, Read, Result},
+    sync::Arc,
+};
+
+use super::{ChromSet, ChromSetHandle};
+
+/// The RefSeq and GenBank accessions of the human primary assembly, without the version
+const HUMAN_ACCESSIONS: &[(&str, &str, &str)] = &[
+    ("NC_000001", "CM000663", "1"),
+    ("NC_000002", "CM000664", "2"),
+    ("NC_000003", "CM000665", "3"),
+    ("NC_000004", "CM000666", "4"),
+    ("NC_000005", "CM000667", "5"),
+    ("NC_000006", "CM000668", "6"),
+    ("NC_000007", "CM000669", "7"),
+    ("NC_000008", "CM000670", "8"),
+    ("NC_000009", "CM000671", "9"),
+    ("NC_000010", "CM000672", "10"),
+    ("NC_000011", "CM000673", "11"),
+    ("NC_000012", "CM000674", "12"),
+    ("NC_000013", "CM000675", "13"),
+    ("NC_000014", "CM000676", "14"),
+    ("NC_000015", "CM000677", "15"),
+    ("NC_000016", "CM000678", "16"),
+    ("NC_000017", "CM000679", "17"),
+    ("NC_000018", "CM000680", "18"),
+    ("NC_000019", "CM000681", "19"),
+    ("NC_000020", "CM000682", "20"),
+    ("NC_000021", "CM000683", "21"),
+    ("NC_000022", "CM000684", "22"),
+    ("NC_000023", "CM000685", "X"),
+    ("NC_000024", "CM000686", "Y"),
+    ("NC_012920", "J01415", "MT"),
+];
+
+/// The spelling of the chromosome names that are recognized by the built-in mappings
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ChromNaming {
+    /// `chr1`, `chrX`, `chrM`
+    Ucsc,
+    /// `1`, `X`, `MT`
+    Ensembl,
+}
+
+/// The table that maps the chromosome names to their canonical spelling
+#[derive(Clone, Debug)]
+pub struct ChromAliases {
+    naming: Option<ChromNaming>,
+    table: HashMap<String, String>,
+}
+
+impl ChromAliases {
+    /// The built-in UCSC/Ensembl/GenBank mappings, the recognized names are spelled in the
+    /// given naming
+    pub fn builtin(naming: ChromNaming) -> Self {
+        Self {
+            naming: Some(naming),
+            table: HashMap::new(),
+        }
+    }
+
+    /// An alias table without any built-in mappings
+    pub fn empty() -> Self {
+        Self {
+            naming: None,
+            table: HashMap::new(),
+        }
+    }
+
+    /// Add an alias, the user supplied aliases take priority over the built-in mappings
+    pub fn with_alias<A: Into<String>, C: Into<String>>(mut self, alias: A, canonical: C) -> Self {
+        self.table.insert(alias.into(), canonical.into());
+        self
+    }
+
+    /// Load the aliases from a tab separated file, each line has the canonical name followed
+    /// by its aliases.
+    pub fn load_table<R: Read>(mut self, reader: R) -> Result<Self> {
+        for (line_no, line) in BufReader::new(reader).lines().enumerate() {
+            let line = line?;
+            if line.trim().is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let mut names = line.split('\t').map(str::trim).filter(|s| !s.is_empty());
+            let canonical = names.next().unwrap_or_default();
+            let mut has_alias = false;
+            for alias in names {
+                self.table.insert(alias.to_string(), canonical.to_string());
+                has_alias = true;
+            }
+            if !has_alias {
+                return Err(Error::new(
+                    ErrorKind::InvalidData,
+                    format!("No alias is defined at line {}: {}", line_no + 1, line),
+                ));
+            }
+        }
+        Ok(self)
+    }
+
+    /// Get the canonical spelling of the name, the names that are not recognized are unchanged
+    pub fn canonical_name(&self, name: &str) -> String {
+        if let Some(canonical) = self.table.get(name) {
+            return canonical.clone();
+        }
+        let naming = match self.naming {
+            Some(naming) => naming,
+            None => return name.to_string(),
+        };
+        let base = match builtin_base_name(name) {
+            Some(base) => base,
+            None => return name.to_string(),
+        };
+        match (naming, base.as_str()) {
+            (ChromNaming::Ensembl, _) => base,
+            (ChromNaming::Ucsc, "MT") => "chrM".to_string(),
+            (ChromNaming::Ucsc, _) => format!("chr{}", base),
+        }
+    }
+}
+
+/// Get the Ensembl style name of a chromosome in the primary assembly
+fn builtin_base_name(name: &str) -> Option<String> {
+    let accession = name.split('.').next().unwrap_or(name);
+    if let Some((_, _, base)) = HUMAN_ACCESSIONS
+        .iter()
+        .find(|(refseq, genbank, _)| *refseq == accession || *genbank == accession)
+    {
+        return Some(base.to_string());
+    }
+
+    let stripped = if name.len() > 3 && name[..3].eq_ignore_ascii_case("chr") {
+        &name[3..]
+    } else {
+        name
+    };
+    if !stripped.is_empty() && stripped.bytes().all(|b| b.is_ascii_digit()) {
+        return Some(stripped.trim_start_matches('0').to_string()).filter(|s| !s.is_empty());
+    }
+    match stripped {
+        "X" | "x" => Some("X".to_string()),
+        "Y" | "y" => Some("Y".to_string()),
+        "M" | "m" | "MT" | "Mt" => Some("MT".to_string()),
+        _ => None,
+    }
+}
+
+/// A chromosome set that maps the equivalent chromosome names, like `chr1`, `1` and
+/// `NC_000001.11`, to the same chromosome of the underlying set.
+pub struct AliasedChromSet<S: ChromSet> {
+    inner: S,
+    aliases: Arc<ChromAliases>,
+}
+
+pub struct AliasedChromHandle<S: ChromSet> {
+    inner: S::Handle,
+    aliases: Arc<ChromAliases>,
+    resolved: HashMap<String, S::RefType>,
+}
+
+impl<S: ChromSet> AliasedChromSet<S> {
+    pub fn new(inner: S, aliases: ChromAliases) -> Self {
+        Self {
+            inner,
+            aliases: Arc::new(aliases),
+        }
+    }
+
+    pub fn inner(&self) -> &S {
+        &self.inner
+    }
+}
+
+impl<S: ChromSet> ChromSetHandle for AliasedChromHandle<S> {
+    type RefType = S::RefType;
+    fn query_or_insert(&mut self, name: &str) -> Self::RefType {
+        if let Some(chrom) = self.resolved.get(name) {
+            return chrom.clone();
+        }
+        let canonical = self.aliases.canonical_name(name);
+        let chrom = self.inner.query_or_insert(&canonical);
+        self.resolved.insert(name.to_string(), chrom.clone());
+        chrom
+    }
+}
+
+impl<S: ChromSet> ChromSet for AliasedChromSet<S> {
+    type Handle = AliasedChromHandle<S>;
+    type RefType = S::RefType;
+    fn get_handle(&self) -> Self::Handle {
+        AliasedChromHandle {
+            inner: self.inner.get_handle(),
+            aliases: self.aliases.clone(),
+            resolved: HashMap::new(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{ChromName, LexicalChromSet};
+
+    #[test]
+    fn test_aliased_chrom_set() {
+        let aliases = ChromAliases::builtin(ChromNaming::Ucsc).with_alias("scaffold_1", "chrUn_1");
+        let chrom_set = AliasedChromSet::new(LexicalChromSet::new(), aliases);
+        let mut handle = chrom_set.get_handle();
+
+        let chr1 = handle.query_or_insert("chr1");
+        assert_eq!(chr1, handle.query_or_insert("1"));
+        assert_eq!(chr1, handle.query_or_insert("NC_000001.11"));
+        assert_eq!(chr1, handle.query_or_insert("CM000663.2"));
+        assert_eq!(ChromName::to_string(&handle.query_or_insert("MT")), "chrM");
+        assert_eq!(
+            ChromName::to_string(&handle.query_or_insert("scaffold_1")),
+            "chrUn_1"
+        );
+        assert_eq!(
+            ChromName::to_string(&handle.query_or_insert("GL000191.1")),
+            "GL000191.1"
+        );
+    }
+}
@@ -0,0 +1,374 @@
+use std::{
+    borrow::Cow,
+    cell::UnsafeCell,
+    cmp::Ordering,
+    fmt::{Debug, Display, Formatter, Result as FmtResult},
+    fs::File,
+    io::{BufRead, BufReader, Error, ErrorKind, Read, Result, Write},
+    path::Path,
+    rc::Rc,
+};
+
+use libflate::gzip::MultiDecoder;
+
+use super::{ChromName, ChromSet, ChromSetHandle, StringPool};
+
+/// The chromosomes with the declared order, the chromosomes that are not declared are appended
+/// when they are first seen
+#[derive(Default)]
+struct GenomePool {
+    names: StringPool,
+    // `None` for a declared chromosome whose length is unknown
+    lengths: Vec<Option<u32>>,
+}
+
+impl GenomePool {
+    fn declared(&self) -> usize {
+        self.lengths.len()
+    }
+}
+
+type SharedGenomePool = Rc<UnsafeCell<GenomePool>>;
+
+/// A chromosome set that orders the chromosomes by the order they are declared in a `.genome`
+/// file, a `.fai` index or a BAM/VCF header.
+///
+/// The chromosomes that are not declared are ordered after all the declared ones, by their
+/// names.
+pub struct GenomeChromSet {
+    pool: SharedGenomePool,
+}
+
+#[derive(Clone)]
+pub struct GenomeChromRef {
+    pool: SharedGenomePool,
+    idx: usize,
+}
+
+pub struct GenomeChromHandle {
+    pool: SharedGenomePool,
+}
+
+impl GenomeChromRef {
+    // See LexicalChromRef::get_string_ref for the safety contract
+    unsafe fn get_string_ref(&self) -> &str {
+        let pool_ref = &*self.pool.get();
+        pool_ref.names.i2s_map[self.idx].as_ref()
+    }
+
+    /// The index in the declared order, the undeclared chromosomes are all after the declared
+    fn rank(&self) -> usize {
+        let pool_ref = unsafe { &*self.pool.get() };
+        if self.idx < pool_ref.declared() {
+            self.idx
+        } else {
+            usize::MAX
+        }
+    }
+
+    /// The length of the chromosome, `None` if the chromosome is not declared or declared
+    /// without a length
+    pub fn length(&self) -> Option<u32> {
+        let pool_ref = unsafe { &*self.pool.get() };
+        pool_ref.lengths.get(self.idx).copied().flatten()
+    }
+}
+
+impl Debug for GenomeChromRef {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        let value = ChromName::to_string(self);
+        write!(f, "{}(Id={})", value, self.idx)
+    }
+}
+
+impl Display for GenomeChromRef {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        let value = ChromName::to_string(self);
+        write!(f, "{}", value)
+    }
+}
+
+impl PartialEq for GenomeChromRef {
+    fn eq(&self, other: &Self) -> bool {
+        if self.pool.get() == other.pool.get() {
+            return self.idx == other.idx;
+        }
+        unsafe { self.get_string_ref() == other.get_string_ref() }
+    }
+}
+
+impl PartialEq<str> for GenomeChromRef {
+    fn eq(&self, other: &str) -> bool {
+        unsafe { self.get_string_ref() == other }
+    }
+}
+
+impl PartialOrd for GenomeChromRef {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Eq for GenomeChromRef {}
+
+impl Ord for GenomeChromRef {
+    fn cmp(&self, other: &Self) -> Ordering {
+        if self.pool.get() == other.pool.get() && self.idx == other.idx {
+            return Ordering::Equal;
+        }
+        self.rank()
+            .cmp(&other.rank())
+            .then_with(|| unsafe { self.get_string_ref().cmp(other.get_string_ref()) })
+    }
+}
+
+impl ChromName for GenomeChromRef {
+    fn to_string(&self) -> Cow<'_, str> {
+        let ret = unsafe { self.get_string_ref().to_string() };
+        Cow::Owned(ret)
+    }
+
+    fn write<W: Write>(&self, mut w: W) -> std::io::Result<()> {
+        let name = unsafe { self.get_string_ref() };
+        w.write_all(name.as_bytes())
+    }
+}
+
+impl ChromSetHandle for GenomeChromHandle {
+    type RefType = GenomeChromRef;
+    fn query_or_insert(&mut self, name: &str) -> Self::RefType {
+        let pool = unsafe { self.pool.get().as_mut().unwrap() };
+        let idx = pool.names.query_id_or_insert(name);
+        GenomeChromRef {
+            pool: self.pool.clone(),
+            idx,
+        }
+    }
+}
+
+impl ChromSet for GenomeChromSet {
+    type Handle = GenomeChromHandle;
+    type RefType = GenomeChromRef;
+    fn get_handle(&self) -> Self::Handle {
+        GenomeChromHandle {
+            pool: self.pool.clone(),
+        }
+    }
+}
+
+fn invalid_data(message: String) -> Error {
+    Error::new(ErrorKind::InvalidData, message)
+}
+
+impl GenomeChromSet {
+    /// Create the chromosome set from the chromosome names and lengths in the declared order
+    pub fn from_chrom_sizes<S: AsRef<str>, I: IntoIterator<Item = (S, u32)>>(sizes: I) -> Self {
+        Self::from_declared(sizes.into_iter().map(|(name, length)| (name, Some(length))))
+    }
+
+    /// Create the chromosome set from the chromosome names in the declared order, some of
+    /// which may not have a known length
+    pub fn from_declared<S: AsRef<str>, I: IntoIterator<Item = (S, Option<u32>)>>(
+        sizes: I,
+    ) -> Self {
+        let mut pool = GenomePool::default();
+        for (name, length) in sizes {
+            let idx = pool.names.query_id_or_insert(name.as_ref());
+            // A duplicated chromosome keeps its first position
+            if idx == pool.lengths.len() {
+                pool.lengths.push(length);
+            }
+        }
+        Self {
+            pool: Rc::new(UnsafeCell::new(pool)),
+        }
+    }
+
+    /// Read the chromosome sizes from a reader of a `.genome` or `.fai` file, the first two
+    /// columns are the name and the length.
+    pub fn from_reader<R: Read>(reader: R) -> Result<Self> {
+        let mut sizes = vec![];
+        for (line_no, line) in BufReader::new(reader).lines().enumerate() {
+            let line = line?;
+            if line.trim().is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let mut fields = line.split_whitespace();
+            let name = fields.next().unwrap_or_default();
+            let length = fields
+                .next()
+                .and_then(|length| length.parse().ok())
+                .ok_or_else(|| {
+                    invalid_data(format!(
+                        "Invalid chromosome size at line {}: {}",
+                        line_no + 1,
+                        line
+                    ))
+                })?;
+            sizes.push((name.to_string(), length));
+        }
+        Ok(Self::from_chrom_sizes(sizes))
+    }
+
+    /// Load the chromosome order from a `.genome` file, which has the chromosome name and
+    /// length in each line
+    pub fn from_genome_file<P: AsRef<Path>>(path: P) -> Result<Self> {
+        Self::from_reader(File::open(path)?)
+    }
+
+    /// Load the chromosome order from a `.fai` index of a FASTA file
+    pub fn from_fai<P: AsRef<Path>>(path: P) -> Result<Self> {
+        Self::from_reader(File::open(path)?)
+    }
+
+    /// Load the chromosome order from the reference sequence dictionary of a BAM file
+    pub fn from_bam_header<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let mut reader = MultiDecoder::new(File::open(path)?)?;
+
+        let mut magic = [0u8; 4];
+        reader.read_exact(&mut magic)?;
+        if &magic != b"BAM\x01" {
+            return Err(invalid_data("Not a BAM file".to_string()));
+        }
+
+        let read_i32 = |reader: &mut MultiDecoder<File>| -> Result<usize> {
+            let mut buf = [0u8; 4];
+            reader.read_exact(&mut buf)?;
+            let value = i32::from_le_bytes(buf);
+            if value < 0 {
+                return Err(invalid_data("Invalid BAM header".to_string()));
+            }
+            Ok(value as usize)
+        };
+
+        let text_size = read_i32(&mut reader)?;
+        std::io::copy(
+            &mut (&mut reader).take(text_size as u64),
+            &mut std::io::sink(),
+        )?;
+
+        let n_ref = read_i32(&mut reader)?;
+        let mut sizes = Vec::with_capacity(n_ref);
+        for _ in 0..n_ref {
+            let name_size = read_i32(&mut reader)?;
+            let mut name = vec![0; name_size];
+            reader.read_exact(&mut name)?;
+            if name.last() == Some(&0) {
+                name.pop();
+            }
+            let name = String::from_utf8(name)
+                .map_err(|_| invalid_data("Invalid chromosome name in BAM header".to_string()))?;
+            let length = read_i32(&mut reader)?;
+            sizes.push((name, length as u32));
+        }
+
+        Ok(Self::from_chrom_sizes(sizes))
+    }
+
+    /// Load the chromosome order from the `##contig` lines of a VCF header, the VCF file can be
+    /// either plain text or compressed
+    pub fn from_vcf_header<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let mut file = File::open(path.as_ref())?;
+        let mut magic = [0u8; 2];
+        let compressed = file.read(&mut magic)? == 2 && magic == [0x1f, 0x8b];
+        let file = File::open(path.as_ref())?;
+        let reader: Box<dyn Read> = if compressed {
+            Box::new(MultiDecoder::new(file)?)
+        } else {
+            Box::new(file)
+        };
+
+        let mut sizes = vec![];
+        for line in BufReader::new(reader).lines() {
+            let line = line?;
+            if !line.starts_with("##") {
+                break;
+            }
+            let contig = match line
+                .strip_prefix("##contig=<")
+                .and_then(|s| s.strip_suffix('>'))
+            {
+                Some(contig) => contig,
+                None => continue,
+            };
+            let mut name = None;
+            let mut length = None;
+            for field in contig.split(',') {
+                if let Some(value) = field.strip_prefix("ID=") {
+                    name = Some(value.to_string());
+                } else if let Some(value) = field.strip_prefix("length=") {
+                    length = value.parse().ok();
+                }
+            }
+            // The length is optional in the VCF spec, such a contig keeps its position in the
+            // header with an unknown length
+            match name {
+                Some(name) => sizes.push((name, length)),
+                None => return Err(invalid_data(format!("Invalid contig line: {}", line))),
+            }
+        }
+
+        Ok(Self::from_declared(sizes))
+    }
+
+    /// The declared chromosome names and lengths in order, the length is `None` if it's unknown
+    pub fn chrom_sizes(&self) -> Vec<(String, Option<u32>)> {
+        let pool = unsafe { &*self.pool.get() };
+        pool.names
+            .i2s_map
+            .iter()
+            .zip(pool.lengths.iter())
+            .map(|(name, length)| (name.clone(), *length))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    #[test]
+    fn test_genome_chrom_set() {
+        let genome: &[u8] = b"chr1\t248956422\nchr2\t242193529\nchr10\t133797422\n";
+        let chrom_set = GenomeChromSet::from_reader(genome).unwrap();
+        let mut handle = chrom_set.get_handle();
+
+        let chr1 = handle.query_or_insert("chr1");
+        let chr2 = handle.query_or_insert("chr2");
+        let chr10 = handle.query_or_insert("chr10");
+        let unknown = handle.query_or_insert("chrUn");
+
+        assert!(chr1 < chr2);
+        assert!(chr2 < chr10);
+        assert!(chr10 < unknown);
+
+        assert_eq!(chr10.length(), Some(133797422));
+        assert_eq!(unknown.length(), None);
+    }
+
+    #[test]
+    fn test_vcf_contigs_without_length() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("header.vcf");
+        std::fs::write(
+            &path,
+            "##fileformat=VCFv4.2\n##contig=<ID=chr2,length=242193529>\n##contig=<ID=chr1>\n\
+             #CHROM\tPOS\tID\tREF\tALT\tQUAL\tFILTER\tINFO\n",
+        )
+        .unwrap();
+        let chrom_set = GenomeChromSet::from_vcf_header(&path).unwrap();
+        assert_eq!(
+            chrom_set.chrom_sizes(),
+            vec![
+                ("chr2".to_string(), Some(242193529)),
+                ("chr1".to_string(), None)
+            ]
+        );
+
+        let mut handle = chrom_set.get_handle();
+        let chr1 = handle.query_or_insert("chr1");
+        let chr2 = handle.query_or_insert("chr2");
+        let chr3 = handle.query_or_insert("chr3");
+        assert!(chr2 < chr1 && chr1 < chr3);
+        assert_eq!(chr1.length(), None);
+    }
+}
@@ -1,84 +1,13 @@
 use std::{
-    borrow::{Borrow, Cow},
+    borrow::Cow,
     cell::UnsafeCell,
     cmp::Ordering,
-    collections::HashMap,
     fmt::{Debug, Display, Formatter, Result as FmtResult},
     io::Write,
     sync::Arc,
 };
 
-pub trait WithChromSet<H: ChromSetHandle> {
-    type Result;
-    fn with_chrom_set(self, handle: &mut H) -> Self::Result;
-}
-
-pub trait ChromName: Ord + Clone {
-    fn to_string(&self) -> Cow<str>;
-    fn write<W: Write>(&self, w: W) -> std::io::Result<()>;
-}
-
-impl ChromName for String {
-    fn to_string(&self) -> Cow<str> {
-        Cow::Borrowed(self)
-    }
-
-    fn write<W: Write>(&self, mut w: W) -> std::io::Result<()> {
-        w.write_all(self.as_bytes())
-    }
-}
-
-impl<'a> ChromName for &'a str {
-    fn to_string(&self) -> Cow<str> {
-        Cow::Borrowed(*self)
-    }
-
-    fn write<W: Write>(&self, mut w: W) -> std::io::Result<()> {
-        w.write_all(self.as_bytes())
-    }
-}
-
-pub trait ChromSetHandle {
-    type RefType: ChromName;
-    fn query_or_insert(&mut self, name: &str) -> Self::RefType;
-}
-
-pub trait ChromSet {
-    type RefType: ChromName;
-    type Handle: ChromSetHandle<RefType = Self::RefType>;
-    fn get_handle(&self) -> Self::Handle;
-}
-
-#[derive(Default)]
-struct StringPool {
-    s2i_map: HashMap<String, usize>,
-    i2s_map: Vec<String>,
-}
-
-impl StringPool {
-    fn query_id(&self, s: &str) -> Option<usize> {
-        if let Some(id) = self.s2i_map.get(s) {
-            Some(*id)
-        } else {
-            None
-        }
-    }
-
-    fn query_id_or_insert<T: Borrow<str>>(&mut self, s: T) -> usize {
-        let sref = s.borrow();
-        if Some(sref) == self.i2s_map.last().map(Borrow::borrow) {
-            return self.i2s_map.len() - 1;
-        }
-        if let Some(id) = self.query_id(sref) {
-            id
-        } else {
-            self.s2i_map
-                .insert(s.borrow().to_string(), self.i2s_map.len());
-            self.i2s_map.push(s.borrow().to_string());
-            self.i2s_map.len() - 1
-        }
-    }
-}
+use super::{ChromName, ChromSet, ChromSetHandle, StringPool};
 
 type SharedStringPool = Arc<UnsafeCell<StringPool>>;
 
@@ -0,0 +1,242 @@
+use std::{
+    borrow::Cow,
+    cell::UnsafeCell,
+    cmp::Ordering,
+    fmt::{Debug, Display, Formatter, Result as FmtResult},
+    io::Write,
+    rc::Rc,
+};
+
+use super::{ChromName, ChromSet, ChromSetHandle, StringPool};
+
+/// A run of digits or non-digits in a chromosome name
+#[derive(PartialEq, Eq, PartialOrd, Ord, Clone, Debug)]
+enum Segment {
+    Number(u64),
+    Text(String),
+}
+
+/// The key that orders the chromosomes naturally: the numbered chromosomes first, then X, Y and
+/// M, the other names are ordered after them by comparing the numbers in the names numerically
+#[derive(PartialEq, Eq, PartialOrd, Ord, Clone, Debug)]
+struct NaturalKey {
+    class: u8,
+    segments: Vec<Segment>,
+}
+
+impl NaturalKey {
+    fn new(name: &str) -> Self {
+        let stripped = if name.len() > 3 && name[..3].eq_ignore_ascii_case("chr") {
+            &name[3..]
+        } else {
+            name
+        };
+
+        let class = if stripped.bytes().all(|b| b.is_ascii_digit()) {
+            0
+        } else {
+            match stripped {
+                "X" | "x" => 1,
+                "Y" | "y" => 2,
+                "M" | "m" | "MT" | "Mt" => 3,
+                _ => 4,
+            }
+        };
+
+        let mut segments = vec![];
+        let mut rest = stripped;
+        while let Some(first) = rest.chars().next() {
+            let is_digit = first.is_ascii_digit();
+            let size = rest
+                .find(|c: char| c.is_ascii_digit() != is_digit)
+                .unwrap_or(rest.len());
+            let (head, tail) = rest.split_at(size);
+            segments.push(match head.parse() {
+                Ok(number) if is_digit => Segment::Number(number),
+                _ => Segment::Text(head.to_string()),
+            });
+            rest = tail;
+        }
+
+        Self { class, segments }
+    }
+}
+
+#[derive(Default)]
+struct NaturalPool {
+    names: StringPool,
+    keys: Vec<NaturalKey>,
+}
+
+type SharedNaturalPool = Rc<UnsafeCell<NaturalPool>>;
+
+/// A chromosome set that orders the chromosomes naturally, for example
+/// `chr1 < chr2 < chr10 < chrX < chrY < chrM`
+pub struct NaturalChromSet {
+    pool: SharedNaturalPool,
+}
+
+#[derive(Clone)]
+pub struct NaturalChromRef {
+    pool: SharedNaturalPool,
+    idx: usize,
+}
+
+pub struct NaturalChromHandle {
+    pool: SharedNaturalPool,
+}
+
+impl NaturalChromRef {
+    // See LexicalChromRef::get_string_ref for the safety contract
+    unsafe fn get_string_ref(&self) -> &str {
+        let pool_ref = &*self.pool.get();
+        pool_ref.names.i2s_map[self.idx].as_ref()
+    }
+
+    unsafe fn get_key_ref(&self) -> &NaturalKey {
+        let pool_ref = &*self.pool.get();
+        &pool_ref.keys[self.idx]
+    }
+}
+
+impl Debug for NaturalChromRef {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        let value = ChromName::to_string(self);
+        write!(f, "{}(Id={})", value, self.idx)
+    }
+}
+
+impl Display for NaturalChromRef {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        let value = ChromName::to_string(self);
+        write!(f, "{}", value)
+    }
+}
+
+impl PartialEq for NaturalChromRef {
+    fn eq(&self, other: &Self) -> bool {
+        if self.pool.get() == other.pool.get() {
+            return self.idx == other.idx;
+        }
+        unsafe { self.get_string_ref() == other.get_string_ref() }
+    }
+}
+
+impl PartialEq<str> for NaturalChromRef {
+    fn eq(&self, other: &str) -> bool {
+        unsafe { self.get_string_ref() == other }
+    }
+}
+
+impl PartialOrd for NaturalChromRef {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Eq for NaturalChromRef {}
+
+impl Ord for NaturalChromRef {
+    fn cmp(&self, other: &Self) -> Ordering {
+        if self.pool.get() == other.pool.get() && self.idx == other.idx {
+            return Ordering::Equal;
+        }
+        // The names with the same key, like chr01 and chr1, are ordered lexically
+        unsafe {
+            self.get_key_ref()
+                .cmp(other.get_key_ref())
+                .then_with(|| self.get_string_ref().cmp(other.get_string_ref()))
+        }
+    }
+}
+
+impl ChromName for NaturalChromRef {
+    fn to_string(&self) -> Cow<'_, str> {
+        let ret = unsafe { self.get_string_ref().to_string() };
+        Cow::Owned(ret)
+    }
+
+    fn write<W: Write>(&self, mut w: W) -> std::io::Result<()> {
+        let name = unsafe { self.get_string_ref() };
+        w.write_all(name.as_bytes())
+    }
+}
+
+impl ChromSetHandle for NaturalChromHandle {
+    type RefType = NaturalChromRef;
+    fn query_or_insert(&mut self, name: &str) -> Self::RefType {
+        let pool = unsafe { self.pool.get().as_mut().unwrap() };
+        let idx = pool.names.query_id_or_insert(name);
+        if idx == pool.keys.len() {
+            pool.keys.push(NaturalKey::new(name));
+        }
+        NaturalChromRef {
+            pool: self.pool.clone(),
+            idx,
+        }
+    }
+}
+
+impl NaturalChromSet {
+    pub fn new() -> Self {
+        Self {
+            pool: Rc::new(UnsafeCell::new(NaturalPool::default())),
+        }
+    }
+}
+
+impl Default for NaturalChromSet {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ChromSet for NaturalChromSet {
+    type Handle = NaturalChromHandle;
+    type RefType = NaturalChromRef;
+    fn get_handle(&self) -> Self::Handle {
+        NaturalChromHandle {
+            pool: self.pool.clone(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    #[test]
+    fn test_natural_chrom_set() {
+        let chrom_set = NaturalChromSet::new();
+        let mut handle = chrom_set.get_handle();
+
+        let mut chroms: Vec<_> = [
+            "chrM",
+            "chr10",
+            "chrY",
+            "chr2",
+            "chrUn_gl000220",
+            "chrX",
+            "chr1",
+            "chr1_gl000191_random",
+        ]
+        .iter()
+        .map(|name| handle.query_or_insert(name))
+        .collect();
+        chroms.sort();
+
+        let names: Vec<_> = chroms.iter().map(|c| ChromName::to_string(c)).collect();
+        assert_eq!(
+            names,
+            [
+                "chr1",
+                "chr2",
+                "chr10",
+                "chrX",
+                "chrY",
+                "chrM",
+                "chr1_gl000191_random",
+                "chrUn_gl000220"
+            ]
+        );
+    }
+}
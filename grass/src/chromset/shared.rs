@@ -0,0 +1,175 @@
+use std::{
+    borrow::Cow,
+    cmp::Ordering,
+    collections::{HashMap, HashSet},
+    fmt::{Debug, Display, Formatter, Result as FmtResult},
+    hash::{Hash, Hasher},
+    io::Write,
+    sync::{Arc, RwLock},
+};
+
+use super::{ChromName, ChromSet, ChromSetHandle};
+
+/// The append-only arena of the interned chromosome names
+type SharedNamePool = Arc<RwLock<HashSet<Arc<str>>>>;
+
+/// A lexically ordered chromosome set that is safe to share across threads.
+///
+/// The names are interned in an append-only arena protected by a lock, and each handle caches
+/// the names it has seen, so that the lock is only taken for the first occurrence of a name.
+#[derive(Clone, Default)]
+pub struct SharedChromSet {
+    pool: SharedNamePool,
+}
+
+/// The reference to an interned chromosome name, which is `Send + Sync`
+#[derive(Clone)]
+pub struct SharedChromRef {
+    name: Arc<str>,
+}
+
+pub struct SharedChromHandle {
+    pool: SharedNamePool,
+    cache: HashMap<Arc<str>, SharedChromRef>,
+}
+
+impl SharedChromRef {
+    pub fn as_str(&self) -> &str {
+        &self.name
+    }
+}
+
+impl Debug for SharedChromRef {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        write!(f, "{}", self.name)
+    }
+}
+
+impl Display for SharedChromRef {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        write!(f, "{}", self.name)
+    }
+}
+
+impl PartialEq for SharedChromRef {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.name, &other.name) || self.name == other.name
+    }
+}
+
+impl PartialEq<str> for SharedChromRef {
+    fn eq(&self, other: &str) -> bool {
+        self.name.as_ref() == other
+    }
+}
+
+impl Eq for SharedChromRef {}
+
+impl Hash for SharedChromRef {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.name.hash(state)
+    }
+}
+
+impl PartialOrd for SharedChromRef {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for SharedChromRef {
+    fn cmp(&self, other: &Self) -> Ordering {
+        if Arc::ptr_eq(&self.name, &other.name) {
+            return Ordering::Equal;
+        }
+        self.name.cmp(&other.name)
+    }
+}
+
+impl ChromName for SharedChromRef {
+    fn to_string(&self) -> Cow<str> {
+        Cow::Borrowed(&self.name)
+    }
+
+    fn write<W: Write>(&self, mut w: W) -> std::io::Result<()> {
+        w.write_all(self.name.as_bytes())
+    }
+}
+
+impl ChromSetHandle for SharedChromHandle {
+    type RefType = SharedChromRef;
+    fn query_or_insert(&mut self, name: &str) -> Self::RefType {
+        if let Some(chrom) = self.cache.get(name) {
+            return chrom.clone();
+        }
+
+        let existing = self.pool.read().unwrap().get(name).cloned();
+        let name = match existing {
+            Some(name) => name,
+            None => {
+                let mut pool = self.pool.write().unwrap();
+                // Someone else may have inserted it after we released the read lock
+                match pool.get(name) {
+                    Some(name) => name.clone(),
+                    None => {
+                        let name: Arc<str> = Arc::from(name);
+                        pool.insert(name.clone());
+                        name
+                    }
+                }
+            }
+        };
+
+        let chrom = SharedChromRef { name };
+        self.cache.insert(chrom.name.clone(), chrom.clone());
+        chrom
+    }
+}
+
+impl SharedChromSet {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl ChromSet for SharedChromSet {
+    type Handle = SharedChromHandle;
+    type RefType = SharedChromRef;
+    fn get_handle(&self) -> Self::Handle {
+        SharedChromHandle {
+            pool: self.pool.clone(),
+            cache: HashMap::new(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    #[test]
+    fn test_shared_chrom_set() {
+        let chrom_set = SharedChromSet::new();
+
+        let workers: Vec<_> = (0..4)
+            .map(|_| {
+                let mut handle = chrom_set.get_handle();
+                std::thread::spawn(move || {
+                    ["chr2", "chr1", "chr10"]
+                        .iter()
+                        .map(|name| handle.query_or_insert(name))
+                        .collect::<Vec<_>>()
+                })
+            })
+            .collect();
+
+        let results: Vec<_> = workers.into_iter().map(|w| w.join().unwrap()).collect();
+        let mut handle = chrom_set.get_handle();
+        let chr1 = handle.query_or_insert("chr1");
+
+        for chroms in results {
+            assert!(Arc::ptr_eq(&chroms[1].name, &chr1.name));
+            assert!(chroms[1] < chroms[2]);
+            assert!(chroms[2] < chroms[0]);
+        }
+    }
+}
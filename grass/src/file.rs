@@ -1,21 +1,117 @@
 use crate::{
-    properties::Parsable,
-    records::{Bed3, Bed4, Bed5},
+    properties::{Parsable, ParseFailure},
+    records::{
+        Bed12, Bed3, Bed4, Bed5, Bed6, BedGraph, BedN, BroadPeak, GffDialect, GffRecord,
+        NarrowPeak, VcfLine,
+    },
     ChromSet, WithChromSet,
 };
 
 use std::{
+    cell::{Cell, RefCell},
+    error::Error,
+    fmt::{Display, Formatter, Result as FmtResult},
     io::{BufRead, BufReader, Read},
     marker::PhantomData,
+    rc::Rc,
 };
 
+/// Why a line of the input can't be turned into a record
+#[derive(Debug)]
+pub enum ParseErrorReason {
+    /// The underlying reader failed, the stream can't continue after this
+    Io(std::io::Error),
+    /// The line is not a valid record
+    Malformed(String),
+}
+
+impl Display for ParseErrorReason {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        match self {
+            Self::Io(e) => write!(f, "I/O error: {}", e),
+            Self::Malformed(reason) => write!(f, "{}", reason),
+        }
+    }
+}
+
+/// The error reported by a record stream when a line can't be parsed
+#[derive(Debug)]
+pub struct ParseError {
+    /// The 1-based line number
+    pub line: usize,
+    /// The byte offset of the beginning of the line
+    pub offset: u64,
+    /// The 1-based column (tab separated field) that causes the error, 0 if the error isn't
+    /// in a particular column
+    pub column: usize,
+    pub reason: ParseErrorReason,
+}
+
+impl Display for ParseError {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        write!(
+            f,
+            "line {} (byte offset {}), column {}: {}",
+            self.line, self.offset, self.column, self.reason
+        )
+    }
+}
+
+impl Error for ParseError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match &self.reason {
+            ParseErrorReason::Io(e) => Some(e),
+            ParseErrorReason::Malformed(_) => None,
+        }
+    }
+}
+
+/// What a record stream does with the lines it can't parse
+#[derive(Clone, Copy, Debug, PartialEq, Default)]
+pub enum ParsePolicy {
+    /// Stop at the first malformed line and keep the error in the error slot, or yield the
+    /// error in the fallible mode
+    #[default]
+    Strict,
+    /// Skip the malformed lines and count them, the callback set by `on_skip` is called with
+    /// the error of each of them
+    SkipAndCount,
+}
+
+/// The records that can be read from a line based text file
+pub trait LineRecord<C: ChromSet>: Sized {
+    /// Parse the line, on failure the 1-based column and the reason are returned
+    fn parse_line(line: &str, handle: &mut C::Handle) -> Result<Self, ParseFailure>;
+}
+
+fn is_header_or_empty(line: &str) -> bool {
+    line.starts_with('#')
+        || line.starts_with("track")
+        || line.starts_with("browser")
+        || line.trim().is_empty()
+}
+
+/// The callback of a record stream for the malformed lines it skips
+type SkipCallback = Box<dyn FnMut(&ParseError)>;
+
 pub struct LineRecordStream<C: ChromSet, R: Read, Rec> {
     chrom_set_handle: C::Handle,
     reader: BufReader<R>,
     buffer: String,
+    policy: ParsePolicy,
+    line_number: usize,
+    offset: u64,
+    skipped: Rc<Cell<usize>>,
+    on_skip: Option<SkipCallback>,
+    error: ErrorSlot,
+    failed: bool,
     _p: PhantomData<Rec>,
 }
 
+/// The error that stops a record stream, it's shared with the stream so that it can be checked
+/// after the stream is consumed
+pub type ErrorSlot = Rc<RefCell<Option<ParseError>>>;
+
 impl<C: ChromSet, R: Read, Rec> LineRecordStream<C, R, Rec> {
     pub(crate) fn with_chrom_set(chrom_set: &C, reader: R) -> Self {
         let chrom_set_handle = chrom_set.get_handle();
@@ -24,9 +120,157 @@ impl<C: ChromSet, R: Read, Rec> LineRecordStream<C, R, Rec> {
             chrom_set_handle,
             reader,
             buffer: String::with_capacity(4096),
+            policy: ParsePolicy::default(),
+            line_number: 0,
+            offset: 0,
+            skipped: Default::default(),
+            on_skip: None,
+            error: Default::default(),
+            failed: false,
             _p: PhantomData,
         }
     }
+
+    /// Change how the malformed lines are handled
+    pub fn with_policy(mut self, policy: ParsePolicy) -> Self {
+        self.policy = policy;
+        self
+    }
+
+    /// Call the function with the error of each malformed line that is skipped, for example to
+    /// print a warning for it
+    pub fn on_skip<F: FnMut(&ParseError) + 'static>(mut self, callback: F) -> Self {
+        self.on_skip = Some(Box::new(callback));
+        self
+    }
+
+    /// The counter of the malformed lines that have been skipped, it's shared with the stream
+    /// so that it can be checked after the stream is consumed
+    pub fn skipped_counter(&self) -> Rc<Cell<usize>> {
+        self.skipped.clone()
+    }
+
+    /// The slot of the error that stops the stream, which is either a malformed line under the
+    /// strict policy or an I/O error. The stream just ends when it fails, so the caller should
+    /// check the slot after the stream is consumed.
+    pub fn error_slot(&self) -> ErrorSlot {
+        self.error.clone()
+    }
+
+    /// Turn the stream into an iterator of `Result<Rec, ParseError>`, so that the errors can
+    /// be handled by the caller.
+    pub fn fallible(self) -> FallibleLineRecordStream<C, R, Rec> {
+        FallibleLineRecordStream {
+            inner: self,
+            failed: false,
+        }
+    }
+}
+
+impl<C: ChromSet, R: Read, Rec: LineRecord<C>> LineRecordStream<C, R, Rec> {
+    /// Read the next record, every malformed line is reported regardless of the policy
+    fn read_record(&mut self) -> Option<Result<Rec, ParseError>> {
+        loop {
+            self.buffer.clear();
+            let line_offset = self.offset;
+            self.line_number += 1;
+            match self.reader.read_line(&mut self.buffer) {
+                Ok(0) => return None,
+                Ok(size) => self.offset += size as u64,
+                Err(e) => {
+                    return Some(Err(ParseError {
+                        line: self.line_number,
+                        offset: line_offset,
+                        column: 0,
+                        reason: ParseErrorReason::Io(e),
+                    }))
+                }
+            }
+
+            if is_header_or_empty(&self.buffer) {
+                continue;
+            }
+
+            return Some(
+                Rec::parse_line(&self.buffer, &mut self.chrom_set_handle).map_err(
+                    |(column, reason)| ParseError {
+                        line: self.line_number,
+                        offset: line_offset,
+                        column,
+                        reason: ParseErrorReason::Malformed(reason),
+                    },
+                ),
+            );
+        }
+    }
+
+    /// Read the next record and apply the skipping policy
+    fn next_with_policy(&mut self) -> Option<Result<Rec, ParseError>> {
+        loop {
+            match self.read_record()? {
+                Err(e) if matches!(e.reason, ParseErrorReason::Malformed(_)) => match self.policy {
+                    ParsePolicy::Strict => return Some(Err(e)),
+                    ParsePolicy::SkipAndCount => {
+                        if let Some(callback) = self.on_skip.as_mut() {
+                            callback(&e);
+                        }
+                        self.skipped.set(self.skipped.get() + 1);
+                    }
+                },
+                other => return Some(other),
+            }
+        }
+    }
+}
+
+/// Note: In the infallible mode, the stream ends at a malformed line under the strict policy or
+/// at an I/O error, and the error is kept in the `error_slot()`. Use `fallible()` to handle the
+/// errors one by one instead.
+impl<C: ChromSet, R: Read, Rec: LineRecord<C>> Iterator for LineRecordStream<C, R, Rec> {
+    type Item = Rec;
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.failed {
+            return None;
+        }
+        match self.next_with_policy()? {
+            Ok(record) => Some(record),
+            Err(e) => {
+                *self.error.borrow_mut() = Some(e);
+                self.failed = true;
+                None
+            }
+        }
+    }
+}
+
+pub struct FallibleLineRecordStream<C: ChromSet, R: Read, Rec> {
+    inner: LineRecordStream<C, R, Rec>,
+    failed: bool,
+}
+
+impl<C: ChromSet, R: Read, Rec> FallibleLineRecordStream<C, R, Rec> {
+    pub fn skipped_counter(&self) -> Rc<Cell<usize>> {
+        self.inner.skipped_counter()
+    }
+}
+
+impl<C: ChromSet, R: Read, Rec: LineRecord<C>> Iterator for FallibleLineRecordStream<C, R, Rec> {
+    type Item = Result<Rec, ParseError>;
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.failed {
+            return None;
+        }
+        let ret = self.inner.next_with_policy()?;
+        // We can't read anything after an I/O error
+        if let Err(ParseError {
+            reason: ParseErrorReason::Io(_),
+            ..
+        }) = ret
+        {
+            self.failed = true;
+        }
+        Some(ret)
+    }
 }
 
 pub trait LineRecordStreamExt: Read {
@@ -45,13 +289,10 @@ impl<R: Read> LineRecordStreamExt for R {}
 
 macro_rules! impl_line_record_stream {
     ($rec_ty:ident) => {
-        impl<C: ChromSet, R: Read> Iterator for LineRecordStream<C, R, $rec_ty<C::RefType>> {
-            type Item = $rec_ty<C::RefType>;
-            fn next(&mut self) -> Option<Self::Item> {
-                self.buffer.clear();
-                self.reader.read_line(&mut self.buffer).ok()?;
-                let (parsed, _) = $rec_ty::parse(self.buffer.as_ref())?;
-                Some(parsed.with_chrom_set(&mut self.chrom_set_handle))
+        impl<C: ChromSet> LineRecord<C> for $rec_ty<C::RefType> {
+            fn parse_line(line: &str, handle: &mut C::Handle) -> Result<Self, ParseFailure> {
+                let (parsed, _) = $rec_ty::try_parse(line)?;
+                Ok(parsed.with_chrom_set(handle))
             }
         }
     };
@@ -60,3 +301,343 @@ macro_rules! impl_line_record_stream {
 impl_line_record_stream!(Bed3);
 impl_line_record_stream!(Bed4);
 impl_line_record_stream!(Bed5);
+impl_line_record_stream!(Bed6);
+impl_line_record_stream!(Bed12);
+impl_line_record_stream!(BedN);
+impl_line_record_stream!(BedGraph);
+impl_line_record_stream!(NarrowPeak);
+impl_line_record_stream!(BroadPeak);
+impl_line_record_stream!(VcfLine);
+
+impl<C: ChromSet, D: GffDialect> LineRecord<C> for GffRecord<C::RefType, D> {
+    fn parse_line(line: &str, handle: &mut C::Handle) -> Result<Self, ParseFailure> {
+        let (parsed, _) = GffRecord::<_, D>::try_parse(line)?;
+        Ok(parsed.with_chrom_set(handle))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{chromset::LexicalChromRef, LexicalChromSet};
+
+    const INPUT: &[u8] = b"#header\nchr1\t10\t20\nchr1\tx\t30\nchr1\t40\n\nchr2\t5\t15\n";
+
+    #[test]
+    fn test_fallible_stream() {
+        let chroms = LexicalChromSet::new();
+        let records: LineRecordStream<_, _, Bed3<LexicalChromRef>> =
+            INPUT.into_record_iter(&chroms);
+        let results: Vec<_> = records.fallible().collect();
+        assert_eq!(results.len(), 4);
+        assert!(results[0].is_ok());
+        assert!(results[3].is_ok());
+
+        let err = results[1].as_ref().err().unwrap();
+        assert_eq!((err.line, err.offset, err.column), (3, 19, 2));
+        let err = results[2].as_ref().err().unwrap();
+        assert_eq!((err.line, err.offset, err.column), (4, 29, 3));
+    }
+
+    #[test]
+    fn test_strict_stream_stops_at_error() {
+        let chroms = LexicalChromSet::new();
+        let mut records: LineRecordStream<_, _, Bed3<LexicalChromRef>> =
+            INPUT.into_record_iter(&chroms);
+        let error = records.error_slot();
+        assert_eq!(records.by_ref().count(), 1);
+        assert!(records.next().is_none());
+
+        let err = error.borrow_mut().take().unwrap();
+        assert_eq!((err.line, err.column), (3, 2));
+    }
+
+    #[test]
+    fn test_diagnose_columns() {
+        let chroms = LexicalChromSet::new();
+        let input: &[u8] = b"chr1\t10\t20\tname\tx\t+\nchr1\t10\t20\tname\t0\n";
+        let records: LineRecordStream<_, _, Bed6<LexicalChromRef>> =
+            input.into_record_iter(&chroms);
+        let columns: Vec<_> = records
+            .fallible()
+            .map(|result| result.err().unwrap().column)
+            .collect();
+        assert_eq!(columns, vec![5, 6]);
+
+        let input: &[u8] = b"chr1\t10\t20\t1\t2\t3\t4\t5\t6\t7\n";
+        let records: LineRecordStream<_, _, NarrowPeak<LexicalChromRef>> =
+            input.into_record_iter(&chroms);
+        let err = records.fallible().next().unwrap().err().unwrap();
+        assert_eq!(err.column, 6);
+    }
+
+    #[test]
+    fn test_parse_failure_reasons() {
+        let chroms = LexicalChromSet::new();
+        let failure = |err: ParseError| match err.reason {
+            ParseErrorReason::Malformed(reason) => (err.column, reason),
+            ParseErrorReason::Io(e) => panic!("unexpected I/O error {}", e),
+        };
+
+        let input: &[u8] = b"chr1\t200\t100\ttx\t0\t+\t100\t200\t0\t1\t10,\t0,\n\
+                             chr1\t100\t200\ttx\t0\t+\t100\t200\t0\t2\t10,20,\t0,90,\n\
+                             chr1\t100\t200\ttx\t0\t+\tx\t200\t0\t1\t10,\t0,\n";
+        let records: LineRecordStream<_, _, Bed12<LexicalChromRef>> =
+            input.into_record_iter(&chroms);
+        let failures: Vec<_> = records
+            .fallible()
+            .map(|result| failure(result.err().unwrap()))
+            .collect();
+        assert_eq!(
+            failures,
+            vec![
+                (
+                    3,
+                    "the end position is before the begin position".to_string()
+                ),
+                (12, "block 2 is out of the record range".to_string()),
+                (7, "invalid thick begin 'x'".to_string()),
+            ]
+        );
+
+        let input: &[u8] = b"chr1\tsrc\tgene\t10\t5\t.\t+\t.\tID=a\n\
+                             chr1\tsrc\tgene\t10\t20\t.\t+\t3\tID=a\n";
+        let records: LineRecordStream<_, _, GffRecord<LexicalChromRef, crate::records::Gff3>> =
+            input.into_record_iter(&chroms);
+        let columns: Vec<_> = records
+            .fallible()
+            .map(|result| failure(result.err().unwrap()).0)
+            .collect();
+        assert_eq!(columns, vec![5, 8]);
+
+        let input: &[u8] = b"chr1\t10\t20\tp\t0\t.\t1\t2\tx\t5\n\
+                             chr1\t10\t20\tp\t0\t.\t1\t2\t3\tx\n\
+                             chr1\t10\t20\tp\t0\t.\t1\t2\n";
+        let records: LineRecordStream<_, _, NarrowPeak<LexicalChromRef>> =
+            input.into_record_iter(&chroms);
+        let failures: Vec<_> = records
+            .fallible()
+            .map(|result| failure(result.err().unwrap()))
+            .collect();
+        assert_eq!(
+            failures,
+            vec![
+                (9, "invalid q-value 'x'".to_string()),
+                (10, "invalid peak offset 'x'".to_string()),
+                (9, "expecting at least 9 columns, but 8 found".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_skip_policy() {
+        let chroms = LexicalChromSet::new();
+        let records: LineRecordStream<_, _, Bed3<LexicalChromRef>> = INPUT
+            .into_record_iter(&chroms)
+            .with_policy(ParsePolicy::SkipAndCount);
+        let skipped = records.skipped_counter();
+        let lines = Rc::new(RefCell::new(vec![]));
+        let records = records.on_skip({
+            let lines = lines.clone();
+            move |e| lines.borrow_mut().push(e.line)
+        });
+        assert_eq!(records.count(), 2);
+        assert_eq!(skipped.get(), 2);
+        assert_eq!(*lines.borrow(), vec![3, 4]);
+    }
+
+    #[test]
+    fn test_bed6_stream() {
+        use crate::properties::{Serializable, Strand, WithName, WithStrand};
+
+        let chroms = LexicalChromSet::new();
+        let input: &[u8] = b"chr1\t10\t20\tgene1\t0\t+\nchr1\t15\t30\tgene2\t.\t-\textra\nchr2\t5\t15\tgene3\t1\tx";
+        let records: LineRecordStream<_, _, Bed6<LexicalChromRef>> =
+            input.into_record_iter(&chroms);
+        let results: Vec<_> = records.fallible().collect();
+        assert_eq!(results.len(), 3);
+
+        let first = results[0].as_ref().ok().unwrap();
+        assert_eq!(first.name(), "gene1");
+        assert_eq!(first.strand(), Some(Strand::Pos));
+        let second = results[1].as_ref().ok().unwrap();
+        assert_eq!(second.strand(), Some(Strand::Neg));
+        assert!(second.core.score.is_none());
+
+        let mut buf = vec![];
+        first.dump(&mut buf).unwrap();
+        assert_eq!(buf, b"chr1\t10\t20\tgene1\t0\t+");
+
+        let err = results[2].as_ref().err().unwrap();
+        assert_eq!((err.line, err.column), (3, 6));
+    }
+
+    #[test]
+    fn test_bed12_stream() {
+        use crate::properties::{Serializable, WithBlocks, WithRegionCore};
+
+        let chroms = LexicalChromSet::new();
+        let input: &[u8] = b"chr1\t100\t200\ttx1\t0\t+\t110\t190\t255,0,0\t2\t20,30,\t0,70,\n\
+                             chr1\t100\t200\ttx2\t0\t+\t100\t200\t0\t2\t20\t0,70\n";
+        let records: LineRecordStream<_, _, Bed12<LexicalChromRef>> =
+            input.into_record_iter(&chroms);
+        let results: Vec<_> = records.fallible().collect();
+        assert_eq!(results.len(), 2);
+
+        let first = results[0].as_ref().ok().unwrap();
+        assert_eq!(first.block_regions(), vec![(100, 120), (170, 200)]);
+        assert_eq!(first.item_rgb, Some((255, 0, 0)));
+        let blocks: Vec<_> = first.blocks().map(|b| (b.begin(), b.end())).collect();
+        assert_eq!(blocks, vec![(100, 120), (170, 200)]);
+
+        let mut buf = vec![];
+        first.dump(&mut buf).unwrap();
+        assert_eq!(
+            buf,
+            &b"chr1\t100\t200\ttx1\t0\t+\t110\t190\t255,0,0\t2\t20,30,\t0,70,"[..]
+        );
+
+        let err = results[1].as_ref().err().unwrap();
+        assert_eq!((err.line, err.column), (2, 11));
+    }
+
+    #[test]
+    fn test_bed5_scores() {
+        use crate::properties::{Serializable, WithName, WithScore};
+
+        let chroms = LexicalChromSet::new();
+        let input: &[u8] = b"chr1\t10\t20\ta\t0.75\nchr1\t15\t30\tb\t3\nchr1\t40\t50\tc\t.\n\
+                             chr1\t60\t70\td\t1000\nchr1\t80\t90\te\t2000\nchr1\t95\t99\tf\t100000\n\
+                             chr1\t100\t110\tg\t1e30\n";
+        let records: Vec<Bed5<LexicalChromRef>> = input.into_record_iter(&chroms).collect();
+        let scores: Vec<_> = records.iter().map(|r| r.score()).collect();
+        assert_eq!(
+            scores,
+            vec![
+                Some(0.75),
+                Some(3.0),
+                None,
+                Some(1000.0),
+                Some(2000.0),
+                Some(100000.0),
+                Some(1e30)
+            ]
+        );
+        assert_eq!(records[1].name(), "b");
+
+        let mut buf = vec![];
+        for record in records.iter() {
+            record.dump(&mut buf).unwrap();
+            buf.push(b'\n');
+        }
+        let output = String::from_utf8(buf).unwrap();
+        let lines: Vec<_> = output.lines().collect();
+        assert_eq!(lines[3], "chr1\t60\t70\td\t1000");
+        assert_eq!(lines[4], "chr1\t80\t90\te\t2000");
+        assert_eq!(lines[5], "chr1\t95\t99\tf\t100000");
+        assert_eq!(
+            lines[6],
+            format!("chr1\t100\t110\tg\t{}", "1".to_string() + &"0".repeat(30))
+        );
+        assert!(
+            output.starts_with("chr1\t10\t20\ta\t0.75\nchr1\t15\t30\tb\t3\nchr1\t40\t50\tc\t.\n")
+        );
+
+        let reparsed: Vec<Bed5<LexicalChromRef>> =
+            output.as_bytes().into_record_iter(&chroms).collect();
+        let rescores: Vec<_> = reparsed.iter().map(|r| r.score()).collect();
+        assert_eq!(rescores, scores);
+    }
+
+    #[test]
+    fn test_gff_stream() {
+        use crate::properties::{Serializable, WithName, WithRegionCore, WithScore};
+        use crate::records::{Gff3Record, GtfRecord};
+
+        let chroms = LexicalChromSet::new();
+        let input: &[u8] = b"##gff-version 3\n\
+                             chr1\tHAVANA\tgene\t11869\t14409\t.\t+\t.\tID=gene0;Name=DDX11L1\n\
+                             chr1\tHAVANA\texon\t0\t10\t.\t+\t.\tID=exon0\n";
+        let records: LineRecordStream<_, _, Gff3Record<LexicalChromRef>> =
+            input.into_record_iter(&chroms);
+        let results: Vec<_> = records.fallible().collect();
+        assert_eq!(results.len(), 2);
+
+        let gene = results[0].as_ref().ok().unwrap();
+        assert_eq!((gene.begin(), gene.end()), (11868, 14409));
+        assert_eq!(gene.name(), "DDX11L1");
+        assert_eq!(gene.attribute("ID"), Some("gene0"));
+        let mut buf = vec![];
+        gene.dump(&mut buf).unwrap();
+        assert_eq!(
+            buf,
+            &b"chr1\tHAVANA\tgene\t11869\t14409\t.\t+\t.\tID=gene0;Name=DDX11L1"[..]
+        );
+        let err = results[1].as_ref().err().unwrap();
+        assert_eq!((err.line, err.column), (3, 4));
+
+        let input: &[u8] = b"chr1\tensembl\tCDS\t100\t200\t0.5\t-\t2\tgene_id \"ENSG1\"; transcript_id \"ENST1\";\n";
+        let records: Vec<GtfRecord<LexicalChromRef>> = input.into_record_iter(&chroms).collect();
+        assert_eq!(records[0].name(), "ENSG1");
+        assert_eq!(records[0].attribute("transcript_id"), Some("ENST1"));
+        assert_eq!((records[0].score(), records[0].phase), (Some(0.5), Some(2)));
+
+        let input: &[u8] = b"chr1\t.\tgene\t1\t10\t.\t?\t.\tgene_id \"G1\";\n\
+                             chr1\t.\tgene\t1\t10\t.\t.\t.\tgene_id \"G2\";\n";
+        let records: Vec<GtfRecord<LexicalChromRef>> = input.into_record_iter(&chroms).collect();
+        let mut buf = vec![];
+        for record in records.iter() {
+            assert_eq!(record.strand, None);
+            record.dump(&mut buf).unwrap();
+            buf.push(b'\n');
+        }
+        assert_eq!(buf, input);
+    }
+
+    #[test]
+    fn test_vcf_stream() {
+        use crate::properties::{Serializable, WithName, WithRegionCore, WithScore};
+
+        let chroms = LexicalChromSet::new();
+        let input: &[u8] = b"##fileformat=VCFv4.2\n\
+                             #CHROM\tPOS\tID\tREF\tALT\tQUAL\tFILTER\tINFO\n\
+                             1\t714427\trs12028261\tGA\tG\t50\tPASS\tDP=12\n\
+                             1\t0\t.\tG\tA\t.\t.\t.\n";
+        let records: LineRecordStream<_, _, VcfLine<LexicalChromRef>> =
+            input.into_record_iter(&chroms);
+        let results: Vec<_> = records.fallible().collect();
+        assert_eq!(results.len(), 2);
+
+        let variant = results[0].as_ref().ok().unwrap();
+        assert_eq!((variant.begin(), variant.end()), (714426, 714428));
+        assert_eq!(variant.name(), "rs12028261");
+        assert_eq!((variant.score(), variant.info()), (Some(50.0), "DP=12"));
+        let mut buf = vec![];
+        variant.dump(&mut buf).unwrap();
+        assert_eq!(buf, &b"1\t714427\trs12028261\tGA\tG\t50\tPASS\tDP=12"[..]);
+        let err = results[1].as_ref().err().unwrap();
+        assert_eq!((err.line, err.column), (4, 2));
+    }
+
+    #[test]
+    fn test_peak_stream() {
+        use crate::properties::Serializable;
+
+        let chroms = LexicalChromSet::new();
+        let input: &[u8] = b"chr1\t100\t200\tpeak1\t500\t.\t5.5\t3.2\t1.5\t50\n";
+        let peaks: Vec<NarrowPeak<LexicalChromRef>> = input.into_record_iter(&chroms).collect();
+        assert_eq!(peaks[0].q_value(), 1.5);
+        assert_eq!(peaks[0].summit(), Some(150));
+        let mut buf = vec![];
+        peaks[0].dump(&mut buf).unwrap();
+        buf.push(b'\n');
+        assert_eq!(buf, input);
+
+        let input: &[u8] = b"chr1\t100\t200\tpeak1\t500\t.\t5.5\tx\t1.5\n";
+        let records: LineRecordStream<_, _, BroadPeak<LexicalChromRef>> =
+            input.into_record_iter(&chroms);
+        let results: Vec<_> = records.fallible().collect();
+        let err = results[0].as_ref().err().unwrap();
+        assert_eq!(err.column, 8);
+    }
+}
@@ -4,48 +4,45 @@ use plotters::{evcxr::SVGWrapper, prelude::*};
 
 use crate::{
     algorithm::{Components, ComponentsIter, Point},
-    chromset::LexicalChromRef,
     properties::{WithRegion, WithRegionCore},
-    records::Bed5,
+    records::BedGraph,
+    ChromName,
 };
 
-pub struct DepthIter<I: Iterator>
+pub struct DepthIter<C: ChromName, I: Iterator>
 where
-    I::Item: WithRegion<LexicalChromRef> + Clone,
+    I::Item: WithRegion<C> + Clone,
 {
-    last: Option<Point<LexicalChromRef, I::Item>>,
-    iter: ComponentsIter<LexicalChromRef, I>,
+    last: Option<Point<C, I::Item>>,
+    iter: ComponentsIter<C, I>,
 }
 
-impl<I: Iterator> Iterator for DepthIter<I>
+impl<C: ChromName, I: Iterator> Iterator for DepthIter<C, I>
 where
-    I::Item: WithRegion<LexicalChromRef> + Clone,
+    I::Item: WithRegion<C> + Clone,
 {
-    type Item = Bed5<LexicalChromRef, usize>;
+    type Item = BedGraph<C, usize>;
     fn next(&mut self) -> Option<Self::Item> {
-        let last = self.last.take()?;
-        if let Some(next) = self.iter.next() {
+        loop {
+            let last = self.last.take()?;
+            let next = self.iter.next()?;
             let (last_chr, last_pos) = last.position();
             let (next_chr, next_pos) = next.position();
-            if last_chr == next_chr {
-                let result = Bed5::new(last_chr, last_pos, next_pos, ".", last.depth);
-                self.last = Some(next);
-                return Some(result);
-            } else {
-                self.last = Some(next);
-                return self.next();
+            self.last = Some(next);
+            // The regions that begin or end at the same position make empty steps
+            if last_chr == next_chr && last_pos < next_pos {
+                return Some(BedGraph::new(last_chr, last_pos, next_pos, last.depth));
             }
         }
-        None
     }
 }
 
-pub trait DepthExt
+pub trait DepthExt<C: ChromName>
 where
     Self: IntoIterator + Sized,
-    <Self as IntoIterator>::Item: WithRegion<LexicalChromRef> + Clone,
+    <Self as IntoIterator>::Item: WithRegion<C> + Clone,
 {
-    fn coverage(self) -> DepthIter<Self::IntoIter> {
+    fn coverage(self) -> DepthIter<C, Self::IntoIter> {
         let mut iter = self.into_iter().components();
         let last = iter.next();
         DepthIter { iter, last }
@@ -167,7 +164,7 @@ where
             }
             let interval = (depth.begin(), depth.end());
             data.last_mut()
-                .map(|data: &mut DepthData| data.append_interval(interval, depth.score.unwrap()));
+                .map(|data: &mut DepthData| data.append_interval(interval, depth.value));
         }
 
         data.last_mut()
@@ -186,7 +183,7 @@ where
                 let ys = data.get_y_range();
 
                 let mut chart = plotters::prelude::ChartBuilder::on(&root)
-                    .caption(format!("{:?}", chrom), ("sans", 15))
+                    .caption(ChromName::to_string(&chrom), ("sans", 15))
                     .x_label_area_size(50)
                     .y_label_area_size(50)
                     .build_cartesian_2d(xs, ys)?;
@@ -205,9 +202,36 @@ where
     }
 }
 
-impl<T> DepthExt for T
+impl<C, T> DepthExt<C> for T
 where
+    C: ChromName,
     T: IntoIterator + Sized,
-    T::Item: WithRegion<LexicalChromRef> + Clone,
+    T::Item: WithRegion<C> + Clone,
 {
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{algorithm::AssumeSorted, records::Bed3};
+
+    #[test]
+    fn test_coverage_shared_boundaries() {
+        let regions = [(100, 200), (100, 300), (150, 200), (300, 400)];
+        let coverage: Vec<_> = regions
+            .iter()
+            .map(|&(begin, end)| Bed3 {
+                chrom: "chr1".to_string(),
+                begin,
+                end,
+            })
+            .assume_sorted()
+            .coverage()
+            .map(|r| (r.begin(), r.end(), r.value))
+            .collect();
+        assert_eq!(
+            coverage,
+            vec![(100, 150, 2), (150, 200, 3), (200, 300, 1), (300, 400, 1)]
+        );
+    }
+}
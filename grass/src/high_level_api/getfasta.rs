@@ -0,0 +1,189 @@
+use std::{
+    io::{ErrorKind, Result, Write},
+    marker::PhantomData,
+};
+
+use crate::{
+    algorithm::Sorted,
+    properties::{
+        Nuclide, Serializable, Strand, WithName, WithRegionCore, WithSequence, WithStrand,
+    },
+    records::{IndexedFasta, Sequence},
+    ChromName,
+};
+
+/// How the sequence records are written
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SequenceFormat {
+    /// A FASTA entry, `>name` followed by the sequence in a single line
+    Fasta,
+    /// The name and the sequence separated by a tab, like `bedtools getfasta -tab`
+    Tab,
+}
+
+/// A region with its sequence read from the reference
+#[derive(Clone)]
+pub struct SequenceRecord<R> {
+    pub region: R,
+    /// The name of the region, or `chrom:begin-end` if the region doesn't have a name
+    pub name: String,
+    pub sequence: Sequence,
+    pub format: SequenceFormat,
+}
+
+impl<R> SequenceRecord<R> {
+    /// Read the sequence of the region from the reference, the sequence of a region on the
+    /// negative strand is reverse complemented if `stranded` is set
+    pub fn fetch<C>(
+        fasta: &IndexedFasta,
+        region: R,
+        stranded: bool,
+        format: SequenceFormat,
+    ) -> Result<Self>
+    where
+        C: ChromName,
+        R: WithRegionCore<C> + WithName + WithStrand,
+    {
+        let sequence = if stranded {
+            fasta.fetch_stranded(&region)?
+        } else {
+            fasta.fetch(&region)?
+        };
+        let name = match region.name() {
+            "." | "" => format!(
+                "{}:{}-{}",
+                ChromName::to_string(region.chrom()),
+                region.begin(),
+                region.end()
+            ),
+            name => name.to_string(),
+        };
+        Ok(Self {
+            region,
+            name,
+            sequence,
+            format,
+        })
+    }
+}
+
+impl<C: ChromName, R: WithRegionCore<C>> WithRegionCore<C> for SequenceRecord<R> {
+    fn begin(&self) -> u32 {
+        self.region.begin()
+    }
+
+    fn end(&self) -> u32 {
+        self.region.end()
+    }
+
+    fn chrom(&self) -> &C {
+        self.region.chrom()
+    }
+}
+
+impl<R> WithName for SequenceRecord<R> {
+    fn name(&self) -> &str {
+        &self.name
+    }
+}
+
+impl<R: WithStrand> WithStrand for SequenceRecord<R> {
+    fn strand(&self) -> Option<Strand> {
+        self.region.strand()
+    }
+}
+
+impl<R> WithSequence for SequenceRecord<R> {
+    type RangeType = Sequence;
+    fn at(&self, offset: usize) -> Nuclide {
+        self.sequence.at(offset)
+    }
+
+    fn range(&self, from: usize, to: usize) -> Self::RangeType {
+        self.sequence.range(from, to)
+    }
+}
+
+impl<R> Serializable for SequenceRecord<R> {
+    fn dump<W: Write>(&self, mut fp: W) -> Result<()> {
+        match self.format {
+            SequenceFormat::Fasta => writeln!(fp, ">{}", self.name)?,
+            SequenceFormat::Tab => write!(fp, "{}\t", self.name)?,
+        }
+        fp.write_all(self.sequence.as_bytes())
+    }
+}
+
+/// Read the sequence of each region from the reference, the regions on the chromosomes that are
+/// not in the reference are skipped. The sequence is read when the record is produced, thus it
+/// panics when the reference can't be read.
+pub struct GetFasta<'a, C, I> {
+    iter: I,
+    fasta: &'a IndexedFasta,
+    format: SequenceFormat,
+    stranded: bool,
+    _phantom: PhantomData<C>,
+}
+
+impl<'a, C, I> GetFasta<'a, C, I> {
+    /// Reverse complement the sequence of the regions on the negative strand
+    pub fn stranded(mut self) -> Self {
+        self.stranded = true;
+        self
+    }
+
+    /// Write the records as the tab separated name and sequence, instead of FASTA
+    pub fn tab_separated(mut self) -> Self {
+        self.format = SequenceFormat::Tab;
+        self
+    }
+}
+
+impl<'a, C, I> Iterator for GetFasta<'a, C, I>
+where
+    C: ChromName,
+    I: Iterator,
+    I::Item: WithRegionCore<C> + WithName + WithStrand,
+{
+    type Item = SequenceRecord<I::Item>;
+    fn next(&mut self) -> Option<Self::Item> {
+        for region in self.iter.by_ref() {
+            match SequenceRecord::fetch(self.fasta, region, self.stranded, self.format) {
+                Ok(record) => return Some(record),
+                Err(e) if e.kind() == ErrorKind::NotFound => continue,
+                Err(e) => panic!("Unable to read the reference sequence: {}", e),
+            }
+        }
+        None
+    }
+}
+
+impl<'a, C, I> Sorted for GetFasta<'a, C, I>
+where
+    C: ChromName,
+    I: Iterator + Sorted,
+    I::Item: WithRegionCore<C> + WithName + WithStrand,
+{
+}
+
+pub trait GetFastaExt<C: ChromName>
+where
+    Self: IntoIterator + Sized,
+    Self::Item: WithRegionCore<C> + WithName + WithStrand,
+{
+    /// Read the sequence of each region from the indexed reference, like `bedtools getfasta`
+    fn get_fasta(self, fasta: &IndexedFasta) -> GetFasta<'_, C, Self::IntoIter> {
+        GetFasta {
+            iter: self.into_iter(),
+            fasta,
+            format: SequenceFormat::Fasta,
+            stranded: false,
+            _phantom: PhantomData,
+        }
+    }
+}
+
+impl<C: ChromName, T: IntoIterator + Sized> GetFastaExt<C> for T where
+    T::Item: WithRegionCore<C> + WithName + WithStrand
+{
+}
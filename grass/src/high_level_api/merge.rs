@@ -1,27 +1,35 @@
-use std::{collections::HashMap, hash::Hash, rc::Rc};
+use std::{
+    cmp::Reverse,
+    collections::{BinaryHeap, HashMap},
+    hash::Hash,
+    sync::Arc,
+};
 
 use crate::{
-    algorithm::{Components, ComponentsIter, Point, Sorted, TaggedComponent, TaggedComponentExt},
-    chromset::LexicalChromRef,
-    properties::{WithRegion, WithRegionCore},
-    records::{Bed3, Bed4},
+    algorithm::{
+        strand_slot, Components, ComponentsIter, Point, Sorted, TaggedComponent,
+        TaggedComponentExt, STRANDS,
+    },
+    properties::{WithRegion, WithRegionCore, WithStrand},
+    records::{Bed3, Bed4, Bed5, Bed6},
+    ChromName,
 };
 
 //use super::{Components, ComponentsIter, Point, Sorted};
 
-pub struct Merger<I: Iterator + Sorted>
+pub struct Merger<C: ChromName, I: Iterator + Sorted>
 where
-    I::Item: WithRegion<LexicalChromRef> + Clone,
+    I::Item: WithRegion<C> + Clone,
 {
-    peek: Option<Point<LexicalChromRef, I::Item>>,
-    iter: ComponentsIter<LexicalChromRef, I>,
+    peek: Option<Point<C, I::Item>>,
+    iter: ComponentsIter<C, I>,
 }
 
-impl<I: Iterator + Sorted> Iterator for Merger<I>
+impl<C: ChromName, I: Iterator + Sorted> Iterator for Merger<C, I>
 where
-    I::Item: WithRegion<LexicalChromRef> + Clone,
+    I::Item: WithRegion<C> + Clone,
 {
-    type Item = Bed3<LexicalChromRef>;
+    type Item = Bed3<C>;
     fn next(&mut self) -> Option<Self::Item> {
         let mut begin = None;
         let mut end;
@@ -45,19 +53,110 @@ where
     }
 }
 
-pub struct TaggedMerger<I, T> {
+/// The merged regions on each strand of a sorted stream, the regions on the different strands
+/// are never merged together.
+///
+/// A group is kept open for each strand and it's done once a record begins after its end. The
+/// groups that are done wait until no open group begins before them, so only a single record
+/// past the current position is read.
+pub struct StrandMerger<C: ChromName, I: Iterator + Sorted>
+where
+    I::Item: WithRegion<C> + WithStrand,
+{
+    iter: I,
+    /// The chromosome, begin and end of the open group of each strand slot
+    open: [Option<(C, u32, u32)>; 3],
+    /// The groups that are done, by the chromosome, begin, strand slot and end
+    done: BinaryHeap<Reverse<(C, u32, usize, u32)>>,
+}
+
+impl<C: ChromName, I: Iterator + Sorted> StrandMerger<C, I>
+where
+    I::Item: WithRegion<C> + WithStrand,
+{
+    fn close(&mut self, slot: usize) {
+        if let Some((chrom, begin, end)) = self.open[slot].take() {
+            self.done.push(Reverse((chrom, begin, slot, end)));
+        }
+    }
+}
+
+impl<C: ChromName, I: Iterator + Sorted> Iterator for StrandMerger<C, I>
+where
+    I::Item: WithRegion<C> + WithStrand,
+{
+    type Item = Bed6<C>;
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(Reverse((chrom, begin, _, _))) = self.done.peek() {
+                let blocked = self
+                    .open
+                    .iter()
+                    .flatten()
+                    .any(|(open_chrom, open_begin, _)| (open_chrom, open_begin) < (chrom, begin));
+                if !blocked {
+                    let Reverse((chrom, begin, slot, end)) = self.done.pop().unwrap();
+                    return Some(Bed6 {
+                        core: Bed5 {
+                            core: Bed4 {
+                                core: Bed3 { chrom, begin, end },
+                                name: Arc::new(".".to_string()),
+                            },
+                            score: None,
+                        },
+                        strand: STRANDS[slot],
+                    });
+                }
+            }
+
+            let item = match self.iter.next() {
+                Some(item) => item,
+                None => {
+                    (0..STRANDS.len()).for_each(|slot| self.close(slot));
+                    if self.done.is_empty() {
+                        return None;
+                    }
+                    continue;
+                }
+            };
+
+            // The groups that end before this record can't grow anymore
+            for slot in 0..STRANDS.len() {
+                let is_done = self.open[slot]
+                    .as_ref()
+                    .is_some_and(|(chrom, _, end)| chrom != item.chrom() || *end < item.begin());
+                if is_done {
+                    self.close(slot);
+                }
+            }
+
+            match &mut self.open[strand_slot(item.strand())] {
+                Some((_, _, end)) => *end = (*end).max(item.end()),
+                open => *open = Some((item.chrom().clone(), item.begin(), item.end())),
+            }
+        }
+    }
+}
+
+impl<C: ChromName, I: Iterator + Sorted> Sorted for StrandMerger<C, I> where
+    I::Item: WithRegion<C> + WithStrand
+{
+}
+
+pub struct TaggedMerger<C, I, T> {
     iter: I,
-    chrom: Option<LexicalChromRef>,
+    chrom: Option<C>,
     begins: HashMap<T, u32>,
 }
 
-impl<I, R, T> Iterator for TaggedMerger<I, T>
+impl<C, I, R, T> Iterator for TaggedMerger<C, I, T>
 where
-    I: Iterator<Item = (T, Point<LexicalChromRef, R>)>,
-    R: WithRegion<LexicalChromRef> + Clone,
+    C: ChromName,
+    I: Iterator<Item = (T, Point<C, R>)>,
+    R: WithRegion<C> + Clone,
     T: ToString + Eq + Hash,
 {
-    type Item = Bed4<LexicalChromRef>;
+    type Item = Bed4<C>;
     fn next(&mut self) -> Option<Self::Item> {
         while let Some((tag, comp)) = self.iter.next() {
             let (chr, pos) = comp.position();
@@ -74,7 +173,7 @@ where
                     };
                     let result = Bed4 {
                         core,
-                        name: Rc::new(tag.to_string()),
+                        name: Arc::new(tag.to_string()),
                     };
                     self.begins.remove(&tag);
                     return Some(result);
@@ -87,30 +186,35 @@ where
     }
 }
 
-pub trait MergeExt
+pub trait MergeExt<C: ChromName>
 where
     Self: IntoIterator + Sized,
     <Self as IntoIterator>::IntoIter: Sorted,
-    <Self as IntoIterator>::Item: WithRegion<LexicalChromRef> + Clone,
+    <Self as IntoIterator>::Item: WithRegion<C> + Clone,
 {
-    fn merge_overlaps(self) -> Merger<<Self as IntoIterator>::IntoIter> {
+    fn merge_overlaps(self) -> Merger<C, <Self as IntoIterator>::IntoIter> {
         let mut iter = self.into_iter().components();
         let peek = iter.next();
         Merger { iter, peek }
     }
+    /// Merge the overlapping regions on each strand separately, the regions on the different
+    /// strands are never merged together.
+    fn merge_overlaps_by_strand(self) -> StrandMerger<C, Self::IntoIter>
+    where
+        Self::Item: WithStrand,
+    {
+        StrandMerger {
+            iter: self.into_iter(),
+            open: Default::default(),
+            done: BinaryHeap::new(),
+        }
+    }
+
     fn tagged_merge<T: Clone + Hash + Eq, F: FnMut(&Self::Item) -> T>(
         self,
         f: F,
-    ) -> TaggedMerger<
-        TaggedComponent<
-            LexicalChromRef,
-            ComponentsIter<LexicalChromRef, Self::IntoIter>,
-            Self::Item,
-            T,
-            F,
-        >,
-        T,
-    > {
+    ) -> TaggedMerger<C, TaggedComponent<C, ComponentsIter<C, Self::IntoIter>, Self::Item, T, F>, T>
+    {
         TaggedMerger {
             iter: self.into_iter().components().with_tag(f),
             begins: HashMap::new(),
@@ -118,9 +222,9 @@ where
         }
     }
 }
-impl<T: IntoIterator + Sized> MergeExt for T
+impl<C: ChromName, T: IntoIterator + Sized> MergeExt<C> for T
 where
     T::IntoIter: Sorted,
-    T::Item: WithRegion<LexicalChromRef> + Clone,
+    T::Item: WithRegion<C> + Clone,
 {
 }
@@ -11,6 +11,9 @@ use self::properties::Intersection;
 
 pub use super::*;
 
+// So that the DSL can split the spliced reads before counting them, e.g. `split_blocks() | coverage()`
+pub use crate::algorithm::SplitBlocks;
+
 mod open_helper;
 pub use open_helper::*;
 
@@ -26,9 +29,17 @@ pub use depth::*;
 mod subtract;
 pub use subtract::*;
 
-// TODO: because we use unsafe cell, so that we actually need a mutex inside the chromset for
-// thread safety. But we currently doesn't have any. as long as we are currently single
-// threaded, it's Ok for now but we definitely should change it later
+mod parallel;
+pub use parallel::*;
+
+mod getfasta;
+pub use getfasta::*;
+
+mod save;
+pub use save::*;
+
+// LexicalChromSet is not thread safe, so the global chrom set is per thread. Use
+// SharedChromSet for the records that are processed by multiple threads.
 thread_local! {
     static CHROM_NAMES : LexicalChromSet = {
         LexicalChromSet::new()
@@ -1,16 +1,17 @@
 use super::CHROM_NAMES;
 use crate::{
-    algorithm::{AssumeSorted, AssumingSortedIter},
+    algorithm::{CheckSorted, CheckedSortedIter, Sorted},
     chromset::LexicalChromRef,
-    records::{Bed3, Bed4, Bed5},
+    records::{Bed12, Bed3, Bed4, Bed5, Bed6, BedN},
     LineRecordStreamExt,
 };
-use std::path::Path;
+use libflate::gzip::MultiDecoder;
+use std::{cell::RefCell, fs::File, io::Result, path::Path, rc::Rc};
 macro_rules! define_open_helper {
         ($name:ident, $($record_type:tt)*) => {
-            pub fn $name<P: AsRef<Path>>(path: P) -> AssumingSortedIter<impl Iterator<Item = $($record_type)*>> {
+            pub fn $name<P: AsRef<Path>>(path: P) -> CheckedSortedIter<LexicalChromRef, impl Iterator<Item = $($record_type)*>> {
                 CHROM_NAMES.with(|chrom_names| {
-                    std::fs::File::open(path).map(|file| file.into_record_iter::<$($record_type)*, _>(chrom_names).assume_sorted())
+                    std::fs::File::open(path).map(|file| file.into_record_iter::<$($record_type)*, _>(chrom_names).check_sorted())
                 }).unwrap()
             }
         };
@@ -18,3 +19,49 @@ macro_rules! define_open_helper {
 define_open_helper!(open_sorted_bed3, Bed3);
 define_open_helper!(open_sorted_bed4, Bed4);
 define_open_helper!(open_sorted_bed5, Bed5<LexicalChromRef, f64>);
+define_open_helper!(open_sorted_bed6, Bed6<LexicalChromRef, f64>);
+define_open_helper!(open_sorted_bed12, Bed12<LexicalChromRef, f64>);
+define_open_helper!(open_sorted_bedn, BedN);
+
+/// Open a gzip compressed file, all the gzip members are decompressed, so a BGZF file, which is
+/// a member per 64 KB block, is read to the end
+pub fn open_gzip<P: AsRef<Path>>(path: P) -> Result<MultiDecoder<File>> {
+    MultiDecoder::new(File::open(path)?)
+}
+
+/// The stream that reports the error that stops it, see `report_error`
+pub struct ReportError<I, E, F> {
+    iter: I,
+    slot: Rc<RefCell<Option<E>>>,
+    report: F,
+}
+
+/// Call `report` with the error in the slot of the stream once the stream ends. The streams
+/// only keep the error that stops them in their slot, so this is for the callers that don't
+/// check the slot afterwards, such as the code generated for the queries.
+pub fn report_error<I, E, F>(
+    iter: I,
+    slot: Rc<RefCell<Option<E>>>,
+    report: F,
+) -> ReportError<I, E, F>
+where
+    I: Iterator,
+    F: FnMut(E),
+{
+    ReportError { iter, slot, report }
+}
+
+impl<I: Iterator, E, F: FnMut(E)> Iterator for ReportError<I, E, F> {
+    type Item = I::Item;
+    fn next(&mut self) -> Option<Self::Item> {
+        let item = self.iter.next();
+        if item.is_none() {
+            if let Some(e) = self.slot.borrow_mut().take() {
+                (self.report)(e);
+            }
+        }
+        item
+    }
+}
+
+impl<I: Sorted, E, F: FnMut(E)> Sorted for ReportError<I, E, F> {}
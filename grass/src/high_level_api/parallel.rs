@@ -0,0 +1,219 @@
+use crate::{
+    algorithm::{
+        run_by_chrom, split_by_chrom, zip_by_chrom, AssumeSorted, ByChromIter, ChromPartitions,
+        PairedPartitions, ParallelConfig, Sorted, SortedIntersect,
+    },
+    properties::WithRegion,
+    records::{Bed3, BedGraph},
+    ChromName,
+};
+
+use super::{DepthExt, MergeExt, SubtractExt};
+
+/// The result of `par_sorted_intersect`
+pub type ParIntersectIter<C, A, B> =
+    ByChromIter<PairedPartitions<C, A, B>, (<A as Iterator>::Item, <B as Iterator>::Item)>;
+
+/// The result of `par_subtract`
+pub type ParSubtractIter<C, A, B> =
+    ByChromIter<PairedPartitions<C, A, B>, (<A as Iterator>::Item, Bed3<C>)>;
+
+/// Run the sorted operations on each chromosome in parallel.
+///
+/// The input is split at the chromosome boundaries, each chromosome is processed by a worker
+/// thread and the results are concatenated in the `ChromSet` order, thus the records must be
+/// `Send`, for example the records using `SharedChromSet`. The input is read one chromosome at
+/// a time, so only the chromosomes being processed are buffered in memory.
+pub trait ParallelExt<C>
+where
+    Self: Iterator + Sorted + Sized,
+    C: ChromName + Send + 'static,
+    Self::Item: WithRegion<C> + Clone + Send + 'static,
+{
+    fn par_merge_overlaps(
+        self,
+        config: &ParallelConfig,
+    ) -> ByChromIter<ChromPartitions<C, Self>, Bed3<C>> {
+        run_by_chrom(split_by_chrom(self), config, |part| {
+            MergeExt::<C>::merge_overlaps(part.records.into_iter().assume_sorted()).collect()
+        })
+    }
+
+    fn par_coverage(
+        self,
+        config: &ParallelConfig,
+    ) -> ByChromIter<ChromPartitions<C, Self>, BedGraph<C, usize>> {
+        run_by_chrom(split_by_chrom(self), config, |part| {
+            DepthExt::<C>::coverage(part.records).collect()
+        })
+    }
+
+    fn par_sorted_intersect<O>(
+        self,
+        other: O,
+        config: &ParallelConfig,
+    ) -> ParIntersectIter<C, Self, O>
+    where
+        O: Iterator + Sorted,
+        O::Item: WithRegion<C> + Clone + Send + 'static,
+    {
+        let parts = zip_by_chrom(split_by_chrom(self), split_by_chrom(other));
+        run_by_chrom(parts, config, |part| {
+            let (a, b) = part.records;
+            if a.is_empty() || b.is_empty() {
+                return vec![];
+            }
+            let a = a.into_iter().assume_sorted();
+            a.sorted_intersect(b.into_iter().assume_sorted()).collect()
+        })
+    }
+
+    fn par_subtract<O>(self, other: O, config: &ParallelConfig) -> ParSubtractIter<C, Self, O>
+    where
+        O: Iterator + Sorted,
+        O::Item: WithRegion<C> + Clone + Send + 'static,
+    {
+        let parts = zip_by_chrom(split_by_chrom(self), split_by_chrom(other));
+        run_by_chrom(parts, config, |part| {
+            let (a, b) = part.records;
+            if a.is_empty() {
+                return vec![];
+            }
+            let a = a.into_iter().assume_sorted();
+            SubtractExt::<C>::subtract(a, b.into_iter().assume_sorted()).collect()
+        })
+    }
+}
+
+impl<C, T> ParallelExt<C> for T
+where
+    T: Iterator + Sorted + Sized,
+    C: ChromName + Send + 'static,
+    T::Item: WithRegion<C> + Clone + Send + 'static,
+{
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        chromset::SharedChromRef, properties::WithRegionCore, ChromSet, ChromSetHandle,
+        SharedChromSet,
+    };
+
+    fn make_records(
+        chrom_set: &SharedChromSet,
+        regions: &[(&str, u32, u32)],
+    ) -> Vec<Bed3<SharedChromRef>> {
+        let mut handle = chrom_set.get_handle();
+        regions
+            .iter()
+            .map(|&(chrom, begin, end)| Bed3 {
+                chrom: handle.query_or_insert(chrom),
+                begin,
+                end,
+            })
+            .collect()
+    }
+
+    fn dump<T: WithRegion<SharedChromRef>>(
+        records: impl Iterator<Item = T>,
+    ) -> Vec<(String, u32, u32)> {
+        records
+            .map(|r| (r.chrom().as_str().to_string(), r.begin(), r.end()))
+            .collect()
+    }
+
+    #[test]
+    fn test_parallel_by_chrom() {
+        let chrom_set = SharedChromSet::new();
+        let config = ParallelConfig { num_threads: 3 };
+        let a = make_records(
+            &chrom_set,
+            &[
+                ("chr1", 10, 20),
+                ("chr1", 15, 30),
+                ("chr2", 5, 10),
+                ("chr3", 0, 100),
+            ],
+        );
+        let b = make_records(&chrom_set, &[("chr1", 25, 40), ("chr3", 50, 60)]);
+
+        let sequential = dump(a.clone().into_iter().assume_sorted().merge_overlaps());
+        let parallel = dump(
+            a.clone()
+                .into_iter()
+                .assume_sorted()
+                .par_merge_overlaps(&config),
+        );
+        assert_eq!(sequential, parallel);
+        assert_eq!(parallel.len(), 3);
+
+        let sequential = dump(a.clone().into_iter().assume_sorted().coverage());
+        let parallel = dump(a.clone().into_iter().assume_sorted().par_coverage(&config));
+        assert_eq!(sequential, parallel);
+
+        let intersect: Vec<_> = a
+            .clone()
+            .into_iter()
+            .assume_sorted()
+            .par_sorted_intersect(b.clone().into_iter().assume_sorted(), &config)
+            .map(|(x, y)| (x.begin(), y.begin()))
+            .collect();
+        assert_eq!(intersect, vec![(15, 25), (0, 50)]);
+
+        let sequential: Vec<_> = a
+            .clone()
+            .into_iter()
+            .assume_sorted()
+            .subtract(b.clone().into_iter().assume_sorted())
+            .map(|(x, y)| (x.begin(), y.begin(), y.end()))
+            .collect();
+        let subtract: Vec<_> = a
+            .into_iter()
+            .assume_sorted()
+            .par_subtract(b.into_iter().assume_sorted(), &config)
+            .map(|(x, y)| (x.begin(), y.begin(), y.end()))
+            .collect();
+        assert_eq!(
+            subtract,
+            vec![
+                (10, 0, 25),
+                (15, 0, 25),
+                (5, 0, u32::MAX),
+                (0, 0, 50),
+                (0, 60, u32::MAX)
+            ]
+        );
+        assert_eq!(sequential, subtract);
+    }
+
+    #[test]
+    fn test_parallel_streaming() {
+        let chrom_set = SharedChromSet::new();
+        let config = ParallelConfig { num_threads: 2 };
+        let names: Vec<_> = (0..40).map(|idx| format!("chr{:02}", idx)).collect();
+        let regions: Vec<_> = names
+            .iter()
+            .flat_map(|name| (0..10).map(move |idx| (name.as_str(), idx * 10, idx * 10 + 15)))
+            .collect();
+        let records = make_records(&chrom_set, &regions);
+
+        let read = std::cell::Cell::new(0);
+        let mut merged = records
+            .iter()
+            .cloned()
+            .inspect(|_| read.set(read.get() + 1))
+            .assume_sorted()
+            .par_merge_overlaps(&config);
+
+        // Only the partitions in flight are read for the first result
+        let first = merged.next().unwrap();
+        assert_eq!(first.chrom().as_str(), "chr00");
+        assert!(read.get() < records.len());
+
+        let rest: Vec<_> = merged.map(|r| r.chrom().as_str().to_string()).collect();
+        assert_eq!(read.get(), records.len());
+        assert_eq!(rest, names[1..].to_vec());
+    }
+}
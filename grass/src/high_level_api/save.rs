@@ -0,0 +1,137 @@
+use std::{
+    fs::File,
+    io::{BufWriter, Error, ErrorKind, Result, Write},
+    path::Path,
+};
+
+use libflate::gzip::Encoder;
+
+use crate::{
+    properties::Serializable,
+    tabix::{BgzfWriter, TabixConfig, TabixIndex, TabixIndexBuilder},
+};
+
+/// How the saved file is compressed
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Compression {
+    Plain,
+    /// A single gzip member, which can't be indexed
+    Gzip,
+    /// BGZF, which can be read by any gzip reader and indexed by tabix
+    Bgzf,
+}
+
+impl Compression {
+    /// The files ending with `.gz` or `.bgz` are written as BGZF, the others are not compressed
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Self {
+        match path.as_ref().extension().and_then(|ext| ext.to_str()) {
+            Some("gz") | Some("bgz") => Compression::Bgzf,
+            _ => Compression::Plain,
+        }
+    }
+}
+
+/// The options of `ShowExt::save_with`
+#[derive(Clone, Debug, Default)]
+pub struct SaveOptions {
+    compression: Option<Compression>,
+    index: Option<TabixConfig>,
+    header: Option<String>,
+}
+
+impl SaveOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Override the compression detected from the file extension
+    pub fn compression(mut self, compression: Compression) -> Self {
+        self.compression = Some(compression);
+        self
+    }
+
+    /// Write the tabix index to `<path>.tbi` along with the file, which is BGZF compressed
+    /// unless another compression is set. The records must be sorted.
+    pub fn tabix(mut self, config: TabixConfig) -> Self {
+        self.index = Some(config);
+        self
+    }
+
+    /// Write a header line before the records, such as the track line made by
+    /// `BedGraph::track_line`
+    pub fn header(mut self, header: &str) -> Self {
+        self.header = Some(header.to_string());
+        self
+    }
+}
+
+fn write_records<W: Write, I>(mut out: W, records: I, header: Option<&str>) -> Result<W>
+where
+    I: IntoIterator,
+    I::Item: Serializable,
+{
+    if let Some(header) = header {
+        out.write_all(header.as_bytes())?;
+        out.write_all(b"\n")?;
+    }
+    for item in records {
+        item.dump(&mut out)?;
+        out.write_all(b"\n")?;
+    }
+    Ok(out)
+}
+
+/// Save the records to the file, see `ShowExt::save_with`
+pub fn save_records<P: AsRef<Path>, I>(records: I, path: P, options: &SaveOptions) -> Result<()>
+where
+    I: IntoIterator,
+    I::Item: Serializable,
+{
+    let path = path.as_ref();
+    let compression = match (options.compression, options.index) {
+        (Some(compression), _) => compression,
+        (None, Some(_)) => Compression::Bgzf,
+        (None, None) => Compression::from_path(path),
+    };
+    if options.index.is_some() && compression != Compression::Bgzf {
+        return Err(Error::new(
+            ErrorKind::InvalidInput,
+            "only the BGZF compressed file can be indexed",
+        ));
+    }
+
+    let out = BufWriter::new(File::create(path)?);
+    let header = options.header.as_deref();
+    match compression {
+        Compression::Plain => write_records(out, records, header)?.flush(),
+        Compression::Gzip => {
+            let encoder = write_records(Encoder::new(out)?, records, header)?;
+            encoder.finish().into_result()?.flush()
+        }
+        Compression::Bgzf => {
+            let mut out = BgzfWriter::new(out);
+            let mut builder = options.index.map(TabixIndexBuilder::new);
+            let mut line = vec![];
+            if let Some(header) = header {
+                line.extend_from_slice(header.as_bytes());
+                line.push(b'\n');
+                out.write_all(&line)?;
+            }
+            for item in records {
+                line.clear();
+                item.dump(&mut line)?;
+                line.push(b'\n');
+                let begin = out.virtual_offset();
+                out.write_all(&line)?;
+                if let Some(builder) = builder.as_mut() {
+                    builder.push(&line, begin, out.virtual_offset())?;
+                }
+            }
+            out.finish()?;
+            if let Some(builder) = builder {
+                builder.finish().write_tbi(TabixIndex::index_path(path))?;
+            }
+            Ok(())
+        }
+    }
+}
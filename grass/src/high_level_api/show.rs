@@ -1,8 +1,7 @@
 use std::{
     cell::RefCell,
     fmt::{Debug, Formatter},
-    fs::File,
-    io::{BufWriter, Write},
+    io::Write,
     iter::Take,
     marker::PhantomData,
     ops::{Add, Range},
@@ -11,8 +10,10 @@ use std::{
 
 use num::iter::RangeFrom;
 
+use super::{save_records, SaveOptions};
 use crate::{
     properties::{Intersection, Serializable, WithRegionCore},
+    tabix::TabixConfig,
     ChromName,
 };
 
@@ -57,16 +58,34 @@ where
             iter: RefCell::new(self.into_iter()),
         }
     }
+    /// Save the records to the file, which is BGZF compressed if the path ends with `.gz`
     fn save<P: AsRef<Path>>(self, path: P) -> std::io::Result<()>
     where
         Self::Item: Serializable,
     {
-        let mut out = BufWriter::new(File::create(path)?);
-        for item in self {
-            item.dump(&mut out)?;
-            out.write_all("\n".as_bytes())?;
-        }
-        Ok(())
+        save_records(self, path, &SaveOptions::new())
+    }
+    /// Save the records after a header line, such as the track line made by
+    /// `BedGraph::track_line`
+    fn save_with_header<P: AsRef<Path>>(self, path: P, header: &str) -> std::io::Result<()>
+    where
+        Self::Item: Serializable,
+    {
+        save_records(self, path, &SaveOptions::new().header(header))
+    }
+    /// Save the sorted BED like records as BGZF along with the tabix index
+    fn save_indexed<P: AsRef<Path>>(self, path: P) -> std::io::Result<()>
+    where
+        Self::Item: Serializable,
+    {
+        save_records(self, path, &SaveOptions::new().tabix(TabixConfig::bed()))
+    }
+    /// Save the records with explicit compression, header and index options
+    fn save_with<P: AsRef<Path>>(self, path: P, options: &SaveOptions) -> std::io::Result<()>
+    where
+        Self::Item: Serializable,
+    {
+        save_records(self, path, options)
     }
 }
 
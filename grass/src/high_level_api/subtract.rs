@@ -1,8 +1,17 @@
-use std::{cell::RefCell, iter::Peekable, rc::Rc};
+use std::{
+    cell::RefCell,
+    cmp::Reverse,
+    collections::{BTreeMap, BinaryHeap, HashMap},
+    iter::Peekable,
+    rc::Rc,
+};
 
 use crate::{
-    algorithm::{Components, ComponentsIter, Sorted, SortedIntersect, SortedIntersectIter},
-    properties::{WithRegion, WithRegionCore},
+    algorithm::{
+        strand_slot, Components, ComponentsIter, Sorted, SortedIntersect, SortedIntersectIter,
+        StrandMode,
+    },
+    properties::{WithRegion, WithRegionCore, WithStrand},
     records::Bed3,
     ChromName,
 };
@@ -148,14 +157,14 @@ where
         if core
             .known_chrom
             .last()
-            .map_or(true, |last| last < ret.chrom())
+            .is_none_or(|last| last < ret.chrom())
         {
             core.known_chrom.push(ret.chrom().clone());
         }
 
         if let Some(next) = core.iter_a.peek() {
             let chrom = next.chrom().clone();
-            if core.known_chrom.last().map_or(true, |last| last < &chrom) {
+            if core.known_chrom.last().is_none_or(|last| last < &chrom) {
                 core.known_chrom.push(chrom);
             }
         }
@@ -174,6 +183,7 @@ where
 {
     core: Rc<RefCell<SubstractData<C, A, B>>>,
     last_chrom: Option<usize>,
+    chrom_seen: bool,
 }
 
 impl<C, A, B> Sorted for SubstractIterB<C, A, B>
@@ -197,37 +207,227 @@ where
     type Item = Bed3<C>;
     fn next(&mut self) -> Option<Self::Item> {
         let core = &mut self.core.borrow_mut();
-        let current_chrom = self
-            .last_chrom
-            .map_or(core.known_chrom.get(0), |id| core.known_chrom.get(id))?
-            .clone();
-        while let Some(next_chrom) = core.iter_b.peek().map(|x| x.chrom().clone()) {
-            if next_chrom < current_chrom {
-                core.iter_b.next();
-            } else if next_chrom == current_chrom {
-                let next = core.iter_b.next()?;
-                return Some(next);
-            } else {
-                break;
+        loop {
+            let idx = self.last_chrom.unwrap_or(0);
+            let current_chrom = core.known_chrom.get(idx)?.clone();
+            self.last_chrom = Some(idx);
+
+            while let Some(next_chrom) = core.iter_b.peek().map(|x| x.chrom().clone()) {
+                if next_chrom < current_chrom {
+                    core.iter_b.next();
+                } else if next_chrom == current_chrom {
+                    self.chrom_seen = true;
+                    return core.iter_b.next();
+                } else {
+                    break;
+                }
             }
+
+            // The chromosome that doesn't present in B isn't subtracted at all
+            if !self.chrom_seen {
+                self.chrom_seen = true;
+                return Some(Bed3 {
+                    chrom: current_chrom,
+                    begin: 0,
+                    end: u32::MAX,
+                });
+            }
+
+            if idx + 1 >= core.known_chrom.len() {
+                // The chromosome after this one is known only when A reads into it, so while A
+                // isn't done, stay at the end of this chromosome with an empty region
+                core.iter_a.peek()?;
+                return Some(Bed3 {
+                    chrom: current_chrom,
+                    begin: u32::MAX,
+                    end: u32::MAX,
+                });
+            }
+            self.last_chrom = Some(idx + 1);
+            self.chrom_seen = false;
         }
+    }
+}
 
-        if self.last_chrom.map_or(core.known_chrom.len() > 0, |cid| {
-            cid < core.known_chrom.len() - 1
-        }) {
-            let idx = self.last_chrom.map_or(0, |x| x + 1);
-            self.last_chrom = Some(idx);
-            return Some(Bed3 {
-                chrom: core.known_chrom[idx].clone(),
-                begin: 0,
-                end: u32::MAX,
+/// A record of A that is being subtracted by `subtract_with_strand`
+struct StrandedRecord<T> {
+    item: T,
+    /// The strand slot of the records of B that are subtracted from it
+    watch: Option<usize>,
+    /// The begin of the piece that isn't covered by B, `None` while it's covered
+    start: Option<u32>,
+}
+
+enum SweepEvent {
+    OpenA,
+    OpenB,
+    CloseA,
+    CloseB(usize),
+}
+
+/// The result of `subtract_with_strand`.
+///
+/// Both streams are swept together by position, reading a single record ahead on each side,
+/// while the depth of B is tracked for each strand. The pieces of a record of A that aren't
+/// covered by B are done as soon as B covers them or the record ends, and they wait until no
+/// open piece begins before them.
+pub struct StrandedSubtractIter<C: ChromName, A: Iterator, B: Iterator> {
+    iter_a: Peekable<A>,
+    iter_b: Peekable<B>,
+    mode: StrandMode,
+    chrom: Option<C>,
+    /// The records of A that are open, by the order they are read
+    active: HashMap<u64, StrandedRecord<A::Item>>,
+    next_id: u64,
+    /// The ends of the open records of A
+    ends_a: BinaryHeap<Reverse<(u32, u64)>>,
+    /// The ends of the open records of B of each strand slot
+    ends_b: [BinaryHeap<Reverse<u32>>; 3],
+    /// The pieces that are done, by the chromosome, begin and the record they come from
+    done: BTreeMap<(C, u32, u64), (A::Item, Bed3<C>)>,
+}
+
+impl<C: ChromName, A: Iterator, B: Iterator> StrandedSubtractIter<C, A, B>
+where
+    A::Item: WithRegion<C> + WithStrand,
+    B::Item: WithRegion<C> + WithStrand,
+{
+    fn next_event(&mut self) -> Option<SweepEvent> {
+        let close = self
+            .ends_a
+            .peek()
+            .map(|Reverse((end, _))| (*end, SweepEvent::CloseA));
+        let close = self
+            .ends_b
+            .iter()
+            .enumerate()
+            .filter_map(|(slot, ends)| ends.peek().map(|Reverse(end)| (*end, slot)))
+            .fold(close, |close, (end, slot)| match close {
+                Some((min, _)) if min <= end => close,
+                _ => Some((end, SweepEvent::CloseB(slot))),
             });
+
+        let open_a = self.iter_a.peek().map(|a| (a.chrom(), a.begin()));
+        let open_b = self.iter_b.peek().map(|b| (b.chrom(), b.begin()));
+        let open = match (open_a, open_b) {
+            (Some(a), Some(b)) if b < a => Some((b, SweepEvent::OpenB)),
+            (Some(a), _) => Some((a, SweepEvent::OpenA)),
+            (None, Some(b)) => Some((b, SweepEvent::OpenB)),
+            (None, None) => None,
+        };
+
+        match (close, open) {
+            (Some((end, close)), Some((key, _))) if (self.chrom.as_ref().unwrap(), end) <= key => {
+                Some(close)
+            }
+            (_, Some((_, open))) => Some(open),
+            (close, None) => close.map(|(_, close)| close),
         }
+    }
 
-        None
+    fn enter(&mut self, chrom: &C) {
+        if self.chrom.as_ref() != Some(chrom) {
+            self.chrom = Some(chrom.clone());
+        }
+    }
+
+    fn add_piece(&mut self, id: u64, item: A::Item, begin: u32, end: u32) {
+        let chrom = self.chrom.clone().unwrap();
+        let piece = Bed3 {
+            chrom: chrom.clone(),
+            begin,
+            end,
+        };
+        self.done.insert((chrom, begin, id), (item, piece));
     }
 }
 
+impl<C: ChromName, A: Iterator, B: Iterator> Iterator for StrandedSubtractIter<C, A, B>
+where
+    A::Item: WithRegion<C> + WithStrand + Clone,
+    B::Item: WithRegion<C> + WithStrand,
+{
+    type Item = (A::Item, Bed3<C>);
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(((chrom, begin, _), _)) = self.done.first_key_value() {
+                let blocked = self.active.values().any(|record| {
+                    record.start.is_some_and(|start| {
+                        (self.chrom.as_ref().unwrap(), start) < (chrom, *begin)
+                    })
+                });
+                if !blocked {
+                    return self.done.pop_first().map(|(_, piece)| piece);
+                }
+            }
+
+            match self.next_event()? {
+                SweepEvent::OpenA => {
+                    let item = self.iter_a.next().unwrap();
+                    self.enter(item.chrom());
+                    let watch = self.mode.matching_slot(strand_slot(item.strand()));
+                    let covered = watch.is_some_and(|slot| !self.ends_b[slot].is_empty());
+                    let id = self.next_id;
+                    self.next_id += 1;
+                    self.ends_a.push(Reverse((item.end(), id)));
+                    let start = if covered { None } else { Some(item.begin()) };
+                    self.active
+                        .insert(id, StrandedRecord { item, watch, start });
+                }
+                SweepEvent::CloseA => {
+                    let Reverse((end, id)) = self.ends_a.pop().unwrap();
+                    let record = self.active.remove(&id).unwrap();
+                    if let Some(start) = record.start.filter(|start| *start < end) {
+                        self.add_piece(id, record.item, start, end);
+                    }
+                }
+                SweepEvent::OpenB => {
+                    let item = self.iter_b.next().unwrap();
+                    self.enter(item.chrom());
+                    // An empty region doesn't cover anything
+                    if item.end() <= item.begin() {
+                        continue;
+                    }
+                    let slot = strand_slot(item.strand());
+                    if self.ends_b[slot].is_empty() {
+                        let begin = item.begin();
+                        let mut pieces = vec![];
+                        for (id, record) in self.active.iter_mut() {
+                            if record.watch != Some(slot) {
+                                continue;
+                            }
+                            if let Some(start) = record.start.take().filter(|s| *s < begin) {
+                                pieces.push((*id, record.item.clone(), start));
+                            }
+                        }
+                        for (id, item, start) in pieces {
+                            self.add_piece(id, item, start, begin);
+                        }
+                    }
+                    self.ends_b[slot].push(Reverse(item.end()));
+                }
+                SweepEvent::CloseB(slot) => {
+                    let Reverse(end) = self.ends_b[slot].pop().unwrap();
+                    if self.ends_b[slot].is_empty() {
+                        for record in self.active.values_mut() {
+                            if record.watch == Some(slot) {
+                                record.start = Some(end);
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl<C: ChromName, A: Iterator, B: Iterator> Sorted for StrandedSubtractIter<C, A, B>
+where
+    A::Item: WithRegion<C> + WithStrand + Clone,
+    B::Item: WithRegion<C> + WithStrand,
+{
+}
+
 pub trait SubtractExt<C: ChromName>
 where
     Self: Iterator + Sorted + Sized,
@@ -254,10 +454,34 @@ where
         let iter_b = SubstractIterB {
             core: core_data,
             last_chrom: None,
+            chrom_seen: false,
         };
 
         iter_a.sorted_intersect(iter_b)
     }
+
+    /// Subtract the regions of B that are on the matching strands only.
+    ///
+    /// Each result pairs the record of A with a piece of it that isn't covered by B, unlike
+    /// `subtract` which pairs it with the whole gap of B.
+    fn subtract_with_strand<T>(self, other: T, mode: StrandMode) -> StrandedSubtractIter<C, Self, T>
+    where
+        T: Iterator + Sorted,
+        T::Item: WithRegion<C> + WithStrand,
+        Self::Item: WithStrand,
+    {
+        StrandedSubtractIter {
+            iter_a: self.peekable(),
+            iter_b: other.peekable(),
+            mode,
+            chrom: None,
+            active: HashMap::new(),
+            next_id: 0,
+            ends_a: BinaryHeap::new(),
+            ends_b: Default::default(),
+            done: BTreeMap::new(),
+        }
+    }
 }
 
 impl<C: ChromName, T> SubtractExt<C> for T
@@ -266,3 +490,132 @@ where
     Self::Item: WithRegion<C> + Clone,
 {
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::algorithm::AssumeSorted;
+
+    fn subtract(a: &[(&str, u32, u32)], b: &[(&str, u32, u32)]) -> Vec<(String, u32, u32)> {
+        let to_bed3 = |regions: &[(&str, u32, u32)]| -> Vec<Bed3<String>> {
+            regions
+                .iter()
+                .map(|&(chrom, begin, end)| Bed3 {
+                    chrom: chrom.to_string(),
+                    begin,
+                    end,
+                })
+                .collect()
+        };
+        let a = to_bed3(a).into_iter().assume_sorted();
+        let b = to_bed3(b).into_iter().assume_sorted();
+        a.subtract(b)
+            .map(|(a, b)| (a.chrom, a.begin.max(b.begin), a.end.min(b.end)))
+            .collect()
+    }
+
+    #[test]
+    fn test_subtract_chroms_missing_from_b() {
+        // chr2 isn't in B, so it's kept as is, while the chromosomes around it are subtracted
+        let a = [("chr1", 10, 20), ("chr2", 10, 20), ("chr3", 10, 20)];
+        let b = [("chr1", 15, 16), ("chr3", 0, 12)];
+        assert_eq!(
+            subtract(&a, &b),
+            vec![
+                ("chr1".to_string(), 10, 15),
+                ("chr1".to_string(), 16, 20),
+                ("chr2".to_string(), 10, 20),
+                ("chr3".to_string(), 12, 20),
+            ]
+        );
+
+        // B only has the chromosome after all the chromosomes of A
+        let a = [("chr1", 10, 20), ("chr2", 10, 20)];
+        let b = [("chr3", 0, 100)];
+        assert_eq!(
+            subtract(&a, &b),
+            vec![("chr1".to_string(), 10, 20), ("chr2".to_string(), 10, 20)]
+        );
+    }
+
+    #[test]
+    fn test_subtract_streaming() {
+        let a: Vec<_> = (0..1000)
+            .map(|idx| Bed3 {
+                chrom: "chr1".to_string(),
+                begin: idx * 10,
+                end: idx * 10 + 5,
+            })
+            .collect();
+        let b = vec![Bed3 {
+            chrom: "chr1".to_string(),
+            begin: 0,
+            end: 2,
+        }];
+
+        let read = std::cell::Cell::new(0);
+        let mut result = a
+            .iter()
+            .cloned()
+            .inspect(|_| read.set(read.get() + 1))
+            .assume_sorted()
+            .subtract(b.into_iter().assume_sorted());
+
+        // Only the records around the first result are read from A
+        let (first, _) = result.next().unwrap();
+        assert_eq!(first.begin, 0);
+        assert!(read.get() < 10);
+
+        assert_eq!(result.count(), 999);
+        assert_eq!(read.get(), 1000);
+    }
+
+    #[test]
+    fn test_subtract_random_regions() {
+        let mut seed = 0x2545_f491_4f6c_dd1d_u64;
+        let mut rand = move |limit: u32| {
+            seed ^= seed << 13;
+            seed ^= seed >> 7;
+            seed ^= seed << 17;
+            (seed % limit as u64) as u32
+        };
+        let mut random_regions = |chroms: &[&'static str]| {
+            let mut regions = vec![];
+            for &chrom in chroms {
+                for _ in 0..rand(20) {
+                    let begin = rand(200);
+                    regions.push((chrom, begin, begin + 1 + rand(30)));
+                }
+            }
+            regions.sort();
+            regions
+        };
+
+        for _ in 0..50 {
+            let a = random_regions(&["chr1", "chr2", "chr3", "chr4"]);
+            let b = random_regions(&["chr2", "chr3", "chr5"]);
+
+            let mut expected = vec![];
+            for &(chrom, begin, end) in a.iter() {
+                let covered =
+                    |pos: u32| b.iter().any(|&(c, b, e)| c == chrom && b <= pos && pos < e);
+                let mut pos = begin;
+                while pos < end {
+                    let piece_begin = pos;
+                    while pos < end && !covered(pos) {
+                        pos += 1;
+                    }
+                    if piece_begin < pos {
+                        expected.push((chrom.to_string(), piece_begin, pos));
+                    }
+                    pos += 1;
+                }
+            }
+            expected.sort();
+
+            let mut result = subtract(&a, &b);
+            result.sort();
+            assert_eq!(result, expected, "a = {:?}, b = {:?}", a, b);
+        }
+    }
+}
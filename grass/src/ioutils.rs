@@ -1,4 +1,6 @@
+use std::fmt::Display;
 use std::io::{Result, Write};
+
 pub(crate) fn write_number<W: Write>(mut fp: W, mut n: i32) -> Result<()> {
     if n == 0 {
         fp.write_all(b"0")
@@ -31,3 +33,10 @@ pub(crate) fn write_number<W: Write>(mut fp: W, mut n: i32) -> Result<()> {
         fp.write_all(&buf[..offset])
     }
 }
+
+/// Write a score in the plain `Display` form, which is already the shortest string that parses
+/// back to the same value, e.g. `0.75`, `3` and `1000`. Scientific notation is never used since
+/// most genome browsers and tools reject it in score columns.
+pub(crate) fn write_score<W: Write, N: Display>(mut fp: W, score: &N) -> Result<()> {
+    write!(fp, "{}", score)
+}
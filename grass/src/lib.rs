@@ -6,14 +6,24 @@ pub mod properties;
 pub mod records;
 
 pub mod chromset;
-pub use chromset::{ChromName, ChromSet, ChromSetHandle, LexicalChromSet, WithChromSet};
+pub use chromset::{
+    AliasedChromSet, ChromName, ChromSet, ChromSetHandle, GenomeChromSet, LexicalChromSet,
+    NaturalChromSet, SharedChromSet, WithChromSet,
+};
 
 mod file;
-pub use file::{LineRecordStream, LineRecordStreamExt};
+pub use file::{
+    ErrorSlot, FallibleLineRecordStream, LineRecord, LineRecordStream, LineRecordStreamExt,
+    ParseError, ParseErrorReason, ParsePolicy,
+};
 
 pub(crate) mod ioutils;
 
 pub mod high_level_api;
 
+pub mod query;
+
+pub mod tabix;
+
 #[cfg(feature = "grass-macros")]
 pub use grass_macros::{grass_query, grass_query_block};
@@ -2,8 +2,17 @@ use crate::{records::Bed3, ChromName};
 use num::Num;
 use std::io::{Result, Write};
 
+/// The 1-based column and the reason of a line that can't be parsed, the column is 0 if the
+/// failure isn't in a particular column
+pub type ParseFailure = (usize, String);
+
 pub trait Parsable<'a>: Sized {
-    fn parse(s: &'a str) -> Option<(Self, usize)>;
+    fn parse(s: &'a str) -> Option<(Self, usize)> {
+        Self::try_parse(s).ok()
+    }
+
+    /// Parse the line and tell why it fails
+    fn try_parse(s: &'a str) -> std::result::Result<(Self, usize), ParseFailure>;
 }
 
 pub trait Serializable {
@@ -156,17 +165,105 @@ pub trait WithScore<T: Num> {
     }
 }
 
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
 pub enum Strand {
     Neg,
     Pos,
 }
 
+impl Strand {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Strand::Pos => "+",
+            Strand::Neg => "-",
+        }
+    }
+}
+
+impl std::fmt::Display for Strand {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// The value of a strand column, which is `+`, `-` or `.` for the unknown strand.
+///
+/// Out of the column, the unknown strand is `None`, thus a strand is an `Option<Strand>` in
+/// [`WithStrand`] and the records, and it's converted from and to the column with this type.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StrandColumn {
+    Known(Strand),
+    Unknown,
+}
+
+impl StrandColumn {
+    /// Parse the strand column, `None` if the column is neither `+`, `-` nor `.`
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "+" => Some(StrandColumn::Known(Strand::Pos)),
+            "-" => Some(StrandColumn::Known(Strand::Neg)),
+            "." => Some(StrandColumn::Unknown),
+            _ => None,
+        }
+    }
+
+    pub fn strand(self) -> Option<Strand> {
+        match self {
+            StrandColumn::Known(strand) => Some(strand),
+            StrandColumn::Unknown => None,
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            StrandColumn::Known(strand) => strand.as_str(),
+            StrandColumn::Unknown => ".",
+        }
+    }
+}
+
+impl From<Option<Strand>> for StrandColumn {
+    fn from(strand: Option<Strand>) -> Self {
+        strand.map_or(StrandColumn::Unknown, StrandColumn::Known)
+    }
+}
+
+impl std::fmt::Display for StrandColumn {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// The records with a strand, `None` means the strand is unknown, see [`StrandColumn`]
 pub trait WithStrand {
     fn strand(&self) -> Option<Strand> {
         None
     }
 }
 
+/// The records made of blocks, like the exons of a transcript in a BED12 file
+pub trait WithBlocks<Chrom: ChromName>: WithRegionCore<Chrom> {
+    /// The regions of the blocks, the records without a block structure have a single block
+    /// that covers the whole record
+    fn block_regions(&self) -> Vec<(u32, u32)> {
+        vec![(self.begin(), self.end())]
+    }
+
+    /// Check if the blocks of the two records overlap, the gaps between the blocks, such as
+    /// introns, don't count
+    fn overlaps_blocks(&self, other: &impl WithBlocks<Chrom>) -> bool {
+        if self.chrom() != other.chrom() {
+            return false;
+        }
+        let other_blocks = other.block_regions();
+        self.block_regions().iter().any(|&(begin, end)| {
+            other_blocks
+                .iter()
+                .any(|&(other_begin, other_end)| begin < other_end && other_begin < end)
+        })
+    }
+}
+
 impl<A: Serializable, B: Serializable> Serializable for (A, B) {
     fn dump<W: Write>(&self, mut fp: W) -> Result<()> {
         self.0.dump(&mut fp)?;
@@ -175,6 +272,7 @@ impl<A: Serializable, B: Serializable> Serializable for (A, B) {
     }
 }
 
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 pub enum Nuclide {
     A,
     T,
@@ -184,6 +282,42 @@ pub enum Nuclide {
     N,
 }
 
+impl Nuclide {
+    /// Convert a base in the sequence file, the case is ignored and the ambiguous bases are N
+    pub fn from_byte(byte: u8) -> Self {
+        match byte.to_ascii_uppercase() {
+            b'A' => Nuclide::A,
+            b'T' => Nuclide::T,
+            b'C' => Nuclide::C,
+            b'G' => Nuclide::G,
+            b'U' => Nuclide::U,
+            _ => Nuclide::N,
+        }
+    }
+
+    /// The complementary DNA base
+    pub fn complement(self) -> Self {
+        match self {
+            Nuclide::A => Nuclide::T,
+            Nuclide::T | Nuclide::U => Nuclide::A,
+            Nuclide::C => Nuclide::G,
+            Nuclide::G => Nuclide::C,
+            Nuclide::N => Nuclide::N,
+        }
+    }
+
+    pub fn as_char(self) -> char {
+        match self {
+            Nuclide::A => 'A',
+            Nuclide::T => 'T',
+            Nuclide::C => 'C',
+            Nuclide::G => 'G',
+            Nuclide::U => 'U',
+            Nuclide::N => 'N',
+        }
+    }
+}
+
 pub trait WithSequence {
     type RangeType: IntoIterator<Item = Nuclide>;
     fn at(&self, offset: usize) -> Nuclide;
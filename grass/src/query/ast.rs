@@ -0,0 +1,89 @@
+/// The location of a syntax node in the query source, as (line, column)
+pub(super) type Pos = (usize, usize);
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(super) enum UnaryOp {
+    Not,
+    Neg,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(super) enum BinaryOp {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Rem,
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    And,
+    Or,
+}
+
+#[derive(Debug, Clone)]
+pub(super) enum ExprKind {
+    Int(i64),
+    Float(f64),
+    Str(String),
+    Bool(bool),
+    Ident(String),
+    Field(Box<Expr>, String),
+    TupleIndex(Box<Expr>, usize),
+    MethodCall(Box<Expr>, String, Vec<Expr>),
+    Call(String, Vec<Expr>),
+    Unary(UnaryOp, Box<Expr>),
+    Binary(BinaryOp, Box<Expr>, Box<Expr>),
+    Cast(Box<Expr>, String),
+    Range(Option<Box<Expr>>, Option<Box<Expr>>),
+}
+
+/// The rust-like expression used by the where, map and invoked operators
+#[derive(Debug, Clone)]
+pub(super) struct Expr {
+    pub kind: ExprKind,
+    pub pos: Pos,
+}
+
+/// A statement in the body of a map operator, for example `_0.begin -= 10`
+#[derive(Debug, Clone)]
+pub(super) enum MapStmt {
+    Assign {
+        target: Expr,
+        op: Option<BinaryOp>,
+        value: Expr,
+    },
+    Expr(Expr),
+}
+
+#[derive(Debug)]
+pub(super) enum Operator {
+    Where(Expr),
+    Map(Vec<MapStmt>),
+    Invoke(String, Vec<Expr>, Pos),
+}
+
+/// QueryExpr :=
+///      open(<StrLit> [, <StrLit>])
+///     intersect(<QueryExpr>, *)
+///     left_outter_intersect(<QueryExpr>, <QueryExpr>)
+///     <id>
+///     <QueryExpr> | <Operator>
+#[derive(Debug)]
+pub(super) enum QueryExpr {
+    /// The path and the region to read with the index
+    Open(String, Option<String>, Pos),
+    Intersect(Vec<QueryExpr>, Pos),
+    LeftOutterIntersect(Vec<QueryExpr>, Pos),
+    VarRef(String, Pos),
+    OpChain(Box<QueryExpr>, Operator),
+}
+
+#[derive(Debug)]
+pub(super) enum QueryStmt {
+    Expr(QueryExpr),
+    Let(String, QueryExpr),
+}
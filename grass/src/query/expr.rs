@@ -0,0 +1,636 @@
+use std::{
+    cmp::Ordering,
+    fmt::{Display, Formatter, Result as FmtResult},
+    io::Write,
+    ops::Range,
+    rc::Rc,
+};
+
+use super::{
+    ast::{BinaryOp, Expr, ExprKind, MapStmt, Pos, UnaryOp},
+    record::{DynRecord, Interval},
+    QueryError,
+};
+use crate::{
+    high_level_api::{Fraction, Original, Overlap, PrintOpt, S},
+    properties::{Intersection, WithName, WithRegionCore},
+    ChromName,
+};
+
+type Result<T> = std::result::Result<T, QueryError>;
+
+/// The printing config of the cat, head and tail operators, which is the runtime counterpart of
+/// the `PrintOpt` combinators, e.g. `Overlap + S("|") + Original(0)`
+#[derive(Clone, Debug)]
+pub(super) enum DynPrintOpt {
+    Overlap,
+    Str(String),
+    Original(usize),
+    OriginalRange(Range<usize>),
+    Fraction(usize),
+    Pair(Box<DynPrintOpt>, Box<DynPrintOpt>),
+    Delim(Box<DynPrintOpt>, String),
+}
+
+impl PrintOpt for DynPrintOpt {
+    fn print<W: Write, C: ChromName, D: Intersection<C>>(
+        &self,
+        intersection: &D,
+        delim: &str,
+        mut target: W,
+    ) -> std::io::Result<()> {
+        match self {
+            Self::Overlap => Overlap.print(intersection, delim, target),
+            Self::Str(text) => S(text).print(intersection, delim, target),
+            Self::Original(idx) => Original(*idx).print(intersection, delim, target),
+            Self::OriginalRange(range) => {
+                Original(range.clone()).print(intersection, delim, target)
+            }
+            Self::Fraction(idx) => Fraction(*idx).print(intersection, delim, target),
+            Self::Pair(first, second) => {
+                // Use a trait object, otherwise the writer type grows with the recursion
+                let target: &mut dyn Write = &mut target;
+                first.print(intersection, delim, &mut *target)?;
+                second.append(intersection, delim, target)
+            }
+            Self::Delim(inner, delim) => inner.print(intersection, delim, target),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub(super) enum Value {
+    Int(i64),
+    Float(f64),
+    Bool(bool),
+    Str(Rc<str>),
+    Null,
+    /// A reference to the record under evaluation, `None` means `_0` and `Some(n)` means `_{n + 1}`
+    Record(Option<usize>),
+    Range(usize, Option<usize>),
+    PrintOpt(DynPrintOpt),
+}
+
+impl Display for Value {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        match self {
+            Self::Int(value) => write!(f, "{}", value),
+            Self::Float(value) => write!(f, "{}", value),
+            Self::Bool(value) => write!(f, "{}", value),
+            Self::Str(value) => write!(f, "{}", value),
+            Self::Null => write!(f, "None"),
+            Self::Record(None) => write!(f, "_0"),
+            Self::Record(Some(idx)) => write!(f, "_{}", idx + 1),
+            Self::Range(begin, Some(end)) => write!(f, "{}..{}", begin, end),
+            Self::Range(begin, None) => write!(f, "{}..", begin),
+            Self::PrintOpt(_) => write!(f, "<print option>"),
+        }
+    }
+}
+
+impl Value {
+    fn type_name(&self) -> &'static str {
+        match self {
+            Self::Int(_) => "integer",
+            Self::Float(_) => "float",
+            Self::Bool(_) => "bool",
+            Self::Str(_) => "string",
+            Self::Null => "None",
+            Self::Record(_) => "record",
+            Self::Range(..) => "range",
+            Self::PrintOpt(_) => "print option",
+        }
+    }
+
+    fn as_f64(&self) -> Option<f64> {
+        match self {
+            Self::Int(value) => Some(*value as f64),
+            Self::Float(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    pub(super) fn as_usize(&self) -> Option<usize> {
+        match self {
+            Self::Int(value) if *value >= 0 => Some(*value as usize),
+            _ => None,
+        }
+    }
+}
+
+/// The record a `_N` reference points to
+enum RecordRef<'a> {
+    Whole(&'a DynRecord),
+    Part(&'a Interval),
+}
+
+impl<'a> RecordRef<'a> {
+    fn resolve(record: &'a DynRecord, idx: Option<usize>) -> Option<Self> {
+        match idx {
+            None if record.parts().len() == 1 => Some(Self::Part(&record.parts()[0])),
+            None => Some(Self::Whole(record)),
+            Some(idx) => record.parts().get(idx).map(Self::Part),
+        }
+    }
+
+    fn region(&self) -> &dyn WithRegionCore<crate::chromset::LexicalChromRef> {
+        match self {
+            Self::Whole(record) => *record,
+            Self::Part(part) => *part,
+        }
+    }
+
+    fn access(&self, name: &str) -> Option<Value> {
+        let region = self.region();
+        let ret = match name {
+            "begin" => Value::Int(region.begin() as i64),
+            "end" => Value::Int(region.end() as i64),
+            "length" => Value::Int(region.length() as i64),
+            "empty" => Value::Bool(region.empty()),
+            "chrom" => Value::Str(ChromName::to_string(region.chrom()).into()),
+            "name" => match self {
+                Self::Part(part) => Value::Str(part.name().into()),
+                Self::Whole(_) => return None,
+            },
+            "score" => match self {
+                Self::Part(part) => part.score.map_or(Value::Null, Value::Float),
+                Self::Whole(_) => return None,
+            },
+            _ => return None,
+        };
+        Some(ret)
+    }
+}
+
+/// The environment an expression is evaluated in
+pub(super) struct EvalContext<'a> {
+    pub record: Option<&'a DynRecord>,
+}
+
+impl<'a> EvalContext<'a> {
+    pub fn eval(&self, expr: &Expr) -> Result<Value> {
+        match &expr.kind {
+            ExprKind::Int(value) => Ok(Value::Int(*value)),
+            ExprKind::Float(value) => Ok(Value::Float(*value)),
+            ExprKind::Bool(value) => Ok(Value::Bool(*value)),
+            ExprKind::Str(value) => Ok(Value::Str(value.as_str().into())),
+            ExprKind::Ident(id) => {
+                if let Some(idx) = parse_record_ref(id) {
+                    if self.record.is_none() {
+                        return error_at(
+                            expr.pos,
+                            format!("{} is only available in where and map", id),
+                        );
+                    }
+                    return Ok(self.record_ref(idx));
+                }
+                match id.as_str() {
+                    "None" => Ok(Value::Null),
+                    "Overlap" => Ok(Value::PrintOpt(DynPrintOpt::Overlap)),
+                    _ => error_at(expr.pos, format!("Unknown identifier {}", id)),
+                }
+            }
+            ExprKind::Field(base, name) => match self.eval(base)? {
+                Value::Record(idx) => self.resolve(idx, base)?.access(name).map_or_else(
+                    || error_at(expr.pos, format!("No field named {}", name)),
+                    Ok,
+                ),
+                other => error_at(
+                    expr.pos,
+                    format!("{} doesn't have any field", other.type_name()),
+                ),
+            },
+            ExprKind::TupleIndex(base, idx) => match self.eval(base)? {
+                Value::Record(None) => Ok(self.record_ref(Some(*idx))),
+                other => error_at(
+                    expr.pos,
+                    format!("Cannot take the tuple index of {}", other.type_name()),
+                ),
+            },
+            ExprKind::MethodCall(base, name, args) => {
+                let base_value = self.eval(base)?;
+                let args = args
+                    .iter()
+                    .map(|arg| self.eval(arg))
+                    .collect::<Result<Vec<_>>>()?;
+                self.call_method(base_value, base, name, args)
+                    .or_else(|message| error_at(expr.pos, message))
+            }
+            ExprKind::Call(name, args) => {
+                let args = args
+                    .iter()
+                    .map(|arg| self.eval(arg))
+                    .collect::<Result<Vec<_>>>()?;
+                call_function(name, args).or_else(|message| error_at(expr.pos, message))
+            }
+            ExprKind::Unary(op, operand) => match (op, self.eval(operand)?) {
+                (UnaryOp::Not, Value::Bool(value)) => Ok(Value::Bool(!value)),
+                (UnaryOp::Neg, Value::Int(value)) => Ok(Value::Int(-value)),
+                (UnaryOp::Neg, Value::Float(value)) => Ok(Value::Float(-value)),
+                (_, Value::Null) => Ok(Value::Null),
+                (op, value) => error_at(
+                    expr.pos,
+                    format!(
+                        "Cannot apply {} to {}",
+                        if *op == UnaryOp::Not { "!" } else { "-" },
+                        value.type_name()
+                    ),
+                ),
+            },
+            ExprKind::Binary(BinaryOp::And, lhs, rhs) => match self.eval(lhs)? {
+                Value::Bool(false) => Ok(Value::Bool(false)),
+                Value::Bool(true) => self.eval_bool(rhs).map(Value::Bool),
+                other => error_at(
+                    expr.pos,
+                    format!("Expecting bool, but {} found", other.type_name()),
+                ),
+            },
+            ExprKind::Binary(BinaryOp::Or, lhs, rhs) => match self.eval(lhs)? {
+                Value::Bool(true) => Ok(Value::Bool(true)),
+                Value::Bool(false) => self.eval_bool(rhs).map(Value::Bool),
+                other => error_at(
+                    expr.pos,
+                    format!("Expecting bool, but {} found", other.type_name()),
+                ),
+            },
+            ExprKind::Binary(op, lhs, rhs) => binary_op(*op, self.eval(lhs)?, self.eval(rhs)?)
+                .or_else(|message| error_at(expr.pos, message)),
+            ExprKind::Cast(operand, ty) => {
+                cast(self.eval(operand)?, ty).or_else(|message| error_at(expr.pos, message))
+            }
+            ExprKind::Range(begin, end) => {
+                let bound = |expr: &Option<Box<Expr>>| -> Result<Option<usize>> {
+                    match expr {
+                        Some(expr) => match self.eval(expr)? {
+                            value @ Value::Int(_) => value.as_usize().map(Some).map_or_else(
+                                || {
+                                    error_at(
+                                        expr.pos,
+                                        "Range bound must not be negative".to_string(),
+                                    )
+                                },
+                                Ok,
+                            ),
+                            other => error_at(
+                                expr.pos,
+                                format!(
+                                    "Range bound must be an integer, but {} found",
+                                    other.type_name()
+                                ),
+                            ),
+                        },
+                        None => Ok(None),
+                    }
+                };
+                let begin = bound(begin)?.unwrap_or(0);
+                let end = bound(end)?;
+                Ok(Value::Range(begin, end))
+            }
+        }
+    }
+
+    /// The missing part of a left outer intersection is None
+    fn record_ref(&self, idx: Option<usize>) -> Value {
+        match (idx, self.record) {
+            (Some(idx), Some(record)) if idx >= record.parts().len() => Value::Null,
+            _ => Value::Record(idx),
+        }
+    }
+
+    pub fn eval_bool(&self, expr: &Expr) -> Result<bool> {
+        match self.eval(expr)? {
+            Value::Bool(value) => Ok(value),
+            other => Err(QueryError::at(
+                expr.pos.0,
+                expr.pos.1,
+                format!("Expecting bool, but {} found", other.type_name()),
+            )),
+        }
+    }
+
+    fn resolve(&self, idx: Option<usize>, expr: &Expr) -> Result<RecordRef<'a>> {
+        let record = self.record.unwrap();
+        RecordRef::resolve(record, idx).ok_or_else(|| {
+            QueryError::at(
+                expr.pos.0,
+                expr.pos.1,
+                format!(
+                    "_{} is out of range, the record only has {} part(s)",
+                    idx.map_or(0, |idx| idx + 1),
+                    record.parts().len()
+                ),
+            )
+        })
+    }
+
+    fn call_method(
+        &self,
+        base: Value,
+        base_expr: &Expr,
+        name: &str,
+        args: Vec<Value>,
+    ) -> std::result::Result<Value, String> {
+        let expect_args = |n: usize| {
+            if args.len() != n {
+                Err(format!(
+                    "{} expects {} argument(s), but {} given",
+                    name,
+                    n,
+                    args.len()
+                ))
+            } else {
+                Ok(())
+            }
+        };
+        match (base, name) {
+            (Value::Record(_), "is_some") => Ok(Value::Bool(true)),
+            (Value::Record(_), "is_none") => Ok(Value::Bool(false)),
+            (Value::Record(idx), "field") => {
+                expect_args(1)?;
+                let column = args[0]
+                    .as_usize()
+                    .ok_or_else(|| format!("Invalid column index {}", args[0]))?;
+                match self.resolve(idx, base_expr).map_err(|e| e.message)? {
+                    RecordRef::Part(part) => Ok(part
+                        .field(column)
+                        .map_or(Value::Null, |value| Value::Str(value.into()))),
+                    RecordRef::Whole(_) => {
+                        Err("field is only available on a single part of the record".to_string())
+                    }
+                }
+            }
+            (Value::Record(idx), _) => {
+                expect_args(0)?;
+                let target = self.resolve(idx, base_expr).map_err(|e| e.message)?;
+                target
+                    .access(name)
+                    .ok_or_else(|| format!("No method named {} for the record", name))
+            }
+            (Value::Null, "unwrap") => Err("Called unwrap on a None value".to_string()),
+            (Value::Null, "unwrap_or") => {
+                expect_args(1)?;
+                Ok(args.into_iter().next().unwrap())
+            }
+            (Value::Null, "is_some") => Ok(Value::Bool(false)),
+            (Value::Null, "is_none") => Ok(Value::Bool(true)),
+            (value, "unwrap") | (value, "unwrap_or") => Ok(value),
+            (_, "is_some") => Ok(Value::Bool(true)),
+            (_, "is_none") => Ok(Value::Bool(false)),
+            (value, "to_string") => {
+                expect_args(0)?;
+                Ok(Value::Str(value.to_string().into()))
+            }
+            (Value::Str(value), "len") => Ok(Value::Int(value.len() as i64)),
+            (Value::Str(value), "starts_with" | "ends_with" | "contains") => {
+                expect_args(1)?;
+                let pattern = match &args[0] {
+                    Value::Str(pattern) => pattern.clone(),
+                    other => {
+                        return Err(format!(
+                            "{} expects a string, but {} found",
+                            name,
+                            other.type_name()
+                        ))
+                    }
+                };
+                Ok(Value::Bool(match name {
+                    "starts_with" => value.starts_with(pattern.as_ref()),
+                    "ends_with" => value.ends_with(pattern.as_ref()),
+                    _ => value.contains(pattern.as_ref()),
+                }))
+            }
+            (Value::Int(value), "abs") => Ok(Value::Int(value.abs())),
+            (Value::Float(value), "abs") => Ok(Value::Float(value.abs())),
+            (value @ Value::Int(_), "min" | "max") | (value @ Value::Float(_), "min" | "max") => {
+                expect_args(1)?;
+                let other = args.into_iter().next().unwrap();
+                let ordering = compare(&value, &other)?;
+                let take_self = (ordering != Ordering::Greater) == (name == "min");
+                Ok(if take_self { value } else { other })
+            }
+            (Value::PrintOpt(opt), "delim") => {
+                expect_args(1)?;
+                match &args[0] {
+                    Value::Str(delim) => Ok(Value::PrintOpt(DynPrintOpt::Delim(
+                        Box::new(opt),
+                        delim.to_string(),
+                    ))),
+                    other => Err(format!(
+                        "delim expects a string, but {} found",
+                        other.type_name()
+                    )),
+                }
+            }
+            (value, _) => Err(format!(
+                "No method named {} for {}",
+                name,
+                value.type_name()
+            )),
+        }
+    }
+
+    /// Run the body of a map operator on the record
+    pub fn run_map(stmts: &[MapStmt], mut record: DynRecord) -> Result<DynRecord> {
+        for stmt in stmts {
+            let (target, op, value) = match stmt {
+                MapStmt::Expr(expr) => {
+                    EvalContext {
+                        record: Some(&record),
+                    }
+                    .eval(expr)?;
+                    continue;
+                }
+                MapStmt::Assign { target, op, value } => (target, op, value),
+            };
+
+            let (base, field) = match &target.kind {
+                ExprKind::Field(base, field) if field == "begin" || field == "end" => (base, field),
+                _ => {
+                    return error_at(
+                        target.pos,
+                        "Only the begin and end of a record can be assigned",
+                    )
+                }
+            };
+
+            let ctx = EvalContext {
+                record: Some(&record),
+            };
+
+            let idx = match ctx.eval(base)? {
+                Value::Record(None) if record.parts().len() == 1 => 0,
+                Value::Record(Some(idx)) if idx < record.parts().len() => idx,
+                Value::Record(_) => {
+                    return error_at(target.pos, "Cannot assign to this record, use _1, _2, ... for the parts of an intersection")
+                }
+                _ => return error_at(target.pos, "Only the begin and end of a record can be assigned"),
+            };
+
+            let mut new_value = ctx.eval(value)?;
+            if let Some(op) = op {
+                let current = ctx.eval(target)?;
+                new_value = binary_op(*op, current, new_value)
+                    .or_else(|message| error_at(target.pos, message))?;
+            }
+
+            let new_value = match new_value {
+                Value::Int(value) if value >= 0 && value <= u32::MAX as i64 => value as u32,
+                Value::Int(value) => {
+                    return error_at(
+                        target.pos,
+                        format!("{} is out of range for the {} of a record", value, field),
+                    )
+                }
+                other => {
+                    return error_at(
+                        target.pos,
+                        format!("Expecting integer, but {} found", other.type_name()),
+                    )
+                }
+            };
+
+            let part = &mut record.parts_mut()[idx];
+            if field == "begin" {
+                part.begin = new_value;
+            } else {
+                part.end = new_value;
+            }
+        }
+        Ok(record)
+    }
+}
+
+fn error_at<T>(pos: Pos, message: impl Into<String>) -> Result<T> {
+    Err(QueryError::at(pos.0, pos.1, message))
+}
+
+/// Parse the `_N` identifiers in the closure style operators
+fn parse_record_ref(id: &str) -> Option<Option<usize>> {
+    let digits = id.strip_prefix('_')?;
+    if digits.is_empty() || !digits.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    let idx: usize = digits.parse().ok()?;
+    Some(idx.checked_sub(1))
+}
+
+fn call_function(name: &str, args: Vec<Value>) -> std::result::Result<Value, String> {
+    let opt = match (name, args.as_slice()) {
+        ("S", [Value::Str(text)]) => DynPrintOpt::Str(text.to_string()),
+        ("Original", [Value::Range(begin, end)]) => {
+            DynPrintOpt::OriginalRange(*begin..end.unwrap_or(usize::MAX))
+        }
+        ("Original", [idx]) if idx.as_usize().is_some() => {
+            DynPrintOpt::Original(idx.as_usize().unwrap())
+        }
+        ("Fraction", [idx]) if idx.as_usize().is_some() => {
+            DynPrintOpt::Fraction(idx.as_usize().unwrap())
+        }
+        ("Some", [value]) => return Ok(value.clone()),
+        ("S", _) | ("Original", _) | ("Fraction", _) | ("Some", _) => {
+            return Err(format!("Invalid arguments for {}", name))
+        }
+        _ => return Err(format!("Unknown function {}", name)),
+    };
+    Ok(Value::PrintOpt(opt))
+}
+
+fn compare(lhs: &Value, rhs: &Value) -> std::result::Result<Ordering, String> {
+    match (lhs, rhs) {
+        (Value::Int(a), Value::Int(b)) => Ok(a.cmp(b)),
+        (Value::Str(a), Value::Str(b)) => Ok(a.cmp(b)),
+        (Value::Bool(a), Value::Bool(b)) => Ok(a.cmp(b)),
+        (a, b) => match (a.as_f64(), b.as_f64()) {
+            (Some(a), Some(b)) => a
+                .partial_cmp(&b)
+                .ok_or_else(|| "Cannot compare NaN".to_string()),
+            _ => Err(format!(
+                "Cannot compare {} with {}",
+                a.type_name(),
+                b.type_name()
+            )),
+        },
+    }
+}
+
+fn binary_op(op: BinaryOp, lhs: Value, rhs: Value) -> std::result::Result<Value, String> {
+    use BinaryOp::*;
+
+    if let (Value::Null, _) | (_, Value::Null) = (&lhs, &rhs) {
+        return Ok(match op {
+            Eq => Value::Bool(matches!((&lhs, &rhs), (Value::Null, Value::Null))),
+            Ne => Value::Bool(!matches!((&lhs, &rhs), (Value::Null, Value::Null))),
+            Lt | Le | Gt | Ge => Value::Bool(false),
+            _ => Value::Null,
+        });
+    }
+
+    let ret = match op {
+        Eq | Ne | Lt | Le | Gt | Ge => {
+            let ordering = compare(&lhs, &rhs)?;
+            Value::Bool(match op {
+                Eq => ordering == Ordering::Equal,
+                Ne => ordering != Ordering::Equal,
+                Lt => ordering == Ordering::Less,
+                Le => ordering != Ordering::Greater,
+                Gt => ordering == Ordering::Greater,
+                _ => ordering != Ordering::Less,
+            })
+        }
+        Add | Sub | Mul | Div | Rem => match (lhs, rhs) {
+            (Value::Int(a), Value::Int(b)) => {
+                let ret = match op {
+                    Add => a.checked_add(b),
+                    Sub => a.checked_sub(b),
+                    Mul => a.checked_mul(b),
+                    Div => a.checked_div(b),
+                    _ => a.checked_rem(b),
+                };
+                Value::Int(
+                    ret.ok_or_else(|| "Arithmetic overflow or division by zero".to_string())?,
+                )
+            }
+            (Value::Str(a), Value::Str(b)) if op == Add => Value::Str(format!("{}{}", a, b).into()),
+            (Value::PrintOpt(a), Value::PrintOpt(b)) if op == Add => {
+                Value::PrintOpt(DynPrintOpt::Pair(Box::new(a), Box::new(b)))
+            }
+            (a, b) => match (a.as_f64(), b.as_f64()) {
+                (Some(a), Some(b)) => Value::Float(match op {
+                    Add => a + b,
+                    Sub => a - b,
+                    Mul => a * b,
+                    Div => a / b,
+                    _ => a % b,
+                }),
+                _ => {
+                    return Err(format!(
+                        "Unsupported operand types {} and {}",
+                        a.type_name(),
+                        b.type_name()
+                    ))
+                }
+            },
+        },
+        And | Or => unreachable!(),
+    };
+    Ok(ret)
+}
+
+fn cast(value: Value, ty: &str) -> std::result::Result<Value, String> {
+    let ret = match (ty, value) {
+        ("f64" | "f32", Value::Int(value)) => Value::Float(value as f64),
+        ("f64" | "f32", value @ Value::Float(_)) => value,
+        (
+            "i8" | "i16" | "i32" | "i64" | "isize" | "u8" | "u16" | "u32" | "u64" | "usize",
+            value,
+        ) => match value {
+            Value::Int(value) => Value::Int(value),
+            Value::Float(value) => Value::Int(value as i64),
+            Value::Bool(value) => Value::Int(value as i64),
+            other => return Err(format!("Cannot cast {} to {}", other.type_name(), ty)),
+        },
+        (_, Value::Null) => Value::Null,
+        (_, other) => return Err(format!("Cannot cast {} to {}", other.type_name(), ty)),
+    };
+    Ok(ret)
+}
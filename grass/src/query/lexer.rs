@@ -0,0 +1,203 @@
+use super::QueryError;
+
+#[derive(Debug, Clone, PartialEq)]
+pub(super) enum TokenKind {
+    Ident(String),
+    Int(i64),
+    Float(f64),
+    Str(String),
+    Punct(&'static str),
+    Eof,
+}
+
+#[derive(Debug, Clone)]
+pub(super) struct Token {
+    pub kind: TokenKind,
+    pub line: usize,
+    pub column: usize,
+}
+
+impl Token {
+    pub fn is_punct(&self, punct: &str) -> bool {
+        matches!(self.kind, TokenKind::Punct(p) if p == punct)
+    }
+    pub fn is_ident(&self, ident: &str) -> bool {
+        matches!(self.kind, TokenKind::Ident(ref id) if id == ident)
+    }
+}
+
+// Note: longer punctuations must go first
+const PUNCTS: &[&str] = &[
+    "&&", "||", "==", "!=", "<=", ">=", "+=", "-=", "*=", "/=", "..", "(", ")", "{", "}", "[", "]",
+    ",", ";", ".", "|", "=", "<", ">", "+", "-", "*", "/", "%", "!", "&",
+];
+
+struct Cursor<'a> {
+    text: &'a str,
+    offset: usize,
+    line: usize,
+    column: usize,
+}
+
+impl<'a> Cursor<'a> {
+    fn rest(&self) -> &'a str {
+        &self.text[self.offset..]
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.rest().chars().next()
+    }
+
+    fn bump(&mut self, n_bytes: usize) {
+        for c in self.text[self.offset..self.offset + n_bytes].chars() {
+            if c == '\n' {
+                self.line += 1;
+                self.column = 1;
+            } else {
+                self.column += 1;
+            }
+        }
+        self.offset += n_bytes;
+    }
+
+    fn error(&self, message: impl Into<String>) -> QueryError {
+        QueryError::at(self.line, self.column, message)
+    }
+
+    fn skip_whitespace_and_comments(&mut self) -> Result<(), QueryError> {
+        loop {
+            let rest = self.rest();
+            if let Some(c) = rest.chars().next().filter(|c| c.is_whitespace()) {
+                self.bump(c.len_utf8());
+            } else if rest.starts_with("//") {
+                let len = rest.find('\n').unwrap_or(rest.len());
+                self.bump(len);
+            } else if rest.starts_with("/*") {
+                match rest.find("*/") {
+                    Some(len) => self.bump(len + 2),
+                    None => return Err(self.error("Unterminated block comment")),
+                }
+            } else {
+                return Ok(());
+            }
+        }
+    }
+
+    fn lex_string(&mut self) -> Result<TokenKind, QueryError> {
+        let raw = self.rest().starts_with('r');
+        if raw {
+            self.bump(1);
+        }
+        self.bump(1);
+        let mut value = String::new();
+        loop {
+            let c = self
+                .peek()
+                .ok_or_else(|| self.error("Unterminated string literal"))?;
+            self.bump(c.len_utf8());
+            match c {
+                '"' => return Ok(TokenKind::Str(value)),
+                '\\' if !raw => {
+                    let escaped = self
+                        .peek()
+                        .ok_or_else(|| self.error("Unterminated string literal"))?;
+                    self.bump(escaped.len_utf8());
+                    value.push(match escaped {
+                        'n' => '\n',
+                        't' => '\t',
+                        'r' => '\r',
+                        '0' => '\0',
+                        other => other,
+                    });
+                }
+                other => value.push(other),
+            }
+        }
+    }
+
+    fn lex_number(&mut self) -> Result<TokenKind, QueryError> {
+        let rest = self.rest();
+        let mut len = rest
+            .find(|c: char| !c.is_ascii_digit() && c != '_')
+            .unwrap_or(rest.len());
+        let mut is_float = false;
+
+        // A dot followed by a digit is a part of the number, otherwise it's a method call or a tuple index
+        if rest[len..].starts_with('.') && rest[len + 1..].starts_with(|c: char| c.is_ascii_digit())
+        {
+            is_float = true;
+            len += 1;
+            len += rest[len..]
+                .find(|c: char| !c.is_ascii_digit() && c != '_')
+                .unwrap_or(rest.len() - len);
+        }
+
+        if rest[len..].starts_with(['e', 'E']) {
+            let exp = &rest[len + 1..];
+            let sign = if exp.starts_with(['+', '-']) { 1 } else { 0 };
+            if exp[sign..].starts_with(|c: char| c.is_ascii_digit()) {
+                is_float = true;
+                len += 1 + sign;
+                len += rest[len..]
+                    .find(|c: char| !c.is_ascii_digit())
+                    .unwrap_or(rest.len() - len);
+            }
+        }
+
+        let text: String = rest[..len].chars().filter(|&c| c != '_').collect();
+        let ret = if is_float {
+            text.parse().map(TokenKind::Float).ok()
+        } else {
+            text.parse().map(TokenKind::Int).ok()
+        };
+        let ret = ret.ok_or_else(|| self.error(format!("Invalid number literal: {}", text)))?;
+        self.bump(len);
+        Ok(ret)
+    }
+
+    fn next_token(&mut self) -> Result<Token, QueryError> {
+        self.skip_whitespace_and_comments()?;
+        let (line, column) = (self.line, self.column);
+        let rest = self.rest();
+        let kind = match rest.chars().next() {
+            None => TokenKind::Eof,
+            Some('"') => self.lex_string()?,
+            Some('r') if rest.starts_with("r\"") => self.lex_string()?,
+            Some(c) if c.is_ascii_digit() => self.lex_number()?,
+            Some(c) if c.is_alphabetic() || c == '_' => {
+                let len = rest
+                    .find(|c: char| !c.is_alphanumeric() && c != '_')
+                    .unwrap_or(rest.len());
+                self.bump(len);
+                TokenKind::Ident(rest[..len].to_string())
+            }
+            Some(c) => {
+                if let Some(punct) = PUNCTS.iter().find(|p| rest.starts_with(*p)) {
+                    self.bump(punct.len());
+                    TokenKind::Punct(punct)
+                } else {
+                    return Err(self.error(format!("Unexpected character '{}'", c)));
+                }
+            }
+        };
+        Ok(Token { kind, line, column })
+    }
+}
+
+pub(super) fn tokenize(text: &str) -> Result<Vec<Token>, QueryError> {
+    let mut cursor = Cursor {
+        text,
+        offset: 0,
+        line: 1,
+        column: 1,
+    };
+    let mut ret = vec![];
+    loop {
+        let token = cursor.next_token()?;
+        let is_eof = token.kind == TokenKind::Eof;
+        ret.push(token);
+        if is_eof {
+            return Ok(ret);
+        }
+    }
+}
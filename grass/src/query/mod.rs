@@ -0,0 +1,326 @@
+//! The runtime interpreter for the GRASS query language.
+//!
+//! Unlike the `grass_query!` macro, which generates the Rust code for the query at compile time,
+//! this module parses and evaluates the query when the program runs. Thus the query can be loaded
+//! from a `.grass` script file or a string, and the format of the input files is detected when
+//! they are opened.
+//!
+//! ```no_run
+//! grass::query::run_query(r#"
+//!     let a = open("data/a.bed");
+//!     let b = open("data/b.bed");
+//!     intersect(a, b) | where(_1.length() as f64 / _0.length() as f64 > 0.10) | show_all();
+//! "#).unwrap();
+//! ```
+//!
+//! Records flowing through the runtime are [`DynRecord`]s. The closure style operators (`where`
+//! and `map`) are evaluated with a small expression language: `_0` refers to the current record
+//! and `_1`, `_2`, ... refer to the original records of an intersection. The records provide
+//! `begin`, `end`, `chrom`, `length`, `empty`, `name` and `score`.
+use std::{
+    error::Error,
+    fmt::{Display, Formatter, Result as FmtResult},
+    io::stdout,
+    path::Path,
+};
+
+mod ast;
+mod expr;
+mod lexer;
+mod parser;
+mod record;
+mod runtime;
+
+pub use record::{DynRecord, Interval, IntoDynRecord};
+pub use runtime::QueryRuntime;
+
+/// An error happens when parsing or running a query
+#[derive(Debug, Clone)]
+pub struct QueryError {
+    /// The line and column in the query source that causes the error, if it's known
+    pub position: Option<(usize, usize)>,
+    pub message: String,
+}
+
+impl QueryError {
+    pub fn new(message: impl Into<String>) -> Self {
+        Self {
+            position: None,
+            message: message.into(),
+        }
+    }
+
+    pub(crate) fn at(line: usize, column: usize, message: impl Into<String>) -> Self {
+        Self {
+            position: Some((line, column)),
+            message: message.into(),
+        }
+    }
+}
+
+impl Display for QueryError {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        if let Some((line, column)) = self.position {
+            write!(f, "{}:{}: {}", line, column, self.message)
+        } else {
+            write!(f, "{}", self.message)
+        }
+    }
+}
+
+impl Error for QueryError {}
+
+impl From<std::io::Error> for QueryError {
+    fn from(e: std::io::Error) -> Self {
+        Self::new(e.to_string())
+    }
+}
+
+/// Run the query and write the results to the standard output
+pub fn run_query(query: &str) -> Result<(), QueryError> {
+    QueryRuntime::new(stdout()).run(query)
+}
+
+/// Run the `.grass` script file and write the results to the standard output
+pub fn run_query_file<P: AsRef<Path>>(path: P) -> Result<(), QueryError> {
+    QueryRuntime::new(stdout()).run_file(path)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn run_to_string(query: &str) -> Result<String, QueryError> {
+        let mut buf = Vec::new();
+        QueryRuntime::new(&mut buf).run(query)?;
+        Ok(String::from_utf8(buf).unwrap())
+    }
+
+    #[test]
+    fn test_run_query() -> Result<(), QueryError> {
+        let output = run_to_string(
+            r#"
+            let a = open("../data/a.bed");
+            let b = open("../data/b.bed");
+            intersect(a, b) | where(_0.length() >= 50 && _2.name() == "2836861") | show_all();
+            "#,
+        )?;
+        assert_eq!(
+            output,
+            "1\t5812\t5912\t|\t1\t5787\t5887\t2836861\n\
+             1\t5819\t5919\t|\t1\t5787\t5887\t2836861\n\
+             -------------------------------\n2 Rows Returned\n"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_query_errors() {
+        let err = run_to_string("let a = open(\"../data/a.bed\");\na | where(_0.length() >)")
+            .unwrap_err();
+        assert_eq!(err.position, Some((2, 24)));
+
+        let err = run_to_string(
+            r#"let a = open("../data/a.bed") | map({ _0.begin += 1; });
+            let b = open("../data/b.bed");
+            intersect(a, b)"#,
+        )
+        .unwrap_err();
+        assert!(err.message.contains("assume_sorted"));
+    }
+
+    #[test]
+    fn test_unsorted_input() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("unsorted.bed");
+        std::fs::write(&path, "chr2\t10\t20\nchr1\t30\t40\nchr1\t5\t15\n").unwrap();
+
+        let err = run_to_string(&format!(
+            "let a = open({:?});\na | merge_overlaps() | show_all()",
+            path
+        ))
+        .unwrap_err();
+        assert_eq!(err.position, Some((2, 5)));
+        assert!(err.message.contains("not sorted"));
+
+        let output = run_to_string(&format!(
+            "open({:?}) | sort() | merge_overlaps() | show_all()",
+            path
+        ))
+        .unwrap();
+        assert_eq!(
+            output,
+            "chr1\t5\t15\nchr1\t30\t40\nchr2\t10\t20\n\
+             -------------------------------\n3 Rows Returned\n"
+        );
+    }
+
+    #[test]
+    fn test_extra_columns() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("bedn.bed");
+        std::fs::write(
+            &path,
+            "chr1\t10\t20\tx\t0\t+\tfoo\t1.5\nchr1\t30\t40\ty\t0\t-\tbar\t2.5\n",
+        )
+        .unwrap();
+
+        let output = run_to_string(&format!(
+            "open({:?}) | where(_0.field(3) == \"bar\") | show_all()",
+            path
+        ))
+        .unwrap();
+        assert_eq!(
+            output,
+            "chr1\t30\t40\ty\t0\t-\tbar\t2.5\n\
+             -------------------------------\n1 Rows Returned\n"
+        );
+    }
+
+    #[test]
+    fn test_bedgraph() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("coverage.bed");
+        std::fs::write(&path, "chr1\t10\t20\nchr1\t15\t30\n").unwrap();
+        let output = run_to_string(&format!("open({:?}) | coverage() | show_all()", path)).unwrap();
+        assert_eq!(
+            output,
+            "chr1\t10\t15\t1\nchr1\t15\t20\t2\nchr1\t20\t30\t1\n\
+             -------------------------------\n3 Rows Returned\n"
+        );
+
+        let path = dir.path().join("track.bedgraph");
+        std::fs::write(
+            &path,
+            "track type=bedGraph\nchr1\t10\t20\t0.5\nchr1\t20\t30\t2.25\n",
+        )
+        .unwrap();
+        let output = run_to_string(&format!(
+            "open({:?}) | where(_0.score() > 1) | show_all()",
+            path
+        ))
+        .unwrap();
+        assert_eq!(
+            output,
+            "chr1\t20\t30\t2.25\n-------------------------------\n1 Rows Returned\n"
+        );
+    }
+
+    #[test]
+    fn test_gtf() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("genes.gtf");
+        std::fs::write(
+            &path,
+            "#!genome-build GRCh38\n\
+             chr1\tensembl\tgene\t11\t20\t.\t+\t.\tgene_id \"G1\"; gene_name \"A\";\n\
+             chr1\tensembl\tgene\t31\t40\t.\t-\t.\tgene_id \"G2\";\n",
+        )
+        .unwrap();
+        let output = run_to_string(&format!(
+            "open({:?}) | where(_0.name() == \"G2\") | show_all()",
+            path
+        ))
+        .unwrap();
+        assert_eq!(
+            output,
+            "chr1\t30\t40\tG2\t.\t-\n-------------------------------\n1 Rows Returned\n"
+        );
+    }
+
+    #[test]
+    fn test_get_fasta() -> Result<(), QueryError> {
+        let output = run_to_string(
+            r#"
+            let genome = open("../data/genome.fa");
+            let peaks = open("../data/peaks.narrowPeak");
+            peaks | get_fasta(genome) | where(_0.field(1).contains("tggaac")) | show_all();
+            "#,
+        )?;
+        assert!(output.starts_with(">peak2\nACGACGCGCTCATTCCCTTGtcggagag"));
+        assert!(output.ends_with("\n1 Rows Returned\n"));
+
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("get-fasta.bed");
+        std::fs::write(
+            &path,
+            "chr1\t300\t320\tfwd\t0\t+\nchr1\t300\t320\trev\t0\t-\n",
+        )
+        .unwrap();
+        let output = run_to_string(&format!(
+            r#"
+            let genome = open("../data/genome.fa");
+            open({:?}) | get_fasta(genome, stranded, tab) | show_all();
+            "#,
+            path
+        ))?;
+        let lines: Vec<_> = output.lines().collect();
+        assert_eq!(lines[0], "fwd\tACGACGCGCTCATTCCCTTG");
+        assert_eq!(lines[1], "rev\tCAAGGGAATGAGCGCGTCGT");
+
+        let err = run_to_string(
+            "let a = open(\"../data/a.bed\");\nlet b = open(\"../data/b.bed\");\na | get_fasta(b)",
+        )
+        .unwrap_err();
+        assert!(err.message.contains("expects a FASTA file"));
+
+        let err = run_to_string(
+            r#"
+            let genome = open("../data/genome.fa");
+            open("../data/a.bed") | get_fasta(genome, upper);
+            "#,
+        )
+        .unwrap_err();
+        assert!(err.message.contains("Unknown get_fasta option"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_open_region() -> Result<(), QueryError> {
+        let output = run_to_string(r#"open("../data/a.bed.gz", "1:2701-2800") | show_all()"#)?;
+        assert_eq!(
+            output,
+            "1\t2745\t2845\n1\t2763\t2863\n-------------------------------\n2 Rows Returned\n"
+        );
+
+        let err = run_to_string(r#"open("../data/a.bed", "1:1-100")"#).unwrap_err();
+        assert!(err.message.contains("region queries need"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_open_vcf_region() -> Result<(), QueryError> {
+        // The end of a variant is computed from the length of REF, like tabix does
+        let output =
+            run_to_string(r#"open("../data/sample.vcf.gz", "1:753846-753848") | show_all()"#)?;
+        assert_eq!(
+            output,
+            "1\t753845\t753847\n1\t753847\t753849\n-------------------------------\n2 Rows Returned\n"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_save_indexed() -> Result<(), QueryError> {
+        use std::io::Read;
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("a.bed.gz");
+        run_to_string(&format!(
+            r#"open("../data/a.bed") | save_indexed("{}")"#,
+            path.display()
+        ))?;
+        assert!(path.with_extension("gz.tbi").exists());
+
+        let mut saved = String::new();
+        libflate::gzip::MultiDecoder::new(std::fs::File::open(&path)?)?
+            .read_to_string(&mut saved)?;
+        assert_eq!(saved, std::fs::read_to_string("../data/a.bed")?);
+
+        let output = run_to_string(&format!(
+            r#"open("{}", "1:2701-2800") | show_all()"#,
+            path.display()
+        ))?;
+        assert!(output.starts_with("1\t2745\t2845\n1\t2763\t2863\n"));
+        Ok(())
+    }
+}
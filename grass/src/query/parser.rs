@@ -0,0 +1,410 @@
+use super::{
+    ast::{BinaryOp, Expr, ExprKind, MapStmt, Operator, Pos, QueryExpr, QueryStmt, UnaryOp},
+    lexer::{tokenize, Token, TokenKind},
+    QueryError,
+};
+
+type Result<T> = std::result::Result<T, QueryError>;
+
+struct Parser {
+    tokens: Vec<Token>,
+    cursor: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> &Token {
+        &self.tokens[self.cursor]
+    }
+
+    fn next(&mut self) -> Token {
+        let ret = self.tokens[self.cursor].clone();
+        if self.cursor + 1 < self.tokens.len() {
+            self.cursor += 1;
+        }
+        ret
+    }
+
+    fn pos(&self) -> Pos {
+        let token = self.peek();
+        (token.line, token.column)
+    }
+
+    fn error<T>(&self, message: impl Into<String>) -> Result<T> {
+        let token = self.peek();
+        Err(QueryError::at(token.line, token.column, message))
+    }
+
+    fn describe_next(&self) -> String {
+        match &self.peek().kind {
+            TokenKind::Ident(id) => format!("'{}'", id),
+            TokenKind::Int(value) => format!("'{}'", value),
+            TokenKind::Float(value) => format!("'{}'", value),
+            TokenKind::Str(value) => format!("{:?}", value),
+            TokenKind::Punct(punct) => format!("'{}'", punct),
+            TokenKind::Eof => "end of input".to_string(),
+        }
+    }
+
+    fn eat_punct(&mut self, punct: &str) -> bool {
+        if self.peek().is_punct(punct) {
+            self.next();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect_punct(&mut self, punct: &str) -> Result<()> {
+        if self.eat_punct(punct) {
+            Ok(())
+        } else {
+            self.error(format!(
+                "Expecting '{}', but {} found",
+                punct,
+                self.describe_next()
+            ))
+        }
+    }
+
+    fn expect_ident(&mut self) -> Result<String> {
+        if let TokenKind::Ident(id) = &self.peek().kind {
+            let id = id.clone();
+            self.next();
+            Ok(id)
+        } else {
+            self.error(format!(
+                "Expecting an identifier, but {} found",
+                self.describe_next()
+            ))
+        }
+    }
+
+    /// Parse a comma separated list until the closing parenthesis, which is consumed as well
+    fn parse_list<T>(
+        &mut self,
+        mut parse_item: impl FnMut(&mut Self) -> Result<T>,
+    ) -> Result<Vec<T>> {
+        let mut ret = vec![];
+        while !self.eat_punct(")") {
+            ret.push(parse_item(self)?);
+            if !self.eat_punct(",") {
+                self.expect_punct(")")?;
+                break;
+            }
+        }
+        Ok(ret)
+    }
+
+    fn parse_body(&mut self) -> Result<Vec<QueryStmt>> {
+        let mut ret = vec![];
+        while self.peek().kind != TokenKind::Eof {
+            ret.push(self.parse_stmt()?);
+        }
+        Ok(ret)
+    }
+
+    fn parse_stmt(&mut self) -> Result<QueryStmt> {
+        let ret = if self.peek().is_ident("let") {
+            self.next();
+            let id = self.expect_ident()?;
+            self.expect_punct("=")?;
+            QueryStmt::Let(id, self.parse_query_expr()?)
+        } else if let TokenKind::Ident(_) = self.peek().kind {
+            QueryStmt::Expr(self.parse_query_expr()?)
+        } else {
+            return self.error(format!(
+                "Unexpected token for the Grass DSL: {}",
+                self.describe_next()
+            ));
+        };
+
+        // Just strip the semicolons if there are any
+        while self.eat_punct(";") {}
+
+        Ok(ret)
+    }
+
+    fn parse_query_expr(&mut self) -> Result<QueryExpr> {
+        let mut result = self.parse_left_most()?;
+        while self.eat_punct("|") {
+            result = QueryExpr::OpChain(Box::new(result), self.parse_operator()?);
+        }
+        Ok(result)
+    }
+
+    fn parse_left_most(&mut self) -> Result<QueryExpr> {
+        let pos = self.pos();
+        let first_ident = self.expect_ident()?;
+        match first_ident.as_str() {
+            "open" => {
+                self.expect_punct("(")?;
+                let path = match self.next().kind {
+                    TokenKind::Str(path) => path,
+                    _ => return Err(QueryError::at(pos.0, pos.1, "open expects a path literal")),
+                };
+                let region = if self.eat_punct(",") {
+                    match self.next().kind {
+                        TokenKind::Str(region) => Some(region),
+                        _ => {
+                            return Err(QueryError::at(
+                                pos.0,
+                                pos.1,
+                                "open expects a region literal, like \"chr1:1000-2000\"",
+                            ))
+                        }
+                    }
+                } else {
+                    None
+                };
+                self.expect_punct(")")?;
+                Ok(QueryExpr::Open(path, region, pos))
+            }
+            "intersect" => {
+                self.expect_punct("(")?;
+                let args = self.parse_list(Self::parse_query_expr)?;
+                Ok(QueryExpr::Intersect(args, pos))
+            }
+            "left_outter_intersect" => {
+                self.expect_punct("(")?;
+                let args = self.parse_list(Self::parse_query_expr)?;
+                if args.len() != 2 {
+                    return Err(QueryError::at(
+                        pos.0,
+                        pos.1,
+                        "left_outter_intersect expects exactly 2 inputs",
+                    ));
+                }
+                Ok(QueryExpr::LeftOutterIntersect(args, pos))
+            }
+            _ => Ok(QueryExpr::VarRef(first_ident, pos)),
+        }
+    }
+
+    fn parse_operator(&mut self) -> Result<Operator> {
+        let pos = self.pos();
+        let name = self.expect_ident()?;
+        self.expect_punct("(")?;
+        let ret = match name.as_str() {
+            "where" => Operator::Where(self.parse_expr()?),
+            "map" => Operator::Map(self.parse_map_body()?),
+            _ => {
+                let args = self.parse_list(Self::parse_expr)?;
+                return Ok(Operator::Invoke(name, args, pos));
+            }
+        };
+        self.expect_punct(")")?;
+        Ok(ret)
+    }
+
+    fn parse_map_body(&mut self) -> Result<Vec<MapStmt>> {
+        if !self.eat_punct("{") {
+            return Ok(vec![self.parse_map_stmt()?]);
+        }
+        let mut ret = vec![];
+        loop {
+            while self.eat_punct(";") {}
+            if self.eat_punct("}") {
+                return Ok(ret);
+            }
+            ret.push(self.parse_map_stmt()?);
+            if !self.peek().is_punct("}") {
+                self.expect_punct(";")?;
+            }
+        }
+    }
+
+    fn parse_map_stmt(&mut self) -> Result<MapStmt> {
+        let target = self.parse_expr()?;
+        let op = match &self.peek().kind {
+            TokenKind::Punct("=") => None,
+            TokenKind::Punct("+=") => Some(BinaryOp::Add),
+            TokenKind::Punct("-=") => Some(BinaryOp::Sub),
+            TokenKind::Punct("*=") => Some(BinaryOp::Mul),
+            TokenKind::Punct("/=") => Some(BinaryOp::Div),
+            _ => return Ok(MapStmt::Expr(target)),
+        };
+        self.next();
+        let value = self.parse_expr()?;
+        Ok(MapStmt::Assign { target, op, value })
+    }
+
+    fn parse_expr(&mut self) -> Result<Expr> {
+        let pos = self.pos();
+        if self.eat_punct("..") {
+            let end = self.parse_range_end()?;
+            return Ok(Expr {
+                kind: ExprKind::Range(None, end),
+                pos,
+            });
+        }
+        let lhs = self.parse_binary(0)?;
+        if self.eat_punct("..") {
+            let end = self.parse_range_end()?;
+            return Ok(Expr {
+                kind: ExprKind::Range(Some(Box::new(lhs)), end),
+                pos,
+            });
+        }
+        Ok(lhs)
+    }
+
+    fn parse_range_end(&mut self) -> Result<Option<Box<Expr>>> {
+        let token = self.peek();
+        if token.is_punct(")") || token.is_punct(",") || token.kind == TokenKind::Eof {
+            Ok(None)
+        } else {
+            Ok(Some(Box::new(self.parse_binary(0)?)))
+        }
+    }
+
+    fn peek_binary_op(&self) -> Option<(BinaryOp, usize)> {
+        let op = match self.peek().kind {
+            TokenKind::Punct("||") => (BinaryOp::Or, 0),
+            TokenKind::Punct("&&") => (BinaryOp::And, 1),
+            TokenKind::Punct("==") => (BinaryOp::Eq, 2),
+            TokenKind::Punct("!=") => (BinaryOp::Ne, 2),
+            TokenKind::Punct("<") => (BinaryOp::Lt, 2),
+            TokenKind::Punct("<=") => (BinaryOp::Le, 2),
+            TokenKind::Punct(">") => (BinaryOp::Gt, 2),
+            TokenKind::Punct(">=") => (BinaryOp::Ge, 2),
+            TokenKind::Punct("+") => (BinaryOp::Add, 3),
+            TokenKind::Punct("-") => (BinaryOp::Sub, 3),
+            TokenKind::Punct("*") => (BinaryOp::Mul, 4),
+            TokenKind::Punct("/") => (BinaryOp::Div, 4),
+            TokenKind::Punct("%") => (BinaryOp::Rem, 4),
+            _ => return None,
+        };
+        Some(op)
+    }
+
+    fn parse_binary(&mut self, min_prec: usize) -> Result<Expr> {
+        let mut lhs = self.parse_cast()?;
+        while let Some((op, prec)) = self.peek_binary_op() {
+            if prec < min_prec {
+                break;
+            }
+            let pos = self.pos();
+            self.next();
+            let rhs = self.parse_binary(prec + 1)?;
+            lhs = Expr {
+                kind: ExprKind::Binary(op, Box::new(lhs), Box::new(rhs)),
+                pos,
+            };
+        }
+        Ok(lhs)
+    }
+
+    fn parse_cast(&mut self) -> Result<Expr> {
+        let mut ret = self.parse_unary()?;
+        while self.peek().is_ident("as") {
+            let pos = self.pos();
+            self.next();
+            let ty = self.expect_ident()?;
+            ret = Expr {
+                kind: ExprKind::Cast(Box::new(ret), ty),
+                pos,
+            };
+        }
+        Ok(ret)
+    }
+
+    fn parse_unary(&mut self) -> Result<Expr> {
+        let pos = self.pos();
+        let op = if self.eat_punct("!") {
+            UnaryOp::Not
+        } else if self.eat_punct("-") {
+            UnaryOp::Neg
+        } else {
+            return self.parse_postfix();
+        };
+        let operand = self.parse_unary()?;
+        Ok(Expr {
+            kind: ExprKind::Unary(op, Box::new(operand)),
+            pos,
+        })
+    }
+
+    fn parse_postfix(&mut self) -> Result<Expr> {
+        let mut ret = self.parse_primary()?;
+        while self.peek().is_punct(".") {
+            let pos = self.pos();
+            self.next();
+            let kind = match self.next().kind {
+                TokenKind::Int(idx) if idx >= 0 => {
+                    ExprKind::TupleIndex(Box::new(ret), idx as usize)
+                }
+                TokenKind::Ident(name) => {
+                    if self.eat_punct("(") {
+                        let args = self.parse_list(Self::parse_expr)?;
+                        ExprKind::MethodCall(Box::new(ret), name, args)
+                    } else {
+                        ExprKind::Field(Box::new(ret), name)
+                    }
+                }
+                _ => {
+                    return Err(QueryError::at(
+                        pos.0,
+                        pos.1,
+                        "Expecting a field, method or tuple index after '.'",
+                    ))
+                }
+            };
+            ret = Expr { kind, pos };
+        }
+        Ok(ret)
+    }
+
+    fn parse_primary(&mut self) -> Result<Expr> {
+        let pos = self.pos();
+        if self.peek().is_punct("|") || self.peek().is_punct("||") {
+            return self.error("Closures are not supported by the query interpreter");
+        }
+        let kind = match self.peek().kind.clone() {
+            TokenKind::Int(value) => {
+                self.next();
+                ExprKind::Int(value)
+            }
+            TokenKind::Float(value) => {
+                self.next();
+                ExprKind::Float(value)
+            }
+            TokenKind::Str(value) => {
+                self.next();
+                ExprKind::Str(value)
+            }
+            TokenKind::Ident(id) => {
+                self.next();
+                match id.as_str() {
+                    "true" => ExprKind::Bool(true),
+                    "false" => ExprKind::Bool(false),
+                    _ if self.eat_punct("(") => {
+                        ExprKind::Call(id, self.parse_list(Self::parse_expr)?)
+                    }
+                    _ => ExprKind::Ident(id),
+                }
+            }
+            TokenKind::Punct("(") => {
+                self.next();
+                let inner = self.parse_expr()?;
+                self.expect_punct(")")?;
+                return Ok(inner);
+            }
+            _ => {
+                return self.error(format!(
+                    "Expecting an expression, but {} found",
+                    self.describe_next()
+                ))
+            }
+        };
+        Ok(Expr { kind, pos })
+    }
+}
+
+/// Parse the query script to the list of statements
+pub(super) fn parse_query(text: &str) -> Result<Vec<QueryStmt>> {
+    let mut parser = Parser {
+        tokens: tokenize(text)?,
+        cursor: 0,
+    };
+    parser.parse_body()
+}
@@ -0,0 +1,369 @@
+use std::{
+    io::{Result, Write},
+    sync::Arc,
+};
+
+use crate::{
+    chromset::LexicalChromRef,
+    high_level_api::{SequenceFormat, SequenceRecord},
+    properties::{
+        Intersection, Serializable, Strand, StrandColumn, WithName, WithRegion, WithRegionCore,
+        WithScore, WithStrand,
+    },
+    records::{
+        Bed12, Bed3, Bed4, Bed5, Bed6, BedGraph, BedN, BroadPeak, GffDialect, GffRecord, NarrowPeak,
+    },
+    ChromName,
+};
+
+/// One of the original intervals that a runtime record is made of
+#[derive(Clone, PartialEq)]
+pub struct Interval {
+    pub chrom: LexicalChromRef,
+    pub begin: u32,
+    pub end: u32,
+    pub name: Option<Arc<String>>,
+    pub score: Option<f64>,
+    /// The serialized columns after the end column, written as they are when the record is dumped
+    tail: Option<Arc<String>>,
+    /// Set for the records made by `get_fasta`, which are written as sequences instead
+    format: Option<SequenceFormat>,
+}
+
+impl Interval {
+    fn from_record<T>(record: &T, name: Option<Arc<String>>, score: Option<f64>) -> Self
+    where
+        T: WithRegionCore<LexicalChromRef> + Serializable,
+    {
+        let mut buf = Vec::new();
+        record.dump(&mut buf).ok();
+        let tail = buf
+            .iter()
+            .enumerate()
+            .filter(|(_, &c)| c == b'\t')
+            .nth(2)
+            .map(|(idx, _)| Arc::new(String::from_utf8_lossy(&buf[idx + 1..]).into_owned()));
+        Self {
+            chrom: record.chrom().clone(),
+            begin: record.begin(),
+            end: record.end(),
+            name,
+            score,
+            tail,
+            format: None,
+        }
+    }
+
+    /// Get a column after the end column, `field(0)` is the 4th column of the original line
+    pub fn field(&self, idx: usize) -> Option<&str> {
+        self.tail.as_ref()?.split('\t').nth(idx)
+    }
+
+    fn from_region<T: WithRegionCore<LexicalChromRef>>(record: &T) -> Self {
+        Self {
+            chrom: record.chrom().clone(),
+            begin: record.begin(),
+            end: record.end(),
+            name: None,
+            score: None,
+            tail: None,
+            format: None,
+        }
+    }
+}
+
+impl WithRegionCore<LexicalChromRef> for Interval {
+    fn begin(&self) -> u32 {
+        self.begin
+    }
+    fn end(&self) -> u32 {
+        self.end
+    }
+    fn chrom(&self) -> &LexicalChromRef {
+        &self.chrom
+    }
+}
+
+impl WithName for Interval {
+    fn name(&self) -> &str {
+        self.name.as_ref().map_or(".", |name| name.as_str())
+    }
+}
+
+impl WithStrand for Interval {
+    fn strand(&self) -> Option<Strand> {
+        self.field(2)
+            .and_then(StrandColumn::parse)
+            .and_then(StrandColumn::strand)
+    }
+}
+
+impl Serializable for Interval {
+    fn dump<W: Write>(&self, mut fp: W) -> Result<()> {
+        if let Some(format) = self.format {
+            let name = self.field(0).unwrap_or_default();
+            let sequence = self.field(1).unwrap_or_default();
+            return match format {
+                SequenceFormat::Fasta => write!(fp, ">{}\n{}", name, sequence),
+                SequenceFormat::Tab => write!(fp, "{}\t{}", name, sequence),
+            };
+        }
+        self.chrom.write(&mut fp)?;
+        write!(fp, "\t{}\t{}", self.begin, self.end)?;
+        if let Some(tail) = self.tail.as_ref() {
+            fp.write_all(b"\t")?;
+            fp.write_all(tail.as_bytes())?;
+        }
+        Ok(())
+    }
+}
+
+/// The record type used by the query runtime, which is either a single interval that comes
+/// from an input file, or the tuple of the original intervals produced by an intersection.
+#[derive(Clone, PartialEq)]
+pub struct DynRecord {
+    parts: Vec<Interval>,
+}
+
+impl DynRecord {
+    pub fn parts(&self) -> &[Interval] {
+        &self.parts
+    }
+
+    pub fn parts_mut(&mut self) -> &mut [Interval] {
+        &mut self.parts
+    }
+
+    /// Make a tuple record, the nested tuples are flattened
+    pub fn concat(mut self, other: Self) -> Self {
+        self.parts.extend(other.parts);
+        self
+    }
+
+    /// Convert the record to a single interval record which has the region of this record
+    pub fn as_bed3(&self) -> Self {
+        Self::from_region(self)
+    }
+
+    pub fn from_region<T: WithRegionCore<LexicalChromRef>>(record: &T) -> Self {
+        Self {
+            parts: vec![Interval::from_region(record)],
+        }
+    }
+
+    /// The region shared by all the parts, (0, 0) if some of the parts don't overlap
+    fn overlap(&self) -> (u32, u32) {
+        let (first, rest) = self.parts.split_first().unwrap();
+        let begin = self.parts.iter().map(|part| part.begin).max().unwrap();
+        let end = self.parts.iter().map(|part| part.end).min().unwrap();
+        if rest.iter().all(|part| part.overlaps(first)) && begin < end {
+            (begin, end)
+        } else {
+            (0, 0)
+        }
+    }
+}
+
+impl WithRegionCore<LexicalChromRef> for DynRecord {
+    fn begin(&self) -> u32 {
+        if self.parts.len() == 1 {
+            self.parts[0].begin
+        } else {
+            self.overlap().0
+        }
+    }
+    fn end(&self) -> u32 {
+        if self.parts.len() == 1 {
+            self.parts[0].end
+        } else {
+            self.overlap().1
+        }
+    }
+    fn chrom(&self) -> &LexicalChromRef {
+        &self.parts[0].chrom
+    }
+}
+
+/// The name of a tuple record is the name of its first part
+impl WithName for DynRecord {
+    fn name(&self) -> &str {
+        self.parts[0].name()
+    }
+}
+
+/// The strand of a tuple record is the strand of its first part
+impl WithStrand for DynRecord {
+    fn strand(&self) -> Option<Strand> {
+        self.parts[0].strand()
+    }
+}
+
+impl Intersection<LexicalChromRef> for DynRecord {
+    fn original(&self, idx: usize) -> &dyn WithRegionCore<LexicalChromRef> {
+        &self.parts[idx]
+    }
+    fn size(&self) -> usize {
+        self.parts.len()
+    }
+}
+
+impl Serializable for DynRecord {
+    fn dump<W: Write>(&self, mut fp: W) -> Result<()> {
+        for (idx, part) in self.parts.iter().enumerate() {
+            if idx > 0 {
+                write!(fp, "\t|\t")?;
+            }
+            part.dump(&mut fp)?;
+        }
+        Ok(())
+    }
+}
+
+/// Records that can be processed by the query runtime
+pub trait IntoDynRecord {
+    fn into_dyn_record(self) -> DynRecord;
+}
+
+impl IntoDynRecord for DynRecord {
+    fn into_dyn_record(self) -> DynRecord {
+        self
+    }
+}
+
+impl IntoDynRecord for Bed3<LexicalChromRef> {
+    fn into_dyn_record(self) -> DynRecord {
+        DynRecord::from_region(&self)
+    }
+}
+
+impl IntoDynRecord for Bed4<LexicalChromRef> {
+    fn into_dyn_record(self) -> DynRecord {
+        let interval = Interval::from_record(&self, Some(self.name.clone()), None);
+        DynRecord {
+            parts: vec![interval],
+        }
+    }
+}
+
+impl IntoDynRecord for Bed5<LexicalChromRef, f64> {
+    fn into_dyn_record(self) -> DynRecord {
+        let interval = Interval::from_record(&self, Some(self.core.name.clone()), self.score);
+        DynRecord {
+            parts: vec![interval],
+        }
+    }
+}
+
+impl IntoDynRecord for BedGraph<LexicalChromRef, f64> {
+    fn into_dyn_record(self) -> DynRecord {
+        let interval = Interval::from_record(&self, None, Some(self.value));
+        DynRecord {
+            parts: vec![interval],
+        }
+    }
+}
+
+impl IntoDynRecord for BedGraph<LexicalChromRef, usize> {
+    fn into_dyn_record(self) -> DynRecord {
+        let interval = Interval::from_record(&self, None, Some(self.value as f64));
+        DynRecord {
+            parts: vec![interval],
+        }
+    }
+}
+
+impl IntoDynRecord for Bed6<LexicalChromRef, f64> {
+    fn into_dyn_record(self) -> DynRecord {
+        let interval =
+            Interval::from_record(&self, Some(self.core.core.name.clone()), self.core.score);
+        DynRecord {
+            parts: vec![interval],
+        }
+    }
+}
+
+macro_rules! impl_bed6_based_dyn_record {
+    ($($rec_ty:ty),*) => {
+        $(
+            impl IntoDynRecord for $rec_ty {
+                fn into_dyn_record(self) -> DynRecord {
+                    let bed5 = &self.core.core;
+                    let interval = Interval::from_record(&self, Some(bed5.core.name.clone()), bed5.score);
+                    DynRecord {
+                        parts: vec![interval],
+                    }
+                }
+            }
+        )*
+    };
+}
+
+impl_bed6_based_dyn_record!(
+    Bed12<LexicalChromRef, f64>,
+    NarrowPeak<LexicalChromRef>,
+    BroadPeak<LexicalChromRef>
+);
+
+impl IntoDynRecord for BedN<LexicalChromRef> {
+    fn into_dyn_record(self) -> DynRecord {
+        let name = self.field(0).map(|name| Arc::new(name.to_string()));
+        let interval = Interval::from_record(&self, name, self.score());
+        DynRecord {
+            parts: vec![interval],
+        }
+    }
+}
+
+/// The name and the sequence are the fields 0 and 1 of the record, which is written as FASTA or
+/// the tab separated name and sequence
+impl IntoDynRecord for SequenceRecord<DynRecord> {
+    fn into_dyn_record(self) -> DynRecord {
+        let mut interval = Interval::from_region(&self.region);
+        interval.tail = Some(Arc::new(format!("{}\t{}", self.name, self.sequence)));
+        interval.name = Some(Arc::new(self.name));
+        interval.format = Some(self.format);
+        DynRecord {
+            parts: vec![interval],
+        }
+    }
+}
+
+/// The annotations are processed as the BED6 records, the GFF columns are dropped
+impl<D: GffDialect> IntoDynRecord for GffRecord<LexicalChromRef, D> {
+    fn into_dyn_record(self) -> DynRecord {
+        self.to_bed6().into_dyn_record()
+    }
+}
+
+/// Only the region is kept, like the VCF records read by htslib
+impl IntoDynRecord for crate::records::VcfLine<LexicalChromRef> {
+    fn into_dyn_record(self) -> DynRecord {
+        DynRecord::from_region(&self)
+    }
+}
+
+#[cfg(feature = "hts")]
+impl<'a> IntoDynRecord for crate::records::BAMRecord<'a, LexicalChromRef> {
+    /// The read is converted to BED6, which has the read name, the MAPQ as the score and the strand
+    fn into_dyn_record(self) -> DynRecord {
+        use crate::properties::WithStrand;
+        let bed6: Bed6<LexicalChromRef, f64> = Bed6 {
+            core: Bed5 {
+                core: Bed4 {
+                    core: Bed3::new(&self),
+                    name: Arc::new(self.read_name().into_owned()),
+                },
+                score: Some(self.mapq() as f64),
+            },
+            strand: self.strand(),
+        };
+        bed6.into_dyn_record()
+    }
+}
+
+#[cfg(feature = "hts")]
+impl<'a> IntoDynRecord for crate::records::VcfRecord<'a, LexicalChromRef> {
+    fn into_dyn_record(self) -> DynRecord {
+        DynRecord::from_region(&self)
+    }
+}
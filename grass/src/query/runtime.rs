@@ -0,0 +1,800 @@
+use std::{
+    cell::RefCell,
+    collections::{HashMap, VecDeque},
+    fs::File,
+    io::{ErrorKind, Read, Write},
+    path::Path,
+    rc::Rc,
+};
+
+use grass_formats::{FileFormat, FileKind, GffFlavor};
+use itertools::Itertools;
+use libflate::gzip::MultiDecoder;
+
+use super::{
+    ast::{Expr, ExprKind, Operator, Pos, QueryExpr, QueryStmt},
+    expr::{DynPrintOpt, EvalContext, Value},
+    parser::parse_query,
+    record::{DynRecord, IntoDynRecord},
+    QueryError,
+};
+use crate::{
+    algorithm::{AssumeSorted, AssumingSortedIter, CheckSorted, SortedIntersect},
+    chromset::LexicalChromRef,
+    high_level_api::{
+        get_global_chrom_list, save_records, DepthExt, InvertExt, MergeExt, PrintOpt, SaveOptions,
+        SequenceFormat, SequenceRecord, SubtractExt,
+    },
+    properties::Serializable,
+    records::{
+        Bed12, Bed3, Bed4, Bed5, Bed6, BedGraph, BedN, BroadPeak, Gff3Record, GtfRecord,
+        IndexedFasta, NarrowPeak, VcfLine,
+    },
+    tabix::{TabixConfig, TabixReader},
+    LexicalChromSet, LineRecord, LineRecordStream, LineRecordStreamExt,
+};
+
+type Result<T> = std::result::Result<T, QueryError>;
+
+type RecordIter = Box<dyn Iterator<Item = DynRecord>>;
+
+/// The first error happened inside the lazily evaluated record iterators
+type ErrorSlot = Rc<RefCell<Option<QueryError>>>;
+
+/// What is known about the order of a stream
+#[derive(Clone, Copy, PartialEq)]
+enum Order {
+    /// The records are sorted, such as the outputs of `sort()` and the sorted algorithms
+    Sorted,
+    /// The records are in the order of the input file, which should be sorted, but it's only
+    /// verified when the records are used as a sorted input
+    Unchecked,
+    Unsorted,
+}
+
+/// A lazily evaluated stream of records
+struct Stream {
+    iter: RecordIter,
+    order: Order,
+}
+
+impl Stream {
+    fn new<I: Iterator<Item = DynRecord> + 'static>(iter: I, order: Order) -> Self {
+        Self {
+            iter: Box::new(iter),
+            order,
+        }
+    }
+
+    /// Get the sorted iterator for the algorithms. The records in the file order are verified
+    /// with `CheckSorted` as they stream through, and the first out-of-order record stops the
+    /// stream with an error.
+    fn require_sorted(
+        self,
+        what: &str,
+        pos: Pos,
+        error: &ErrorSlot,
+    ) -> Result<AssumingSortedIter<RecordIter>> {
+        match self.order {
+            Order::Sorted => return Ok(self.iter.assume_sorted()),
+            Order::Unchecked => (),
+            Order::Unsorted => {
+                return Err(QueryError::at(
+                    pos.0,
+                    pos.1,
+                    format!(
+                        "{} requires a sorted input, use sort(), or assume_sorted() if the input is known to be sorted",
+                        what
+                    ),
+                ))
+            }
+        }
+        let mut checked = self.iter.check_sorted();
+        let error = error.clone();
+        let what = what.to_string();
+        let iter = std::iter::from_fn(move || match checked.try_next() {
+            Ok(record) => record,
+            Err(e) => {
+                let message = format!("The input of {} is invalid. {}", what, e);
+                error
+                    .borrow_mut()
+                    .get_or_insert(QueryError::at(pos.0, pos.1, message));
+                None
+            }
+        });
+        Ok((Box::new(iter) as RecordIter).assume_sorted())
+    }
+}
+
+/// The value of a query expression
+enum QueryValue {
+    Stream(Stream),
+    Print {
+        rows: RecordIter,
+        opt: Option<DynPrintOpt>,
+    },
+    /// A reference sequence, which is shared by all the `get_fasta` using it
+    Fasta(Rc<IndexedFasta>),
+    Unit,
+}
+
+/// The interpreter of the GRASS query language, the printed results are written to `out`
+pub struct QueryRuntime<W: Write> {
+    /// The variables, `None` means the value has been moved out
+    env: HashMap<String, Option<QueryValue>>,
+    error: ErrorSlot,
+    out: W,
+}
+
+impl<W: Write> QueryRuntime<W> {
+    pub fn new(out: W) -> Self {
+        Self {
+            env: HashMap::new(),
+            error: Default::default(),
+            out,
+        }
+    }
+
+    /// Open the file and bind it to a variable, which is the same as `let <name> = open("<path>")`
+    pub fn bind_file<P: AsRef<Path>>(&mut self, name: &str, path: P) -> Result<()> {
+        let value = open(path.as_ref(), None, &self.error)?;
+        self.env.insert(name.to_string(), Some(value));
+        Ok(())
+    }
+
+    /// Run the `.grass` script file
+    pub fn run_file<P: AsRef<Path>>(&mut self, path: P) -> Result<()> {
+        let mut query = String::new();
+        File::open(path.as_ref())?.read_to_string(&mut query)?;
+        self.run(&query)
+    }
+
+    /// Parse the query and run all the statements in it
+    pub fn run(&mut self, query: &str) -> Result<()> {
+        for stmt in parse_query(query)? {
+            match stmt {
+                QueryStmt::Let(id, expr) => {
+                    let value = self.eval(&expr)?;
+                    self.env.insert(id, Some(value));
+                }
+                QueryStmt::Expr(expr) => {
+                    let value = self.eval(&expr)?;
+                    self.emit(value)?;
+                }
+            }
+        }
+        self.out.flush()?;
+        Ok(())
+    }
+
+    fn check_error(&self) -> Result<()> {
+        match self.error.borrow_mut().take() {
+            Some(e) => Err(e),
+            None => Ok(()),
+        }
+    }
+
+    /// Print the result of an expression statement. A bare stream is printed as if `show_all()`
+    /// is applied, since otherwise the statement has no effect.
+    fn emit(&mut self, value: QueryValue) -> Result<()> {
+        let (rows, opt) = match value {
+            QueryValue::Stream(stream) => (stream.iter, None),
+            QueryValue::Print { rows, opt } => (rows, opt),
+            QueryValue::Fasta(_) | QueryValue::Unit => return Ok(()),
+        };
+
+        let mut count = 0;
+        for row in rows {
+            match opt.as_ref() {
+                Some(opt) => opt.print(&row, "\t", &mut self.out)?,
+                None => row.dump(&mut self.out)?,
+            }
+            self.out.write_all(b"\n")?;
+            count += 1;
+        }
+        self.check_error()?;
+
+        writeln!(
+            self.out,
+            "-------------------------------\n{} Rows Returned",
+            count
+        )?;
+        Ok(())
+    }
+
+    fn take_var(&mut self, id: &str, pos: Pos) -> Result<QueryValue> {
+        match self.env.get_mut(id) {
+            Some(value) => value.take().ok_or_else(|| {
+                QueryError::at(pos.0, pos.1, format!("Use of moved variable {}", id))
+            }),
+            None => Err(QueryError::at(
+                pos.0,
+                pos.1,
+                format!("Unknown variable {}", id),
+            )),
+        }
+    }
+
+    fn eval_stream(&mut self, expr: &QueryExpr) -> Result<Stream> {
+        match self.eval(expr)? {
+            QueryValue::Stream(stream) => Ok(stream),
+            _ => {
+                let pos = query_expr_pos(expr);
+                Err(QueryError::at(pos.0, pos.1, "Expecting a record stream"))
+            }
+        }
+    }
+
+    fn eval(&mut self, expr: &QueryExpr) -> Result<QueryValue> {
+        match expr {
+            QueryExpr::Open(path, region, pos) => {
+                open(path.as_ref(), region.as_deref(), &self.error)
+                    .map_err(|e| QueryError::at(pos.0, pos.1, e.message))
+            }
+            QueryExpr::VarRef(id, pos) => self.take_var(id, *pos),
+            QueryExpr::Intersect(inputs, pos) => {
+                let mut inputs = inputs.iter();
+                let first = match inputs.next() {
+                    Some(first) => self.eval_stream(first)?,
+                    None => return Err(QueryError::at(pos.0, pos.1, "Intersection with no input")),
+                };
+                let mut result = first;
+                for input in inputs {
+                    let other = self.eval_stream(input)?;
+                    let a = result.require_sorted("intersect", *pos, &self.error)?;
+                    let b = other.require_sorted("intersect", *pos, &self.error)?;
+                    result = Stream::new(
+                        a.sorted_intersect(b).map(|(a, b)| a.concat(b)),
+                        Order::Sorted,
+                    );
+                }
+                Ok(QueryValue::Stream(result))
+            }
+            QueryExpr::LeftOutterIntersect(inputs, pos) => {
+                let a = self.eval_stream(&inputs[0])?;
+                let b = self.eval_stream(&inputs[1])?;
+                let a = a.require_sorted("left_outter_intersect", *pos, &self.error)?;
+                let b = b.require_sorted("left_outter_intersect", *pos, &self.error)?;
+                let iter = a.sorted_left_outer_intersect(b).map(|(a, b)| match b {
+                    Some(b) => a.concat(b),
+                    None => a,
+                });
+                Ok(QueryValue::Stream(Stream::new(iter, Order::Sorted)))
+            }
+            QueryExpr::OpChain(upstream, op) => {
+                let upstream = self.eval_stream(upstream)?;
+                self.apply(upstream, op)
+            }
+        }
+    }
+
+    fn apply(&mut self, upstream: Stream, op: &Operator) -> Result<QueryValue> {
+        let error = self.error.clone();
+        let ret = match op {
+            Operator::Where(pred) => {
+                let pred = pred.clone();
+                let iter = upstream
+                    .iter
+                    .scan((), move |_, record| {
+                        let ctx = EvalContext {
+                            record: Some(&record),
+                        };
+                        match ctx.eval_bool(&pred) {
+                            Ok(true) => Some(Some(record)),
+                            Ok(false) => Some(None),
+                            Err(e) => {
+                                error.borrow_mut().get_or_insert(e);
+                                None
+                            }
+                        }
+                    })
+                    .flatten();
+                Stream::new(iter, upstream.order)
+            }
+            Operator::Map(stmts) => {
+                let stmts = stmts.clone();
+                let iter = upstream.iter.scan((), move |_, record| {
+                    match EvalContext::run_map(&stmts, record) {
+                        Ok(record) => Some(record),
+                        Err(e) => {
+                            error.borrow_mut().get_or_insert(e);
+                            None
+                        }
+                    }
+                });
+                // The modified records are not sorted anymore
+                Stream::new(iter, Order::Unsorted)
+            }
+            Operator::Invoke(name, args, pos) => return self.invoke(upstream, name, args, *pos),
+        };
+        Ok(QueryValue::Stream(ret))
+    }
+
+    fn invoke(
+        &mut self,
+        upstream: Stream,
+        name: &str,
+        args: &[Expr],
+        pos: Pos,
+    ) -> Result<QueryValue> {
+        if let Some(expected) = match name {
+            "as_bed3" | "show_all" | "merge_overlaps" | "invert" | "dedup" | "assume_sorted"
+            | "sort" | "coverage" => Some(0),
+            "show_top" | "save" | "save_indexed" | "cat" | "subtract" | "project" => Some(1),
+            "head" | "tail" => Some(2),
+            _ => None,
+        } {
+            if args.len() != expected {
+                return error_at(
+                    pos,
+                    format!(
+                        "{} expects {} argument(s), but {} given",
+                        name,
+                        expected,
+                        args.len()
+                    ),
+                );
+            }
+        }
+
+        let eval_arg = |idx: usize| EvalContext { record: None }.eval(&args[idx]);
+        let usize_arg = |idx: usize| -> Result<usize> {
+            let value = eval_arg(idx)?;
+            value.as_usize().map_or_else(
+                || {
+                    error_at(
+                        pos,
+                        format!(
+                            "{} expects a non-negative integer, but {} found",
+                            name, value
+                        ),
+                    )
+                },
+                Ok,
+            )
+        };
+        let print_opt_arg = |idx: usize| -> Result<DynPrintOpt> {
+            match eval_arg(idx)? {
+                Value::PrintOpt(opt) => Ok(opt),
+                other => error_at(
+                    pos,
+                    format!("{} expects a print option, but {} found", name, other),
+                ),
+            }
+        };
+
+        let order = upstream.order;
+        let ret = match name {
+            "as_bed3" => Stream::new(upstream.iter.map(|record| record.as_bed3()), order),
+            "assume_sorted" => Stream::new(upstream.iter, Order::Sorted),
+            "sort" => Stream::new(upstream.iter.sort_if_unsorted(), Order::Sorted),
+            "dedup" => Stream::new(upstream.iter.dedup(), order),
+            "project" => {
+                let idx = usize_arg(0)?;
+                let iter = upstream
+                    .iter
+                    .filter_map(move |record| record.parts().get(idx).map(DynRecord::from_region));
+                Stream::new(iter, order)
+            }
+            "merge_overlaps" => {
+                let iter = upstream
+                    .require_sorted(name, pos, &self.error)?
+                    .merge_overlaps();
+                Stream::new(iter.map(|bed3| bed3.into_dyn_record()), Order::Sorted)
+            }
+            "invert" => {
+                let iter = InvertExt::<LexicalChromRef>::invert(upstream.require_sorted(
+                    name,
+                    pos,
+                    &self.error,
+                )?);
+                Stream::new(iter.map(|bed3| bed3.into_dyn_record()), Order::Sorted)
+            }
+            "coverage" => {
+                let iter = upstream.require_sorted(name, pos, &self.error)?.coverage();
+                Stream::new(iter.map(|record| record.into_dyn_record()), Order::Sorted)
+            }
+            "subtract" => {
+                let other = match &args[0].kind {
+                    ExprKind::Ident(id) => match self.take_var(id, args[0].pos)? {
+                        QueryValue::Stream(stream) => stream,
+                        _ => {
+                            return error_at(
+                                pos,
+                                format!("subtract expects a record stream, {} is not", id),
+                            )
+                        }
+                    },
+                    _ => return error_at(pos, "subtract expects a variable".to_string()),
+                };
+                let a = upstream.require_sorted(name, pos, &self.error)?;
+                let b = other.require_sorted(name, pos, &self.error)?;
+                let iter = SubtractExt::<LexicalChromRef>::subtract(a, b)
+                    .map(|(a, b)| a.concat(b.into_dyn_record()));
+                Stream::new(iter, Order::Sorted)
+            }
+            "get_fasta" => {
+                let fasta = match args.first().map(|arg| &arg.kind) {
+                    Some(ExprKind::Ident(id)) => match self.env.get(id) {
+                        Some(Some(QueryValue::Fasta(fasta))) => fasta.clone(),
+                        Some(None) => {
+                            return error_at(pos, format!("Use of moved variable {}", id))
+                        }
+                        _ => {
+                            return error_at(
+                                pos,
+                                format!("get_fasta expects a FASTA file, {} is not", id),
+                            )
+                        }
+                    },
+                    _ => return error_at(pos, "get_fasta expects a variable".to_string()),
+                };
+                let mut stranded = false;
+                let mut format = SequenceFormat::Fasta;
+                for option in &args[1..] {
+                    match &option.kind {
+                        ExprKind::Ident(id) if id == "stranded" => stranded = true,
+                        ExprKind::Ident(id) if id == "tab" => format = SequenceFormat::Tab,
+                        _ => {
+                            return error_at(
+                                option.pos,
+                                "Unknown get_fasta option, expecting stranded or tab".to_string(),
+                            )
+                        }
+                    }
+                }
+                let error = self.error.clone();
+                let iter = upstream
+                    .iter
+                    .scan((), move |_, record| {
+                        match SequenceRecord::fetch(&fasta, record, stranded, format) {
+                            Ok(record) => Some(Some(record.into_dyn_record())),
+                            // Like bedtools, the regions not in the reference are skipped
+                            Err(e) if e.kind() == ErrorKind::NotFound => Some(None),
+                            Err(e) => {
+                                error.borrow_mut().get_or_insert(QueryError::at(
+                                    pos.0,
+                                    pos.1,
+                                    format!("Unable to read the reference sequence: {}", e),
+                                ));
+                                None
+                            }
+                        }
+                    })
+                    .flatten();
+                Stream::new(iter, order)
+            }
+            "show_all" => {
+                return Ok(QueryValue::Print {
+                    rows: upstream.iter,
+                    opt: None,
+                })
+            }
+            "show_top" => {
+                return Ok(QueryValue::Print {
+                    rows: Box::new(upstream.iter.take(usize_arg(0)?)),
+                    opt: None,
+                })
+            }
+            "cat" => {
+                return Ok(QueryValue::Print {
+                    rows: upstream.iter,
+                    opt: Some(print_opt_arg(0)?),
+                })
+            }
+            "head" => {
+                return Ok(QueryValue::Print {
+                    rows: Box::new(upstream.iter.take(usize_arg(0)?)),
+                    opt: Some(print_opt_arg(1)?),
+                })
+            }
+            "tail" => {
+                let n = usize_arg(0)?;
+                let opt = print_opt_arg(1)?;
+                let mut last = VecDeque::new();
+                for record in upstream.iter {
+                    last.push_back(record);
+                    if last.len() > n {
+                        last.pop_front();
+                    }
+                }
+                self.check_error()?;
+                return Ok(QueryValue::Print {
+                    rows: Box::new(last.into_iter()),
+                    opt: Some(opt),
+                });
+            }
+            "save" | "save_indexed" => {
+                let path = match eval_arg(0)? {
+                    Value::Str(path) => path,
+                    other => {
+                        return error_at(
+                            pos,
+                            format!("{} expects a path, but {} found", name, other),
+                        )
+                    }
+                };
+                let options = if name == "save_indexed" {
+                    SaveOptions::new().tabix(TabixConfig::bed())
+                } else {
+                    SaveOptions::new()
+                };
+                let result = save_records(upstream.iter, path.as_ref(), &options);
+                self.check_error()?;
+                result?;
+                return Ok(QueryValue::Unit);
+            }
+            _ => return error_at(pos, format!("Unknown operator {}", name)),
+        };
+        Ok(QueryValue::Stream(ret))
+    }
+}
+
+fn error_at<T>(pos: Pos, message: impl Into<String>) -> Result<T> {
+    Err(QueryError::at(pos.0, pos.1, message))
+}
+
+fn query_expr_pos(expr: &QueryExpr) -> Pos {
+    match expr {
+        QueryExpr::Open(_, _, pos)
+        | QueryExpr::Intersect(_, pos)
+        | QueryExpr::LeftOutterIntersect(_, pos)
+        | QueryExpr::VarRef(_, pos) => *pos,
+        QueryExpr::OpChain(upstream, _) => query_expr_pos(upstream),
+    }
+}
+
+/// Open a BED file, the first malformed line stops the stream and is reported through the
+/// error slot
+fn open_bed<Rec>(reader: Box<dyn Read>, path: &Path, error: &ErrorSlot) -> RecordIter
+where
+    Rec: IntoDynRecord + LineRecord<LexicalChromSet> + 'static,
+{
+    let path = path.display().to_string();
+    let error = error.clone();
+    get_global_chrom_list().with(|chroms| {
+        let records: LineRecordStream<_, _, Rec> = reader.into_record_iter(chroms);
+        let iter = records.fallible().scan((), move |_, record| match record {
+            Ok(record) => Some(record.into_dyn_record()),
+            Err(e) => {
+                let message = format!("Unable to read {}: {}", path, e);
+                error.borrow_mut().get_or_insert(QueryError::new(message));
+                None
+            }
+        });
+        Box::new(iter) as RecordIter
+    })
+}
+
+/// Open the file and detect its format, the records are in the file order, which is verified when
+/// they are used as a sorted input. If the region is given, only the records overlapping it are
+/// read with the index.
+fn open(path: &Path, region: Option<&str>, error: &ErrorSlot) -> Result<QueryValue> {
+    let format = FileFormat::detect_file(path).map_err(|e| {
+        QueryError::new(format!(
+            "Unable to detect file format for {}, reason: {}",
+            path.display(),
+            e
+        ))
+    })?;
+
+    let unsupported_region = match format.kind {
+        FileKind::Fasta => region.is_some(),
+        FileKind::Alignment(_) => false,
+        _ => region.is_some() && !format.deflated,
+    };
+    if unsupported_region {
+        return Err(QueryError::new(format!(
+            "{}: region queries need a BAM, CRAM or bgzipped file with an index",
+            path.display()
+        )));
+    }
+
+    if format.kind == FileKind::Fasta {
+        let fasta = IndexedFasta::open(path)
+            .map_err(|e| QueryError::new(format!("{}: {}", path.display(), e)))?;
+        return Ok(QueryValue::Fasta(Rc::new(fasta)));
+    }
+
+    let iter = match format.kind {
+        FileKind::Bed(_)
+        | FileKind::BedGraph
+        | FileKind::Gff(_)
+        | FileKind::NarrowPeak
+        | FileKind::BroadPeak
+        | FileKind::Vcf
+            // The VCF files are read by htslib, unless a region is queried with the tabix index
+            if format.kind != FileKind::Vcf || region.is_some() =>
+        {
+            let reader: Box<dyn Read> = if let Some(region) = region {
+                let region = TabixReader::open(path).and_then(|reader| reader.fetch(region));
+                Box::new(region.map_err(|e| QueryError::new(format!("{}: {}", path.display(), e)))?)
+            } else if format.deflated {
+                Box::new(MultiDecoder::new(File::open(path)?)?)
+            } else {
+                Box::new(File::open(path)?)
+            };
+            match format.kind {
+                FileKind::Gff(GffFlavor::GFF3) => {
+                    open_bed::<Gff3Record<LexicalChromRef>>(reader, path, error)
+                }
+                FileKind::Gff(GffFlavor::GTF) => {
+                    open_bed::<GtfRecord<LexicalChromRef>>(reader, path, error)
+                }
+                FileKind::NarrowPeak => {
+                    open_bed::<NarrowPeak<LexicalChromRef>>(reader, path, error)
+                }
+                FileKind::BroadPeak => open_bed::<BroadPeak<LexicalChromRef>>(reader, path, error),
+                FileKind::Vcf => open_bed::<VcfLine<LexicalChromRef>>(reader, path, error),
+                FileKind::BedGraph => {
+                    open_bed::<BedGraph<LexicalChromRef, f64>>(reader, path, error)
+                }
+                FileKind::Bed(0..=3) => open_bed::<Bed3<LexicalChromRef>>(reader, path, error),
+                FileKind::Bed(4) => open_bed::<Bed4<LexicalChromRef>>(reader, path, error),
+                FileKind::Bed(5) => open_bed::<Bed5<LexicalChromRef, f64>>(reader, path, error),
+                FileKind::Bed(6) => open_bed::<Bed6<LexicalChromRef, f64>>(reader, path, error),
+                FileKind::Bed(12) => open_bed::<Bed12<LexicalChromRef, f64>>(reader, path, error),
+                _ => open_bed::<BedN<LexicalChromRef>>(reader, path, error),
+            }
+        }
+        #[cfg(feature = "hts")]
+        FileKind::Alignment(_) => hts_open::open_alignment(path, region, error)?,
+        #[cfg(feature = "hts")]
+        FileKind::Vcf => hts_open::open_vcf(path)?,
+        kind => {
+            return Err(QueryError::new(format!(
+                "Unsupported file format {:?} for {}",
+                kind,
+                path.display()
+            )))
+        }
+    };
+
+    Ok(QueryValue::Stream(Stream::new(iter, Order::Unchecked)))
+}
+
+#[cfg(feature = "hts")]
+mod hts_open {
+    use super::{DynRecord, ErrorSlot, QueryError, RecordIter, Result};
+    use crate::{
+        algorithm::AssumeSorted,
+        chromset::LexicalChromSet,
+        high_level_api::get_global_chrom_list,
+        properties::WithRegionCore,
+        query::IntoDynRecord,
+        records::{BAMRecord, BamFile, Bed3, VcfFile, VcfRecord},
+        ChromSet,
+    };
+    use std::{iter::once, path::Path, vec};
+
+    /// The number of records read from the file at a time
+    const BATCH_SIZE: usize = 4096;
+
+    /// The size of the windows that a region query is split into
+    const WINDOW_SIZE: u32 = 1 << 20;
+
+    type ReadBatch<F> = Box<dyn FnMut(&F) -> Option<Vec<DynRecord>>>;
+
+    /// The records of a file owned by the iterator. The records are read batch by batch, and the
+    /// iterators borrowing the file only live while a batch is being read, so nothing outlives the
+    /// file. The batch reader returns `None` at the end of the file.
+    struct OwningIter<F> {
+        file: F,
+        read_batch: ReadBatch<F>,
+        batch: vec::IntoIter<DynRecord>,
+    }
+
+    impl<F: 'static> OwningIter<F> {
+        fn boxed<R>(file: F, read_batch: R) -> RecordIter
+        where
+            R: FnMut(&F) -> Option<Vec<DynRecord>> + 'static,
+        {
+            Box::new(Self {
+                file,
+                read_batch: Box::new(read_batch),
+                batch: Vec::new().into_iter(),
+            })
+        }
+    }
+
+    impl<F> Iterator for OwningIter<F> {
+        type Item = DynRecord;
+        fn next(&mut self) -> Option<Self::Item> {
+            loop {
+                if let Some(record) = self.batch.next() {
+                    return Some(record);
+                }
+                self.batch = (self.read_batch)(&self.file)?.into_iter();
+            }
+        }
+    }
+
+    fn non_empty(batch: Vec<DynRecord>) -> Option<Vec<DynRecord>> {
+        if batch.is_empty() {
+            None
+        } else {
+            Some(batch)
+        }
+    }
+
+    fn hts_error(path: &Path) -> impl FnOnce(hts::HtsError) -> QueryError + '_ {
+        move |e| QueryError::new(format!("Unable to open {}: {:?}", path.display(), e))
+    }
+
+    pub(super) fn open_alignment(
+        path: &Path,
+        region: Option<&str>,
+        error: &ErrorSlot,
+    ) -> Result<RecordIter> {
+        let file = BamFile::open(path).map_err(hts_error(path))?;
+        let region = match region {
+            Some(region) => region,
+            None => {
+                return Ok(OwningIter::boxed(file, |file: &BamFile| {
+                    non_empty(get_global_chrom_list().with(|chroms| {
+                        BAMRecord::iter_of::<LexicalChromSet>(file, chroms.get_handle())
+                            .take(BATCH_SIZE)
+                            .map(IntoDynRecord::into_dyn_record)
+                            .collect()
+                    }))
+                }))
+            }
+        };
+
+        let parsed = Bed3::parse_region(region)
+            .ok_or_else(|| QueryError::new(format!("Malformed region {}", region)))?;
+        let (chrom, end) = (parsed.chrom.to_string(), parsed.end);
+        let path = path.display().to_string();
+        let error = error.clone();
+        let mut window_begin = Some(parsed.begin);
+        // The reads beginning before the previous window are already produced
+        let mut skip_before = 0;
+        Ok(OwningIter::boxed(file, move |file: &BamFile| {
+            let begin = window_begin?;
+            let window_end = begin.saturating_add(WINDOW_SIZE).min(end);
+            let window = Bed3 {
+                chrom: chrom.clone(),
+                begin,
+                end: window_end,
+            };
+            let skip = skip_before;
+            let batch = get_global_chrom_list().with(|chroms| {
+                let reads = BAMRecord::query_regions::<LexicalChromSet, String, _>(
+                    file,
+                    chroms.get_handle(),
+                    once(window).assume_sorted(),
+                )?;
+                let read_error = reads.error_slot();
+                let batch: Vec<_> = reads
+                    .filter(|read| read.begin() >= skip)
+                    .map(IntoDynRecord::into_dyn_record)
+                    .collect();
+                let read_error = read_error.borrow_mut().take();
+                read_error.map_or(Ok(batch), Err)
+            });
+            let batch = match batch {
+                Ok(batch) => batch,
+                Err(e) => {
+                    let message = format!("Unable to read {}: {}", path, e);
+                    error.borrow_mut().get_or_insert(QueryError::new(message));
+                    return None;
+                }
+            };
+            skip_before = window_end;
+            window_begin = Some(window_end).filter(|&begin| begin < end);
+            Some(batch)
+        }))
+    }
+
+    pub(super) fn open_vcf(path: &Path) -> Result<RecordIter> {
+        let file = VcfFile::open(path).map_err(hts_error(path))?;
+        Ok(OwningIter::boxed(file, |file: &VcfFile| {
+            non_empty(get_global_chrom_list().with(|chroms| {
+                VcfRecord::iter_of::<LexicalChromSet>(file, chroms.get_handle())
+                    .take(BATCH_SIZE)
+                    .map(IntoDynRecord::into_dyn_record)
+                    .collect()
+            }))
+        }))
+    }
+}
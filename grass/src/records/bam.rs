@@ -1,31 +1,168 @@
-use crate::properties::WithRegionCore;
+use crate::properties::{Strand, WithBlocks, WithName, WithRegionCore, WithStrand};
 
+use super::{Bed3, Sequence};
+use crate::algorithm::{AssumeSorted, Sorted};
 use crate::{ChromName, ChromSet, ChromSetHandle};
 use hts::alignment::{Alignment, AlignmentFile, AlignmentReader};
+pub use hts::alignment::{Cigar, CigarIter, CigarOps};
+pub use hts::TypedValue;
+use hts::{HtsError, HtsResult, Nucleotide};
+use std::borrow::Cow;
+use std::cell::RefCell;
+use std::io::{Error, ErrorKind, Result};
+use std::iter::{once, Peekable};
 use std::rc::Rc;
 
 pub type BamFile = AlignmentFile;
 
+/// The slot of the error that stops a `BAMRegionIter`, it's shared with the iterator so that it
+/// can be checked after the iterator is consumed
+pub type BamErrorSlot = Rc<RefCell<Option<Error>>>;
+
+fn hts_error(what: &str, e: HtsError) -> Error {
+    Error::other(format!("{}: {:?}", what, e))
+}
+
+/// Load the index of the file, so that a missing or unreadable index is reported before any read
+fn load_index(file: &BamFile) -> Result<()> {
+    match file.get_chrom_name_by_id(0) {
+        // The index is loaded on the first query
+        Ok(chrom) => match file.ranged(chrom, 0, 1) {
+            Ok(_) => Ok(()),
+            Err(e) => Err(hts_error("unable to load the index", e)),
+        },
+        // Nothing can be queried from a file without chromosomes
+        Err(_) => Ok(()),
+    }
+}
+
+/// The reference blocks covered by the aligned bases (M, = and X) of the CIGAR, the deletions
+/// join the blocks around them if `with_deletions` is set, while the skipped regions (N) always
+/// split the blocks
+fn cigar_blocks(
+    begin: u32,
+    cigar: impl IntoIterator<Item = Cigar>,
+    with_deletions: bool,
+) -> Vec<(u32, u32)> {
+    let mut blocks: Vec<(u32, u32)> = vec![];
+    let mut pos = begin;
+    for op in cigar {
+        let is_block = match op.ops {
+            CigarOps::Match | CigarOps::Equal | CigarOps::Diff => true,
+            CigarOps::Delete => with_deletions,
+            CigarOps::Skip => false,
+            _ => continue,
+        };
+        let end = pos + op.len;
+        if is_block {
+            match blocks.last_mut() {
+                Some(last) if last.1 == pos => last.1 = end,
+                _ => blocks.push((pos, end)),
+            }
+        }
+        pos = end;
+    }
+    blocks
+}
+
+/// The FLAG field of an alignment
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BamFlag(pub u16);
+
+impl BamFlag {
+    pub const PAIRED: u16 = 0x1;
+    pub const PROPER_PAIR: u16 = 0x2;
+    pub const UNMAPPED: u16 = 0x4;
+    pub const MATE_UNMAPPED: u16 = 0x8;
+    pub const REVERSE: u16 = 0x10;
+    pub const MATE_REVERSE: u16 = 0x20;
+    pub const FIRST_IN_PAIR: u16 = 0x40;
+    pub const SECOND_IN_PAIR: u16 = 0x80;
+    pub const SECONDARY: u16 = 0x100;
+    pub const QC_FAIL: u16 = 0x200;
+    pub const DUPLICATE: u16 = 0x400;
+    pub const SUPPLEMENTARY: u16 = 0x800;
+
+    /// Check if all the bits in the mask are set
+    pub fn contains(&self, mask: u16) -> bool {
+        self.0 & mask == mask
+    }
+
+    pub fn is_paired(&self) -> bool {
+        self.contains(Self::PAIRED)
+    }
+
+    pub fn is_proper_pair(&self) -> bool {
+        self.contains(Self::PROPER_PAIR)
+    }
+
+    pub fn is_unmapped(&self) -> bool {
+        self.contains(Self::UNMAPPED)
+    }
+
+    pub fn is_mate_unmapped(&self) -> bool {
+        self.contains(Self::MATE_UNMAPPED)
+    }
+
+    pub fn is_reverse(&self) -> bool {
+        self.contains(Self::REVERSE)
+    }
+
+    pub fn is_mate_reverse(&self) -> bool {
+        self.contains(Self::MATE_REVERSE)
+    }
+
+    pub fn is_first_in_pair(&self) -> bool {
+        self.contains(Self::FIRST_IN_PAIR)
+    }
+
+    pub fn is_second_in_pair(&self) -> bool {
+        self.contains(Self::SECOND_IN_PAIR)
+    }
+
+    pub fn is_secondary(&self) -> bool {
+        self.contains(Self::SECONDARY)
+    }
+
+    pub fn is_qc_fail(&self) -> bool {
+        self.contains(Self::QC_FAIL)
+    }
+
+    pub fn is_duplicate(&self) -> bool {
+        self.contains(Self::DUPLICATE)
+    }
+
+    pub fn is_supplementary(&self) -> bool {
+        self.contains(Self::SUPPLEMENTARY)
+    }
+}
+
 #[derive(Clone)]
 pub struct BAMRecord<'a, C: ChromName> {
     chrom_name: C,
     record: Rc<Alignment<'a>>,
 }
 
+fn chrom_list_of<C, H: ChromSetHandle<RefType = C>>(file: &BamFile, handle: &mut H) -> Vec<C> {
+    let mut chrom_list: Vec<C> = vec![];
+
+    for idx in 0.. {
+        if let Ok(chrom_name) = file.get_chrom_name_by_id(idx) {
+            chrom_list.push(handle.query_or_insert(chrom_name));
+        } else {
+            break;
+        }
+    }
+
+    chrom_list
+}
+
 impl<'a, C: ChromName + 'a> BAMRecord<'a, C> {
     pub fn iter_of<S: ChromSet<RefType = C>>(
         file: &'a BamFile,
         mut handle: S::Handle,
     ) -> impl Iterator<Item = BAMRecord<'a, C>> + 'a {
-        let mut chrom_list: Vec<C> = vec![];
-
-        for idx in 0.. {
-            if let Ok(chrom_name) = file.get_chrom_name_by_id(idx) {
-                chrom_list.push(handle.query_or_insert(chrom_name));
-            } else {
-                break;
-            }
-        }
+        let chrom_list = chrom_list_of(file, &mut handle);
 
         let iter = file.alignment_iter();
         iter.map(move |record| BAMRecord {
@@ -33,8 +170,244 @@ impl<'a, C: ChromName + 'a> BAMRecord<'a, C> {
             record: Rc::new(record),
         })
     }
+
+    /// Read the reads overlapping the sorted regions with the BAM or CRAM index, which is
+    /// loaded from the `.bai`, `.csi` or `.crai` file next to the alignment file. It fails if
+    /// the index can't be loaded.
+    pub fn query_regions<S, RC, I>(
+        file: &'a BamFile,
+        mut handle: S::Handle,
+        regions: I,
+    ) -> Result<BAMRegionIter<'a, C>>
+    where
+        S: ChromSet<RefType = C>,
+        RC: ChromName,
+        I: IntoIterator,
+        I::IntoIter: Sorted + 'a,
+        I::Item: WithRegionCore<RC>,
+    {
+        load_index(file)?;
+        let regions = regions.into_iter().map(|region| {
+            let chrom = ChromName::to_string(region.chrom()).into_owned();
+            (chrom, region.begin(), region.end())
+        });
+        Ok(BAMRegionIter {
+            file,
+            chrom_list: chrom_list_of(file, &mut handle),
+            regions: (Box::new(regions) as QueryRegions<'a>).peekable(),
+            view: None,
+            skip_before: 0,
+            last_region: None,
+            error: Default::default(),
+            failed: false,
+        })
+    }
+
+    /// Read the reads overlapping a region like `chr1:1000-2000` with the index, see
+    /// `Bed3::parse_region` for the region syntax. It fails if the region is malformed or the
+    /// index can't be loaded.
+    pub fn fetch<S: ChromSet<RefType = C>>(
+        file: &'a BamFile,
+        handle: S::Handle,
+        region: &str,
+    ) -> Result<BAMRegionIter<'a, C>> {
+        let region = Bed3::parse_region(region).ok_or_else(|| {
+            Error::new(
+                ErrorKind::InvalidInput,
+                format!("malformed region {}", region),
+            )
+        })?;
+        let region = Bed3 {
+            chrom: region.chrom.to_string(),
+            begin: region.begin,
+            end: region.end,
+        };
+        Self::query_regions::<S, String, _>(file, handle, once(region).assume_sorted())
+    }
+}
+
+impl<'a, C: ChromName> BAMRecord<'a, C> {
+    /// The mapping quality, 255 means it's not available
+    pub fn mapq(&self) -> u8 {
+        self.record.mapping_qual()
+    }
+
+    pub fn flag(&self) -> BamFlag {
+        BamFlag(self.record.flags())
+    }
+
+    pub fn is_reverse(&self) -> bool {
+        self.flag().is_reverse()
+    }
+
+    pub fn is_duplicate(&self) -> bool {
+        self.flag().is_duplicate()
+    }
+
+    pub fn is_unmapped(&self) -> bool {
+        self.flag().is_unmapped()
+    }
+
+    pub fn is_secondary(&self) -> bool {
+        self.flag().is_secondary()
+    }
+
+    pub fn is_supplementary(&self) -> bool {
+        self.flag().is_supplementary()
+    }
+
+    pub fn read_name(&self) -> Cow<'_, str> {
+        self.record.query_name()
+    }
+
+    pub fn cigar(&self) -> CigarIter<'_> {
+        self.record.cigar()
+    }
+
+    /// The observed template length, which is negative for the rightmost read of the pair
+    pub fn insert_size(&self) -> i64 {
+        self.record.insert_size()
+    }
+
+    /// Get the value of the optional field, such as `tag(b"NM")` for the edit distance
+    pub fn tag(&self, name: &[u8; 2]) -> Option<TypedValue<'_>> {
+        self.record
+            .aux_iter()
+            .find(|(tag, _)| tag == name)
+            .map(|(_, value)| value)
+    }
+
+    /// The read sequence as it's stored in the file, which is reverse complemented for the
+    /// reads on the reverse strand
+    pub fn seq(&self) -> Sequence {
+        Sequence::new(
+            self.record
+                .sequence()
+                .map(|base| match base {
+                    Nucleotide::A => b'A',
+                    Nucleotide::T => b'T',
+                    Nucleotide::C => b'C',
+                    Nucleotide::G => b'G',
+                    Nucleotide::N => b'N',
+                })
+                .collect(),
+        )
+    }
+
+    /// The reference blocks of the aligned bases, which are split by the skipped regions, such
+    /// as the introns of a spliced read, and by the deletions unless `with_deletions` is set
+    pub fn aligned_blocks(&self, with_deletions: bool) -> Vec<(u32, u32)> {
+        cigar_blocks(self.begin(), self.cigar(), with_deletions)
+    }
+
+    /// The per base qualities without the +33 offset, 0xff means the qualities are not stored
+    pub fn qual(&self) -> &[u8] {
+        self.record.per_base_qual()
+    }
 }
 
+type QueryRegions<'a> = Box<dyn Iterator<Item = (String, u32, u32)> + 'a>;
+/// Reads the next alignment in the current region
+type RegionView<'a> = Box<dyn Fn() -> HtsResult<Option<Alignment<'a>>> + 'a>;
+
+/// The reads overlapping a sorted stream of regions, which seeks to each region with the index
+/// instead of scanning the whole file. The overlapping regions are merged and a read overlapping
+/// more than one region is produced only once, so the reads are also sorted. The regions on the
+/// chromosomes that are not in the file are skipped.
+///
+/// Note: If the file can't be read, the iterator ends and the error is kept in the
+/// `error_slot()`.
+pub struct BAMRegionIter<'a, C: ChromName> {
+    file: &'a BamFile,
+    chrom_list: Vec<C>,
+    regions: Peekable<QueryRegions<'a>>,
+    view: Option<RegionView<'a>>,
+    /// The reads beginning before the end of the previous region are already produced
+    skip_before: u32,
+    last_region: Option<(String, u32)>,
+    error: BamErrorSlot,
+    failed: bool,
+}
+
+impl<'a, C: ChromName> BAMRegionIter<'a, C> {
+    /// The slot of the error that stops the iterator
+    pub fn error_slot(&self) -> BamErrorSlot {
+        self.error.clone()
+    }
+
+    fn fail(&mut self, e: Error) -> Option<BAMRecord<'a, C>> {
+        *self.error.borrow_mut() = Some(e);
+        self.view = None;
+        self.failed = true;
+        None
+    }
+
+    fn next_region(&mut self) -> Option<(String, u32, u32)> {
+        let (chrom, begin, mut end) = self.regions.next()?;
+        while let Some((next_chrom, next_begin, next_end)) = self.regions.peek() {
+            if *next_chrom != chrom || *next_begin > end {
+                break;
+            }
+            end = end.max(*next_end);
+            self.regions.next();
+        }
+        Some((chrom, begin, end))
+    }
+}
+
+impl<'a, C: ChromName> Iterator for BAMRegionIter<'a, C> {
+    type Item = BAMRecord<'a, C>;
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.failed {
+            return None;
+        }
+        loop {
+            let read = self.view.as_ref().map(|read_next| read_next());
+            match read {
+                Some(Ok(Some(record))) => {
+                    let begin = record.ref_begin() as u32;
+                    if begin < self.skip_before {
+                        continue;
+                    }
+                    return Some(BAMRecord {
+                        chrom_name: self.chrom_list[record.chrom_id()].clone(),
+                        record: Rc::new(record),
+                    });
+                }
+                Some(Err(e)) => return self.fail(hts_error("unable to read the alignment", e)),
+                Some(Ok(None)) | None => {}
+            }
+
+            let (chrom, begin, end) = self.next_region()?;
+            self.skip_before = match &self.last_region {
+                Some((last_chrom, last_end)) if *last_chrom == chrom => *last_end,
+                _ => 0,
+            };
+            self.view = match self.file.ranged(&chrom, begin as i64, end as i64) {
+                Ok(view) => Some(Box::new(move || {
+                    let record = match view.read_next()? {
+                        Some(record) => record,
+                        None => return Ok(None),
+                    };
+                    // The alignment only borrows the memory pool and the header of the file,
+                    // not the index iterator of the view, so it lives as long as the file.
+                    Ok(Some(unsafe {
+                        std::mem::transmute::<Alignment<'_>, Alignment<'a>>(record)
+                    }))
+                })),
+                Err(HtsError::NoSuchChromosome) => None,
+                Err(e) => {
+                    let what = format!("unable to query {}:{}-{}", chrom, begin + 1, end);
+                    return self.fail(hts_error(&what, e));
+                }
+            };
+            self.last_region = Some((chrom, end));
+        }
+    }
+}
+
+impl<'a, C: ChromName> Sorted for BAMRegionIter<'a, C> {}
+
 impl<'a, C: ChromName> WithRegionCore<C> for BAMRecord<'a, C> {
     fn begin(&self) -> u32 {
         self.record.ref_begin() as u32
@@ -48,3 +421,100 @@ impl<'a, C: ChromName> WithRegionCore<C> for BAMRecord<'a, C> {
         &self.chrom_name
     }
 }
+
+impl<'a, C: ChromName> WithName for BAMRecord<'a, C> {
+    fn name(&self) -> &str {
+        match self.record.query_name() {
+            Cow::Borrowed(name) => name,
+            // The read names are ASCII, which is always borrowed
+            Cow::Owned(_) => ".",
+        }
+    }
+}
+
+impl<'a, C: ChromName> WithStrand for BAMRecord<'a, C> {
+    fn strand(&self) -> Option<Strand> {
+        if self.is_reverse() {
+            Some(Strand::Neg)
+        } else {
+            Some(Strand::Pos)
+        }
+    }
+}
+
+impl<'a, C: ChromName> WithBlocks<C> for BAMRecord<'a, C> {
+    fn block_regions(&self) -> Vec<(u32, u32)> {
+        self.aligned_blocks(false)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_cigar_blocks() {
+        // 10M 2D 5M 100N 3I 7= 4S
+        let cigar = || {
+            vec![
+                Cigar {
+                    ops: CigarOps::Match,
+                    len: 10,
+                },
+                Cigar {
+                    ops: CigarOps::Delete,
+                    len: 2,
+                },
+                Cigar {
+                    ops: CigarOps::Match,
+                    len: 5,
+                },
+                Cigar {
+                    ops: CigarOps::Skip,
+                    len: 100,
+                },
+                Cigar {
+                    ops: CigarOps::Insert,
+                    len: 3,
+                },
+                Cigar {
+                    ops: CigarOps::Equal,
+                    len: 7,
+                },
+                Cigar {
+                    ops: CigarOps::Soft,
+                    len: 4,
+                },
+            ]
+        };
+        assert_eq!(
+            cigar_blocks(100, cigar(), false),
+            vec![(100, 110), (112, 117), (217, 224)]
+        );
+        assert_eq!(
+            cigar_blocks(100, cigar(), true),
+            vec![(100, 117), (217, 224)]
+        );
+    }
+
+    #[test]
+    fn test_fetch_errors() {
+        use crate::LexicalChromSet;
+
+        // There's no index next to the file
+        let file = BamFile::open("../data/range.bam").unwrap();
+        let chroms = LexicalChromSet::new();
+        let fetch = |region: &str| {
+            BAMRecord::fetch::<LexicalChromSet>(&file, chroms.get_handle(), region)
+                .err()
+                .unwrap()
+        };
+        assert!(fetch("CHROMOSOME_I:1-100")
+            .to_string()
+            .contains("unable to load the index"));
+        assert_eq!(
+            fetch("CHROMOSOME_I:200-100").kind(),
+            ErrorKind::InvalidInput
+        );
+    }
+}
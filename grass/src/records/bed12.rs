@@ -0,0 +1,230 @@
+use super::{invalid_field, next_field, parse_field, Bed6};
+use num::Num;
+use std::fmt::Display;
+use std::io::{Result, Write};
+use std::str::FromStr;
+use std::sync::Arc;
+
+use crate::{
+    chromset::LexicalChromRef,
+    properties::{
+        Parsable, ParseFailure, Serializable, Strand, WithBlocks, WithName, WithRegionCore,
+        WithScore, WithStrand,
+    },
+    ChromName, ChromSetHandle, WithChromSet,
+};
+
+/// A BED12 record, which describes a feature made of blocks, such as a transcript and its exons
+#[derive(Clone)]
+pub struct Bed12<T: ChromName = LexicalChromRef, N: Num = f64> {
+    pub core: Bed6<T, N>,
+    pub thick_begin: u32,
+    pub thick_end: u32,
+    /// The display color, `None` if it's written as `0`
+    pub item_rgb: Option<(u8, u8, u8)>,
+    /// The sizes of the blocks
+    pub block_sizes: Arc<[u32]>,
+    /// The begin of the blocks, relative to the begin of the record
+    pub block_starts: Arc<[u32]>,
+}
+
+impl<T: ChromName, H: ChromSetHandle, N: Num> WithChromSet<H> for Bed12<T, N> {
+    type Result = Bed12<H::RefType, N>;
+    fn with_chrom_set(self, handle: &mut H) -> Self::Result {
+        self.with_chrom_list(handle)
+    }
+}
+
+fn parse_item_rgb(s: &str) -> Option<Option<(u8, u8, u8)>> {
+    if s == "0" {
+        return Some(None);
+    }
+    let mut channels = s.split(',').map(|value| value.parse().ok());
+    let rgb = (channels.next()??, channels.next()??, channels.next()??);
+    if channels.next().is_some() {
+        return None;
+    }
+    Some(Some(rgb))
+}
+
+fn parse_block_list(s: &str, count: usize) -> Option<Arc<[u32]>> {
+    let values = s
+        .trim_end_matches(',')
+        .split(',')
+        .map(|value| value.parse().ok())
+        .collect::<Option<Vec<u32>>>()?;
+    if values.len() != count {
+        return None;
+    }
+    Some(values.into())
+}
+
+impl<'a, N: Num + FromStr> Parsable<'a> for Bed12<&'a str, N> {
+    fn try_parse(s: &'a str) -> std::result::Result<(Self, usize), ParseFailure> {
+        let (core, mut end) = Bed6::try_parse(s)?;
+        let line = s.trim_end_matches(&['\r', '\n'][..]);
+        let mut fields = [""; 6];
+        for (idx, field) in fields.iter_mut().enumerate() {
+            let (value, value_end) = next_field(line, end, idx + 7)?;
+            *field = value;
+            end = value_end;
+        }
+        let [thick_begin, thick_end, item_rgb, block_count, block_sizes, block_starts] = fields;
+
+        let thick_begin = parse_field(thick_begin, 7, "thick begin")?;
+        let thick_end = parse_field(thick_end, 8, "thick end")?;
+        let item_rgb =
+            parse_item_rgb(item_rgb).ok_or_else(|| invalid_field(9, "item RGB", item_rgb))?;
+        let count = parse_field(block_count, 10, "block count")?;
+        let invalid_blocks = |column, what, field| {
+            (
+                column,
+                format!("invalid {} '{}' for {} blocks", what, field, count),
+            )
+        };
+        let block_sizes = parse_block_list(block_sizes, count)
+            .ok_or_else(|| invalid_blocks(11, "block sizes", block_sizes))?;
+        let block_starts = parse_block_list(block_starts, count)
+            .ok_or_else(|| invalid_blocks(12, "block starts", block_starts))?;
+        let length = core.end().checked_sub(core.begin()).ok_or_else(|| {
+            (
+                3,
+                "the end position is before the begin position".to_string(),
+            )
+        })?;
+        for (idx, (start, size)) in block_starts.iter().zip(block_sizes.iter()).enumerate() {
+            if start.checked_add(*size).is_none_or(|end| end > length) {
+                return Err((12, format!("block {} is out of the record range", idx + 1)));
+            }
+        }
+
+        Ok((
+            Self {
+                core,
+                thick_begin,
+                thick_end,
+                item_rgb,
+                block_sizes,
+                block_starts,
+            },
+            end,
+        ))
+    }
+}
+
+impl<T: ChromName, N: Num> WithRegionCore<T> for Bed12<T, N> {
+    fn begin(&self) -> u32 {
+        self.core.begin()
+    }
+
+    fn end(&self) -> u32 {
+        self.core.end()
+    }
+
+    fn chrom(&self) -> &T {
+        self.core.chrom()
+    }
+}
+
+impl<T: ChromName, N: Num> WithName for Bed12<T, N> {
+    fn name(&self) -> &str {
+        self.core.name()
+    }
+}
+
+fn write_block_list<W: Write>(mut fp: W, values: &[u32]) -> Result<()> {
+    for value in values {
+        write!(fp, "{},", value)?;
+    }
+    Ok(())
+}
+
+impl<T: ChromName, N: Display + Num> Serializable for Bed12<T, N> {
+    fn dump<W: Write>(&self, mut fp: W) -> Result<()> {
+        self.core.dump(&mut fp)?;
+        write!(fp, "\t{}\t{}\t", self.thick_begin, self.thick_end)?;
+        match self.item_rgb {
+            Some((r, g, b)) => write!(fp, "{},{},{}", r, g, b)?,
+            None => fp.write_all(b"0")?,
+        }
+        write!(fp, "\t{}\t", self.block_sizes.len())?;
+        write_block_list(&mut fp, &self.block_sizes)?;
+        fp.write_all(b"\t")?;
+        write_block_list(&mut fp, &self.block_starts)
+    }
+}
+
+impl<T: ChromName, N: Num> Bed12<T, N> {
+    pub fn with_chrom_list<H: ChromSetHandle>(self, chrom_list: &mut H) -> Bed12<H::RefType, N> {
+        Bed12 {
+            core: self.core.with_chrom_list(chrom_list),
+            thick_begin: self.thick_begin,
+            thick_end: self.thick_end,
+            item_rgb: self.item_rgb,
+            block_sizes: self.block_sizes,
+            block_starts: self.block_starts,
+        }
+    }
+
+    pub fn block_count(&self) -> usize {
+        self.block_sizes.len()
+    }
+
+    /// Split the record into its blocks, each block is a BED6 record that has the name, score
+    /// and strand of this record
+    pub fn blocks(&self) -> impl Iterator<Item = Bed6<T, N>> + '_
+    where
+        N: Clone,
+    {
+        self.block_regions().into_iter().map(move |(begin, end)| {
+            let mut block = self.core.clone();
+            block.core.core.core.begin = begin;
+            block.core.core.core.end = end;
+            block
+        })
+    }
+}
+
+impl<T: ChromName, N: Num + Clone> WithScore<N> for Bed12<T, N> {
+    fn score(&self) -> Option<N> {
+        self.core.score()
+    }
+}
+impl<T: ChromName, N: Num> WithStrand for Bed12<T, N> {
+    fn strand(&self) -> Option<Strand> {
+        self.core.strand()
+    }
+}
+
+impl<T: ChromName, N: Num> WithBlocks<T> for Bed12<T, N> {
+    fn block_regions(&self) -> Vec<(u32, u32)> {
+        let begin = self.begin();
+        self.block_starts
+            .iter()
+            .zip(self.block_sizes.iter())
+            .map(|(start, size)| (begin + start, begin + start + size))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse_block_range() {
+        let parse = |s: &'static str| Bed12::<&str>::parse(s).map(|(r, _)| r);
+        assert!(parse("chr1\t100\t200\tx\t0\t+\t100\t200\t0\t2\t10,20,\t0,80,").is_some());
+        // The blocks must be in the record, and the record must not end before it begins
+        assert!(parse("chr1\t100\t200\tx\t0\t+\t100\t200\t0\t1\t10,\t95,").is_none());
+        assert!(parse("chr1\t200\t100\tx\t0\t+\t100\t200\t0\t1\t10,\t0,").is_none());
+        assert!(parse("chr1\t0\t10\tx\t0\t+\t0\t10\t0\t1\t4294967295,\t1,").is_none());
+    }
+
+    #[test]
+    fn test_write_block_list() {
+        let mut buf = vec![];
+        write_block_list(&mut buf, &[0, 3_000_000_000]).unwrap();
+        assert_eq!(buf, b"0,3000000000,");
+    }
+}
@@ -1,6 +1,10 @@
+use super::{missing_field, parse_field};
 use crate::{
     chromset::LexicalChromRef,
-    properties::{Parsable, Serializable, WithName, WithRegionCore, WithScore, WithStrand},
+    properties::{
+        Parsable, ParseFailure, Serializable, WithBlocks, WithName, WithRegionCore, WithScore,
+        WithStrand,
+    },
 };
 use crate::{ChromName, ChromSetHandle, WithChromSet};
 use std::io::{Result, Write};
@@ -31,7 +35,7 @@ impl<T: ChromName, H: ChromSetHandle> WithChromSet<H> for Bed3<T> {
 }
 
 impl<'a> Parsable<'a> for Bed3<&'a str> {
-    fn parse(s: &'a str) -> Option<(Self, usize)> {
+    fn try_parse(s: &'a str) -> std::result::Result<(Self, usize), ParseFailure> {
         let mut bytes = s.as_bytes();
 
         if bytes.last() == Some(&b'\n') {
@@ -39,22 +43,50 @@ impl<'a> Parsable<'a> for Bed3<&'a str> {
         }
 
         let mut token_pos_iter = memchr::Memchr::new(b'\t', bytes);
-        let end_1 = token_pos_iter.next()?;
-        let end_2 = token_pos_iter.next()?;
+        let end_1 = token_pos_iter.next().ok_or_else(|| missing_field(2))?;
+        let end_2 = token_pos_iter.next().ok_or_else(|| missing_field(3))?;
         let end_3 = token_pos_iter.next().unwrap_or(bytes.len());
         let chrom = &s[..end_1];
 
-        Some((
+        Ok((
             Self {
                 chrom,
-                begin: s[end_1 + 1..end_2].parse().ok()?,
-                end: s[end_2 + 1..end_3].parse().ok()?,
+                begin: parse_field(&s[end_1 + 1..end_2], 2, "begin position")?,
+                end: parse_field(&s[end_2 + 1..end_3], 3, "end position")?,
             },
             end_3,
         ))
     }
 }
 
+impl<'a> Bed3<&'a str> {
+    /// Parse a region like `chr1:1,000-2,000`, which is 1-based and closed like samtools. A
+    /// region without the end runs to the end of the chromosome, and a region that is only the
+    /// chromosome name covers the whole chromosome.
+    pub fn parse_region(s: &'a str) -> Option<Self> {
+        let s = s.trim();
+        let parse_pos = |pos: &str| pos.replace(',', "").parse::<u32>().ok();
+        let range = s.rfind(':').and_then(|pos| {
+            let range = &s[pos + 1..];
+            let (begin, end) = match range.find('-') {
+                Some(dash) => (parse_pos(&range[..dash])?, parse_pos(&range[dash + 1..])?),
+                None => (parse_pos(range)?, u32::MAX),
+            };
+            Some((&s[..pos], begin, end))
+        });
+        // Contig names may contain colons, so a suffix that is not a range is a part of the name
+        let (chrom, begin, end) = range.unwrap_or((s, 1, u32::MAX));
+        if chrom.is_empty() || begin > end {
+            return None;
+        }
+        Some(Self {
+            chrom,
+            begin: begin.saturating_sub(1),
+            end,
+        })
+    }
+}
+
 impl<C: ChromName + Clone> Bed3<C> {
     pub fn new<T: WithRegionCore<C>>(region: T) -> Self {
         Self {
@@ -96,3 +128,20 @@ impl<T: ChromName> WithName for Bed3<T> {
 }
 impl<T: ChromName> WithScore<i32> for Bed3<T> {}
 impl<T: ChromName> WithStrand for Bed3<T> {}
+impl<T: ChromName> WithBlocks<T> for Bed3<T> {}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse_region() {
+        let parse = |s| Bed3::parse_region(s).map(|r| (r.chrom, r.begin, r.end));
+        assert_eq!(parse("chr1:1,001-2,000"), Some(("chr1", 1000, 2000)));
+        assert_eq!(parse("chr1:1001"), Some(("chr1", 1000, u32::MAX)));
+        assert_eq!(parse("chr1"), Some(("chr1", 0, u32::MAX)));
+        assert_eq!(parse("chrUn:abc"), Some(("chrUn:abc", 0, u32::MAX)));
+        assert_eq!(parse("chr1:2000-1000"), None);
+        assert_eq!(parse(""), None);
+    }
+}
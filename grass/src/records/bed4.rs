@@ -1,17 +1,20 @@
 use super::Bed3;
 use crate::{
     chromset::LexicalChromRef,
-    properties::{Parsable, Serializable, WithName, WithRegionCore, WithScore, WithStrand},
+    properties::{
+        Parsable, ParseFailure, Serializable, WithBlocks, WithName, WithRegionCore, WithScore,
+        WithStrand,
+    },
     ChromName, ChromSetHandle, WithChromSet,
 };
 
 use std::io::{Result, Write};
-use std::rc::Rc;
+use std::sync::Arc;
 
 #[derive(Clone)]
 pub struct Bed4<T: ChromName = LexicalChromRef> {
     pub core: Bed3<T>,
-    pub name: Rc<String>,
+    pub name: Arc<String>,
 }
 
 impl<T: ChromName, H: ChromSetHandle> WithChromSet<H> for Bed4<T> {
@@ -24,19 +27,24 @@ impl<T: ChromName, H: ChromSetHandle> WithChromSet<H> for Bed4<T> {
 }
 
 impl<'a> Parsable<'a> for Bed4<&'a str> {
-    fn parse(s: &'a str) -> Option<(Self, usize)> {
-        let (core, rem) = Bed3::parse(s)?;
-        let rem = s[rem + 1..]
-            .split(|c| c == '\t' || c == '\n')
-            .next()
-            .unwrap_or(".")
-            .to_string();
-        Some((
+    fn try_parse(s: &'a str) -> std::result::Result<(Self, usize), ParseFailure> {
+        let (core, mut end) = Bed3::try_parse(s)?;
+        let line = s.trim_end_matches(&['\r', '\n'][..]);
+        let name = if end < line.len() {
+            let begin = end + 1;
+            end = line[begin..]
+                .find('\t')
+                .map_or(line.len(), |size| begin + size);
+            &line[begin..end]
+        } else {
+            "."
+        };
+        Ok((
             Self {
                 core,
-                name: Rc::new(rem),
+                name: Arc::new(name.to_string()),
             },
-            s.len(),
+            end,
         ))
     }
 }
@@ -80,3 +88,4 @@ impl<T: ChromName> Bed4<T> {
 
 impl<T: ChromName> WithScore<i32> for Bed4<T> {}
 impl<T: ChromName> WithStrand for Bed4<T> {}
+impl<T: ChromName> WithBlocks<T> for Bed4<T> {}
@@ -1,10 +1,15 @@
 use super::{Bed3, Bed4};
-use num::{Num, ToPrimitive};
+use num::Num;
+use std::fmt::Display;
 use std::io::{Result, Write};
+use std::str::FromStr;
 
 use crate::{
     chromset::LexicalChromRef,
-    properties::{Parsable, Serializable, WithName, WithRegionCore, WithScore, WithStrand},
+    properties::{
+        Parsable, ParseFailure, Serializable, WithBlocks, WithName, WithRegionCore, WithScore,
+        WithStrand,
+    },
     ChromName, ChromSetHandle, WithChromSet,
 };
 
@@ -23,16 +28,20 @@ impl<T: ChromName, H: ChromSetHandle, N: Num> WithChromSet<H> for Bed5<T, N> {
     }
 }
 
-impl<'a, N: Num> Parsable<'a> for Bed5<&'a str, N> {
-    fn parse(s: &'a str) -> Option<(Self, usize)> {
-        let (core, rem) = Bed4::parse(s)?;
-        Some((
-            Self {
-                core,
-                score: N::from_str_radix(&s[rem..], 10).ok(),
-            },
-            s.len(),
-        ))
+impl<'a, N: Num + FromStr> Parsable<'a> for Bed5<&'a str, N> {
+    fn try_parse(s: &'a str) -> std::result::Result<(Self, usize), ParseFailure> {
+        let (core, mut end) = Bed4::try_parse(s)?;
+        let line = s.trim_end_matches(&['\r', '\n'][..]);
+        let score = if end < line.len() {
+            let begin = end + 1;
+            end = line[begin..]
+                .find('\t')
+                .map_or(line.len(), |size| begin + size);
+            line[begin..end].parse().ok()
+        } else {
+            None
+        };
+        Ok((Self { core, score }, end))
     }
 }
 
@@ -56,13 +65,12 @@ impl<T: ChromName, N: Num> WithName for Bed5<T, N> {
     }
 }
 
-// TODO:gerenalize this implementation
-impl<T: ChromName, N: ToPrimitive + Num> Serializable for Bed5<T, N> {
+impl<T: ChromName, N: Display + Num> Serializable for Bed5<T, N> {
     fn dump<W: Write>(&self, mut fp: W) -> Result<()> {
         self.core.dump(&mut fp)?;
         fp.write(b"\t")?;
         if let Some(ref score) = self.score {
-            crate::ioutils::write_number(fp, score.to_i32().unwrap_or(-1))
+            crate::ioutils::write_score(fp, score)
         } else {
             fp.write_all(b".")
         }
@@ -80,7 +88,7 @@ impl<T: ChromName, N: Num> Bed5<T, N> {
     pub fn new(chrom: T, begin: u32, end: u32, name: &str, score: N) -> Self {
         Self {
             core: Bed4 {
-                name: std::rc::Rc::new(name.to_string()),
+                name: std::sync::Arc::new(name.to_string()),
                 core: Bed3 { begin, end, chrom },
             },
             score: Some(score),
@@ -88,5 +96,10 @@ impl<T: ChromName, N: Num> Bed5<T, N> {
     }
 }
 
-impl<T: ChromName, N: Num> WithScore<i32> for Bed5<T, N> {}
+impl<T: ChromName, N: Num + Clone> WithScore<N> for Bed5<T, N> {
+    fn score(&self) -> Option<N> {
+        self.score.clone()
+    }
+}
 impl<T: ChromName, N: Num> WithStrand for Bed5<T, N> {}
+impl<T: ChromName, N: Num> WithBlocks<T> for Bed5<T, N> {}
@@ -0,0 +1,114 @@
+use super::{invalid_field, next_field, parse_field, Bed3, Bed4, Bed5};
+use num::Num;
+use std::fmt::Display;
+use std::io::{Result, Write};
+use std::str::FromStr;
+use std::sync::Arc;
+
+use crate::{
+    chromset::LexicalChromRef,
+    properties::{
+        Parsable, ParseFailure, Serializable, Strand, StrandColumn, WithBlocks, WithName,
+        WithRegionCore, WithScore, WithStrand,
+    },
+    ChromName, ChromSetHandle, WithChromSet,
+};
+
+#[derive(Clone)]
+pub struct Bed6<T: ChromName = LexicalChromRef, N: Num = f64> {
+    pub core: Bed5<T, N>,
+    /// `None` if the strand is unknown, which is written as `.`
+    pub strand: Option<Strand>,
+}
+
+impl<T: ChromName, H: ChromSetHandle, N: Num> WithChromSet<H> for Bed6<T, N> {
+    type Result = Bed6<H::RefType, N>;
+    fn with_chrom_set(self, handle: &mut H) -> Self::Result {
+        self.with_chrom_list(handle)
+    }
+}
+
+impl<'a, N: Num + FromStr> Parsable<'a> for Bed6<&'a str, N> {
+    fn try_parse(s: &'a str) -> std::result::Result<(Self, usize), ParseFailure> {
+        let (core, mut end) = Bed3::try_parse(s)?;
+        let line = s.trim_end_matches(&['\r', '\n'][..]);
+        let mut fields = [""; 3];
+        for (idx, field) in fields.iter_mut().enumerate() {
+            let (value, value_end) = next_field(line, end, idx + 4)?;
+            *field = value;
+            end = value_end;
+        }
+        let [name, score, strand] = fields;
+
+        let score = match score {
+            "." => None,
+            score => Some(parse_field(score, 5, "score")?),
+        };
+
+        Ok((
+            Self {
+                core: Bed5 {
+                    core: Bed4 {
+                        core,
+                        name: Arc::new(name.to_string()),
+                    },
+                    score,
+                },
+                strand: StrandColumn::parse(strand)
+                    .ok_or_else(|| invalid_field(6, "strand", strand))?
+                    .strand(),
+            },
+            end,
+        ))
+    }
+}
+
+impl<T: ChromName, N: Num> WithRegionCore<T> for Bed6<T, N> {
+    fn begin(&self) -> u32 {
+        self.core.begin()
+    }
+
+    fn end(&self) -> u32 {
+        self.core.end()
+    }
+
+    fn chrom(&self) -> &T {
+        self.core.chrom()
+    }
+}
+
+impl<T: ChromName, N: Num> WithName for Bed6<T, N> {
+    fn name(&self) -> &str {
+        self.core.name()
+    }
+}
+
+impl<T: ChromName, N: Display + Num> Serializable for Bed6<T, N> {
+    fn dump<W: Write>(&self, mut fp: W) -> Result<()> {
+        self.core.dump(&mut fp)?;
+        fp.write(b"\t")?;
+        fp.write_all(StrandColumn::from(self.strand).as_str().as_bytes())
+    }
+}
+
+impl<T: ChromName, N: Num> Bed6<T, N> {
+    pub fn with_chrom_list<H: ChromSetHandle>(self, chrom_list: &mut H) -> Bed6<H::RefType, N> {
+        Bed6 {
+            core: self.core.with_chrom_list(chrom_list),
+            strand: self.strand,
+        }
+    }
+}
+
+impl<T: ChromName, N: Num + Clone> WithScore<N> for Bed6<T, N> {
+    fn score(&self) -> Option<N> {
+        self.core.score()
+    }
+}
+impl<T: ChromName, N: Num> WithStrand for Bed6<T, N> {
+    fn strand(&self) -> Option<Strand> {
+        self.strand
+    }
+}
+
+impl<T: ChromName, N: Num> WithBlocks<T> for Bed6<T, N> {}
@@ -0,0 +1,102 @@
+use super::{next_field, parse_field, Bed3};
+use num::Num;
+use std::fmt::Display;
+use std::io::{Result, Write};
+use std::str::FromStr;
+
+use crate::{
+    chromset::LexicalChromRef,
+    properties::{
+        Parsable, ParseFailure, Serializable, WithBlocks, WithName, WithRegionCore, WithScore,
+        WithStrand,
+    },
+    ChromName, ChromSetHandle, WithChromSet,
+};
+
+/// A bedGraph record, which is a region with a value, such as the depth of the region
+#[derive(Clone)]
+pub struct BedGraph<T: ChromName = LexicalChromRef, N: Num = f64> {
+    pub core: Bed3<T>,
+    pub value: N,
+}
+
+impl<T: ChromName, H: ChromSetHandle, N: Num> WithChromSet<H> for BedGraph<T, N> {
+    type Result = BedGraph<H::RefType, N>;
+    fn with_chrom_set(self, handle: &mut H) -> Self::Result {
+        self.with_chrom_list(handle)
+    }
+}
+
+impl<'a, N: Num + FromStr> Parsable<'a> for BedGraph<&'a str, N> {
+    fn try_parse(s: &'a str) -> std::result::Result<(Self, usize), ParseFailure> {
+        let (core, end) = Bed3::try_parse(s)?;
+        let line = s.trim_end_matches(&['\r', '\n'][..]);
+        let (value, end) = next_field(line, end, 4)?;
+        let value = parse_field(value, 4, "value")?;
+        Ok((Self { core, value }, end))
+    }
+}
+
+impl<T: ChromName, N: Num> BedGraph<T, N> {
+    pub fn new(chrom: T, begin: u32, end: u32, value: N) -> Self {
+        Self {
+            core: Bed3 { begin, end, chrom },
+            value,
+        }
+    }
+
+    pub fn with_chrom_list<H: ChromSetHandle>(self, chrom_list: &mut H) -> BedGraph<H::RefType, N> {
+        BedGraph {
+            core: self.core.with_chrom_list(chrom_list),
+            value: self.value,
+        }
+    }
+}
+
+impl BedGraph {
+    /// The track line that should be put before the records, so that genome browsers such as
+    /// IGV and UCSC load the file as a bedGraph track
+    pub fn track_line(name: Option<&str>) -> String {
+        match name {
+            Some(name) => format!("track type=bedGraph name=\"{}\"", name),
+            None => "track type=bedGraph".to_string(),
+        }
+    }
+}
+
+impl<T: ChromName, N: Num> WithRegionCore<T> for BedGraph<T, N> {
+    fn begin(&self) -> u32 {
+        self.core.begin()
+    }
+
+    fn end(&self) -> u32 {
+        self.core.end()
+    }
+
+    fn chrom(&self) -> &T {
+        self.core.chrom()
+    }
+}
+
+impl<T: ChromName, N: Num> WithName for BedGraph<T, N> {
+    fn name(&self) -> &str {
+        "."
+    }
+}
+
+impl<T: ChromName, N: Display + Num> Serializable for BedGraph<T, N> {
+    fn dump<W: Write>(&self, mut fp: W) -> Result<()> {
+        self.core.dump(&mut fp)?;
+        fp.write_all(b"\t")?;
+        crate::ioutils::write_score(fp, &self.value)
+    }
+}
+
+impl<T: ChromName, N: Num + Clone> WithScore<N> for BedGraph<T, N> {
+    fn score(&self) -> Option<N> {
+        Some(self.value.clone())
+    }
+}
+
+impl<T: ChromName, N: Num> WithStrand for BedGraph<T, N> {}
+impl<T: ChromName, N: Num> WithBlocks<T> for BedGraph<T, N> {}
@@ -0,0 +1,108 @@
+use super::Bed3;
+use std::io::{Result, Write};
+use std::sync::Arc;
+
+use crate::{
+    chromset::LexicalChromRef,
+    properties::{
+        Parsable, ParseFailure, Serializable, Strand, StrandColumn, WithBlocks, WithName,
+        WithRegionCore, WithScore, WithStrand,
+    },
+    ChromName, ChromSetHandle, WithChromSet,
+};
+
+/// A BED record with any number of columns, the columns after the end column are kept as they
+/// are, thus they are written back verbatim when the record is dumped.
+#[derive(Clone)]
+pub struct BedN<T: ChromName = LexicalChromRef> {
+    pub core: Bed3<T>,
+    /// The tab separated columns after the end column, `None` if there's no such column
+    pub fields: Option<Arc<str>>,
+}
+
+impl<T: ChromName, H: ChromSetHandle> WithChromSet<H> for BedN<T> {
+    type Result = BedN<H::RefType>;
+    fn with_chrom_set(self, handle: &mut H) -> Self::Result {
+        self.with_chrom_list(handle)
+    }
+}
+
+impl<'a> Parsable<'a> for BedN<&'a str> {
+    fn try_parse(s: &'a str) -> std::result::Result<(Self, usize), ParseFailure> {
+        let (core, end) = Bed3::try_parse(s)?;
+        let line = s.trim_end_matches(&['\r', '\n'][..]);
+        let fields = if end < line.len() {
+            Some(line[end + 1..].into())
+        } else {
+            None
+        };
+        Ok((Self { core, fields }, line.len()))
+    }
+}
+
+impl<T: ChromName> BedN<T> {
+    pub fn with_chrom_list<H: ChromSetHandle>(self, chrom_list: &mut H) -> BedN<H::RefType> {
+        BedN {
+            core: self.core.with_chrom_list(chrom_list),
+            fields: self.fields,
+        }
+    }
+
+    /// Get a column after the end column, `field(0)` is the 4th column of the line
+    pub fn field(&self, idx: usize) -> Option<&str> {
+        self.fields.as_ref()?.split('\t').nth(idx)
+    }
+
+    /// The number of columns after the end column
+    pub fn num_fields(&self) -> usize {
+        self.fields
+            .as_ref()
+            .map_or(0, |fields| fields.split('\t').count())
+    }
+}
+
+impl<T: ChromName> WithRegionCore<T> for BedN<T> {
+    fn begin(&self) -> u32 {
+        self.core.begin()
+    }
+
+    fn end(&self) -> u32 {
+        self.core.end()
+    }
+
+    fn chrom(&self) -> &T {
+        self.core.chrom()
+    }
+}
+
+impl<T: ChromName> WithName for BedN<T> {
+    fn name(&self) -> &str {
+        self.field(0).unwrap_or(".")
+    }
+}
+
+impl<T: ChromName> Serializable for BedN<T> {
+    fn dump<W: Write>(&self, mut fp: W) -> Result<()> {
+        self.core.dump(&mut fp)?;
+        if let Some(fields) = self.fields.as_ref() {
+            fp.write_all(b"\t")?;
+            fp.write_all(fields.as_bytes())?;
+        }
+        Ok(())
+    }
+}
+
+impl<T: ChromName> WithScore<f64> for BedN<T> {
+    fn score(&self) -> Option<f64> {
+        self.field(1)?.parse().ok()
+    }
+}
+impl<T: ChromName> WithStrand for BedN<T> {
+    fn strand(&self) -> Option<Strand> {
+        self.field(2)
+            .and_then(StrandColumn::parse)
+            .and_then(StrandColumn::strand)
+    }
+}
+
+impl<T: ChromName> WithBlocks<T> for BedN<T> {}
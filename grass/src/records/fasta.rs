@@ -0,0 +1,401 @@
+use std::{
+    cell::RefCell,
+    collections::HashMap,
+    fmt::{Display, Formatter, Result as FmtResult},
+    fs::File,
+    io::{BufRead, BufReader, Error, ErrorKind, Read, Result, Seek, SeekFrom, Write},
+    iter::Map,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    properties::{Nuclide, Strand, WithRegionCore, WithSequence, WithStrand},
+    ChromName,
+};
+
+/// A line of the `.fai` index, which locates a sequence in the FASTA file
+#[derive(Clone, Debug, PartialEq)]
+pub struct FaiEntry {
+    pub name: String,
+    pub length: u64,
+    /// The file offset of the first base
+    pub offset: u64,
+    /// The number of bases in each line
+    pub line_bases: u64,
+    /// The number of bytes in each line, including the line break
+    pub line_width: u64,
+}
+
+impl FaiEntry {
+    fn parse(line: &str) -> Option<Self> {
+        let mut fields = line.trim_end().split('\t');
+        Some(Self {
+            name: fields.next()?.to_string(),
+            length: fields.next()?.parse().ok()?,
+            offset: fields.next()?.parse().ok()?,
+            line_bases: fields.next()?.parse().ok()?,
+            line_width: fields.next()?.parse().ok()?,
+        })
+    }
+
+    /// The file offset of the base at the position
+    fn file_offset(&self, pos: u64) -> u64 {
+        self.offset + pos / self.line_bases * self.line_width + pos % self.line_bases
+    }
+}
+
+fn invalid_data(message: String) -> Error {
+    Error::new(ErrorKind::InvalidData, message)
+}
+
+/// Scan the FASTA file and build the index, like `samtools faidx` does
+fn build_index<R: BufRead>(mut reader: R) -> Result<Vec<FaiEntry>> {
+    let mut entries: Vec<FaiEntry> = vec![];
+    let mut offset = 0;
+    let mut line = vec![];
+    // A line shorter than the others is only allowed at the end of a sequence
+    let mut seen_short_line = false;
+    loop {
+        line.clear();
+        let size = reader.read_until(b'\n', &mut line)? as u64;
+        if size == 0 {
+            break;
+        }
+        offset += size;
+        if line[0] == b'>' {
+            let name = String::from_utf8_lossy(&line[1..])
+                .split_whitespace()
+                .next()
+                .unwrap_or_default()
+                .to_string();
+            entries.push(FaiEntry {
+                name,
+                length: 0,
+                offset,
+                line_bases: 0,
+                line_width: 0,
+            });
+            seen_short_line = false;
+            continue;
+        }
+        let entry = match entries.last_mut() {
+            Some(entry) => entry,
+            None if line.iter().all(u8::is_ascii_whitespace) => continue,
+            None => return Err(invalid_data("sequence before the first header".to_string())),
+        };
+        let has_line_break = line.ends_with(b"\n");
+        let bases = line
+            .iter()
+            .take_while(|&&c| c != b'\r' && c != b'\n')
+            .count() as u64;
+        if bases == 0 {
+            seen_short_line = true;
+            continue;
+        }
+        if entry.line_bases == 0 {
+            entry.line_bases = bases;
+            entry.line_width = size;
+        } else if seen_short_line
+            || bases > entry.line_bases
+            || (has_line_break && bases == entry.line_bases && size != entry.line_width)
+        {
+            return Err(invalid_data(format!(
+                "different line length in sequence {}",
+                entry.name
+            )));
+        }
+        seen_short_line |= bases < entry.line_bases;
+        entry.length += bases;
+    }
+    Ok(entries)
+}
+
+/// Complement a base in the FASTA file, the IUPAC codes and the soft masking are kept
+fn complement_byte(base: u8) -> u8 {
+    let complement = match base.to_ascii_uppercase() {
+        b'A' => b'T',
+        b'T' | b'U' => b'A',
+        b'C' => b'G',
+        b'G' => b'C',
+        b'R' => b'Y',
+        b'Y' => b'R',
+        b'K' => b'M',
+        b'M' => b'K',
+        b'B' => b'V',
+        b'V' => b'B',
+        b'D' => b'H',
+        b'H' => b'D',
+        other => other,
+    };
+    if base.is_ascii_lowercase() {
+        complement.to_ascii_lowercase()
+    } else {
+        complement
+    }
+}
+
+/// A piece of sequence read from a FASTA file, the soft masked bases are in lower case
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Sequence {
+    bases: Vec<u8>,
+}
+
+impl Sequence {
+    pub fn new(bases: Vec<u8>) -> Self {
+        Self { bases }
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.bases
+    }
+
+    pub fn len(&self) -> usize {
+        self.bases.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.bases.is_empty()
+    }
+
+    /// Check if the base is soft masked, such as a base in a repeat region
+    pub fn is_masked(&self, offset: usize) -> bool {
+        self.bases[offset].is_ascii_lowercase()
+    }
+
+    /// The sequence with the soft masking removed
+    pub fn to_unmasked(&self) -> Self {
+        Self::new(self.bases.to_ascii_uppercase())
+    }
+
+    /// The sequence of the opposite strand
+    pub fn reverse_complement(&self) -> Self {
+        Self::new(
+            self.bases
+                .iter()
+                .rev()
+                .map(|&b| complement_byte(b))
+                .collect(),
+        )
+    }
+}
+
+impl Display for Sequence {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        write!(f, "{}", String::from_utf8_lossy(&self.bases))
+    }
+}
+
+impl IntoIterator for Sequence {
+    type Item = Nuclide;
+    type IntoIter = Map<std::vec::IntoIter<u8>, fn(u8) -> Nuclide>;
+    fn into_iter(self) -> Self::IntoIter {
+        self.bases.into_iter().map(Nuclide::from_byte)
+    }
+}
+
+impl WithSequence for Sequence {
+    type RangeType = Sequence;
+    fn at(&self, offset: usize) -> Nuclide {
+        Nuclide::from_byte(self.bases[offset])
+    }
+
+    fn range(&self, from: usize, to: usize) -> Self::RangeType {
+        Self::new(self.bases[from..to].to_vec())
+    }
+}
+
+/// A FASTA file with the random access by the `.fai` index
+pub struct IndexedFasta {
+    file: RefCell<File>,
+    entries: Vec<FaiEntry>,
+    lookup: HashMap<String, usize>,
+}
+
+impl IndexedFasta {
+    /// Open the FASTA file, the index at `<path>.fai` is used when it presents, otherwise the
+    /// index is built by scanning the file. The compressed FASTA files are not supported.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let mut file = File::open(path)?;
+
+        let mut magic = [0u8; 2];
+        if file.read(&mut magic)? == 2 && magic == [0x1f, 0x8b] {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!("{}: compressed FASTA is not supported", path.display()),
+            ));
+        }
+        file.seek(SeekFrom::Start(0))?;
+
+        let index_path = Self::index_path(path);
+        let entries = if index_path.exists() {
+            let mut entries = vec![];
+            for line in BufReader::new(File::open(&index_path)?).lines() {
+                let line = line?;
+                let entry = FaiEntry::parse(&line).ok_or_else(|| {
+                    invalid_data(format!("{}: malformed line {}", index_path.display(), line))
+                })?;
+                entries.push(entry);
+            }
+            entries
+        } else {
+            build_index(BufReader::new(&mut file))?
+        };
+
+        let lookup = entries
+            .iter()
+            .enumerate()
+            .map(|(idx, entry)| (entry.name.clone(), idx))
+            .collect();
+        Ok(Self {
+            file: RefCell::new(file),
+            entries,
+            lookup,
+        })
+    }
+
+    fn index_path(path: &Path) -> PathBuf {
+        let mut index_path = path.as_os_str().to_owned();
+        index_path.push(".fai");
+        index_path.into()
+    }
+
+    /// Write the index, so that the file doesn't need to be scanned next time
+    pub fn write_index<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        let mut out = File::create(path)?;
+        for entry in self.entries.iter() {
+            writeln!(
+                out,
+                "{}\t{}\t{}\t{}\t{}",
+                entry.name, entry.length, entry.offset, entry.line_bases, entry.line_width
+            )?;
+        }
+        Ok(())
+    }
+
+    /// The sequences in the file order
+    pub fn entries(&self) -> &[FaiEntry] {
+        &self.entries
+    }
+
+    pub fn sequence(&self, name: &str) -> Option<ChromSequence<'_>> {
+        let entry = &self.entries[*self.lookup.get(name)?];
+        Some(ChromSequence { fasta: self, entry })
+    }
+
+    fn read(&self, entry: &FaiEntry, from: u64, to: u64) -> Result<Sequence> {
+        let to = to.min(entry.length);
+        if from >= to {
+            return Ok(Sequence::default());
+        }
+        let begin = entry.file_offset(from);
+        let end = entry.file_offset(to - 1) + 1;
+        let mut bases = vec![0; (end - begin) as usize];
+        let mut file = self.file.borrow_mut();
+        file.seek(SeekFrom::Start(begin))?;
+        file.read_exact(&mut bases)?;
+        bases.retain(|c| !c.is_ascii_whitespace());
+        Ok(Sequence::new(bases))
+    }
+
+    /// Read the sequence of the region, the part beyond the end of the chromosome is dropped
+    pub fn fetch<C: ChromName, R: WithRegionCore<C>>(&self, region: &R) -> Result<Sequence> {
+        let chrom = ChromName::to_string(region.chrom());
+        let sequence = self.sequence(&chrom).ok_or_else(|| {
+            Error::new(ErrorKind::NotFound, format!("Unknown sequence {}", chrom))
+        })?;
+        sequence.try_range(region.begin() as usize, region.end() as usize)
+    }
+
+    /// Read the sequence of the region on its strand, the sequence of a region on the negative
+    /// strand is reverse complemented
+    pub fn fetch_stranded<C, R>(&self, region: &R) -> Result<Sequence>
+    where
+        C: ChromName,
+        R: WithRegionCore<C> + WithStrand,
+    {
+        let sequence = self.fetch(region)?;
+        if region.strand() == Some(Strand::Neg) {
+            Ok(sequence.reverse_complement())
+        } else {
+            Ok(sequence)
+        }
+    }
+}
+
+/// A single sequence in an indexed FASTA file, the bases are read from the file on demand
+pub struct ChromSequence<'a> {
+    fasta: &'a IndexedFasta,
+    entry: &'a FaiEntry,
+}
+
+impl<'a> ChromSequence<'a> {
+    pub fn name(&self) -> &str {
+        &self.entry.name
+    }
+
+    pub fn len(&self) -> usize {
+        self.entry.length as usize
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entry.length == 0
+    }
+
+    pub fn try_range(&self, from: usize, to: usize) -> Result<Sequence> {
+        self.fasta.read(self.entry, from as u64, to as u64)
+    }
+}
+
+/// The bases are read from the file, thus it panics when the file can't be read, use
+/// `ChromSequence::try_range` to handle the errors
+impl<'a> WithSequence for ChromSequence<'a> {
+    type RangeType = Sequence;
+    fn at(&self, offset: usize) -> Nuclide {
+        self.range(offset, offset + 1)
+            .into_iter()
+            .next()
+            .unwrap_or(Nuclide::N)
+    }
+
+    fn range(&self, from: usize, to: usize) -> Self::RangeType {
+        self.try_range(from, to)
+            .unwrap_or_else(|e| panic!("Unable to read sequence {}: {}", self.name(), e))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{properties::Parsable, records::Bed6};
+
+    #[test]
+    fn test_indexed_fasta() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("indexed.fa");
+        std::fs::write(&path, ">chr1 test\nACGTacgt\nAACCGG\n>chr2\nTTTT\nGG\n").unwrap();
+        let index_path = IndexedFasta::index_path(&path);
+
+        let fasta = IndexedFasta::open(&path).unwrap();
+        assert_eq!(fasta.entries()[1].name, "chr2");
+        assert_eq!(fasta.entries()[0].length, 14);
+
+        let chr1 = fasta.sequence("chr1").unwrap();
+        let bases = chr1.range(6, 10);
+        assert_eq!(bases.to_string(), "gtAA");
+        assert!(bases.is_masked(0) && !bases.is_masked(2));
+        assert_eq!(chr1.at(9), Nuclide::A);
+        assert_eq!(chr1.range(12, 100).to_string(), "GG");
+
+        let (region, _) = Bed6::<_, f64>::parse("chr1\t2\t7\tx\t0\t-").unwrap();
+        assert_eq!(fasta.fetch(&region).unwrap().to_string(), "GTacg");
+        assert_eq!(fasta.fetch_stranded(&region).unwrap().to_string(), "cgtAC");
+
+        fasta.write_index(&index_path).unwrap();
+        let fasta = IndexedFasta::open(&path).unwrap();
+        assert_eq!(
+            fasta.sequence("chr2").unwrap().range(2, 6).to_string(),
+            "TTGG"
+        );
+    }
+}
@@ -0,0 +1,260 @@
+use super::{invalid_field, missing_field, parse_field, Bed3, Bed4, Bed5, Bed6};
+use std::io::{Result, Write};
+use std::marker::PhantomData;
+use std::sync::Arc;
+
+use crate::{
+    chromset::LexicalChromRef,
+    properties::{
+        Parsable, ParseFailure, Serializable, Strand, StrandColumn, WithBlocks, WithName,
+        WithRegionCore, WithScore, WithStrand,
+    },
+    ChromName, ChromSetHandle, WithChromSet,
+};
+
+/// The syntax of the attribute column, which is the only difference between GFF3 and GTF
+pub trait GffDialect: Clone {
+    /// The attributes used as the name of the record, the first one presents is used
+    const NAME_KEYS: &'static [&'static str];
+
+    /// Split a single attribute into the key and the value
+    fn split_attribute(attribute: &str) -> Option<(&str, &str)>;
+}
+
+/// The GFF3 attributes, such as `ID=gene0;Name=TP53`
+#[derive(Clone)]
+pub struct Gff3;
+
+impl GffDialect for Gff3 {
+    const NAME_KEYS: &'static [&'static str] = &["Name", "ID"];
+
+    fn split_attribute(attribute: &str) -> Option<(&str, &str)> {
+        let mut parts = attribute.splitn(2, '=');
+        Some((parts.next()?, parts.next()?))
+    }
+}
+
+/// The GTF attributes, such as `gene_id "ENSG00000141510"; gene_name "TP53";`
+#[derive(Clone)]
+pub struct Gtf;
+
+impl GffDialect for Gtf {
+    const NAME_KEYS: &'static [&'static str] = &["gene_name", "gene_id"];
+
+    fn split_attribute(attribute: &str) -> Option<(&str, &str)> {
+        let mut parts = attribute.splitn(2, ' ');
+        let key = parts.next()?;
+        let value = parts.next()?.trim();
+        Some((key, value.trim_matches('"')))
+    }
+}
+
+/// A feature line of a GFF3 or GTF file.
+///
+/// The 1-based closed coordinates in the file are converted to the half-open region, thus the
+/// `begin` is one less than the start column. The attributes are kept as they are and only
+/// parsed when they are queried.
+#[derive(Clone)]
+pub struct GffRecord<T: ChromName, D: GffDialect> {
+    pub core: Bed3<T>,
+    pub source: Arc<str>,
+    pub feature: Arc<str>,
+    pub score: Option<f64>,
+    /// `None` if the strand is unknown, which is written as `.` or `?`
+    pub strand: Option<Strand>,
+    /// The phase of a CDS feature, `None` if it's written as `.`
+    pub phase: Option<u8>,
+    /// If the unknown strand is written as `?`, which marks the features whose strand is
+    /// relevant but unknown
+    strand_relevant: bool,
+    attributes: Arc<str>,
+    _dialect: PhantomData<D>,
+}
+
+pub type Gff3Record<T = LexicalChromRef> = GffRecord<T, Gff3>;
+pub type GtfRecord<T = LexicalChromRef> = GffRecord<T, Gtf>;
+
+impl<T: ChromName, H: ChromSetHandle, D: GffDialect> WithChromSet<H> for GffRecord<T, D> {
+    type Result = GffRecord<H::RefType, D>;
+    fn with_chrom_set(self, handle: &mut H) -> Self::Result {
+        self.with_chrom_list(handle)
+    }
+}
+
+impl<'a, D: GffDialect> Parsable<'a> for GffRecord<&'a str, D> {
+    fn try_parse(s: &'a str) -> std::result::Result<(Self, usize), ParseFailure> {
+        let line = s.trim_end_matches(&['\r', '\n'][..]);
+        let mut fields = [""; 9];
+        let mut tokens = line.splitn(9, '\t');
+        for (idx, field) in fields.iter_mut().enumerate() {
+            *field = tokens.next().ok_or_else(|| missing_field(idx + 1))?;
+        }
+        let [chrom, source, feature, start_field, end, score, strand, phase, attributes] = fields;
+
+        let start: u32 = parse_field(start_field, 4, "start position")?;
+        let end: u32 = parse_field(end, 5, "end position")?;
+        if start == 0 {
+            return Err(invalid_field(4, "start position", start_field));
+        }
+        if start.checked_sub(1).is_some_and(|begin| end < begin) {
+            return Err((
+                5,
+                "the end position is before the start position".to_string(),
+            ));
+        }
+        let score = match score {
+            "." => None,
+            score => Some(parse_field(score, 6, "score")?),
+        };
+        let strand_relevant = strand == "?";
+        let strand = match strand {
+            "?" => None,
+            strand => StrandColumn::parse(strand)
+                .ok_or_else(|| invalid_field(7, "strand", strand))?
+                .strand(),
+        };
+        let phase = match phase {
+            "." => None,
+            phase => Some(
+                phase
+                    .parse()
+                    .ok()
+                    .filter(|phase| *phase < 3)
+                    .ok_or_else(|| invalid_field(8, "phase", phase))?,
+            ),
+        };
+
+        Ok((
+            Self {
+                core: Bed3 {
+                    chrom,
+                    begin: start - 1,
+                    end,
+                },
+                source: source.into(),
+                feature: feature.into(),
+                score,
+                strand,
+                phase,
+                strand_relevant,
+                attributes: attributes.into(),
+                _dialect: PhantomData,
+            },
+            line.len(),
+        ))
+    }
+}
+
+impl<T: ChromName, D: GffDialect> GffRecord<T, D> {
+    pub fn with_chrom_list<H: ChromSetHandle>(
+        self,
+        chrom_list: &mut H,
+    ) -> GffRecord<H::RefType, D> {
+        GffRecord {
+            core: self.core.with_chrom_list(chrom_list),
+            source: self.source,
+            feature: self.feature,
+            score: self.score,
+            strand: self.strand,
+            phase: self.phase,
+            strand_relevant: self.strand_relevant,
+            attributes: self.attributes,
+            _dialect: PhantomData,
+        }
+    }
+
+    /// The attributes in the order of the file, the values are not unescaped
+    pub fn attributes(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.attributes
+            .split(';')
+            .map(str::trim)
+            .filter(|attribute| !attribute.is_empty())
+            .filter_map(D::split_attribute)
+    }
+
+    /// Get the value of the first attribute with the key
+    pub fn attribute(&self, key: &str) -> Option<&str> {
+        self.attributes()
+            .find(|(attr_key, _)| *attr_key == key)
+            .map(|(_, value)| value)
+    }
+
+    /// Convert the record to a BED6 record, which has the name, score and strand of the record
+    pub fn to_bed6(&self) -> Bed6<T, f64> {
+        Bed6 {
+            core: Bed5 {
+                core: Bed4 {
+                    core: self.core.clone(),
+                    name: Arc::new(self.name().to_string()),
+                },
+                score: self.score,
+            },
+            strand: self.strand,
+        }
+    }
+}
+
+impl<T: ChromName, D: GffDialect> WithRegionCore<T> for GffRecord<T, D> {
+    fn begin(&self) -> u32 {
+        self.core.begin()
+    }
+
+    fn end(&self) -> u32 {
+        self.core.end()
+    }
+
+    fn chrom(&self) -> &T {
+        self.core.chrom()
+    }
+}
+
+impl<T: ChromName, D: GffDialect> WithName for GffRecord<T, D> {
+    fn name(&self) -> &str {
+        D::NAME_KEYS
+            .iter()
+            .find_map(|key| self.attribute(key))
+            .unwrap_or(".")
+    }
+}
+
+impl<T: ChromName, D: GffDialect> Serializable for GffRecord<T, D> {
+    fn dump<W: Write>(&self, mut fp: W) -> Result<()> {
+        self.chrom().write(&mut fp)?;
+        write!(
+            fp,
+            "\t{}\t{}\t{}\t{}\t",
+            self.source,
+            self.feature,
+            self.begin() + 1,
+            self.end()
+        )?;
+        match self.score.as_ref() {
+            Some(score) => crate::ioutils::write_score(&mut fp, score)?,
+            None => fp.write_all(b".")?,
+        }
+        let strand = match self.strand {
+            None if self.strand_relevant => "?",
+            strand => StrandColumn::from(strand).as_str(),
+        };
+        write!(fp, "\t{}\t", strand)?;
+        match self.phase {
+            Some(phase) => write!(fp, "{}", phase)?,
+            None => fp.write_all(b".")?,
+        }
+        write!(fp, "\t{}", self.attributes)
+    }
+}
+
+impl<T: ChromName, D: GffDialect> WithScore<f64> for GffRecord<T, D> {
+    fn score(&self) -> Option<f64> {
+        self.score
+    }
+}
+
+impl<T: ChromName, D: GffDialect> WithStrand for GffRecord<T, D> {
+    fn strand(&self) -> Option<Strand> {
+        self.strand
+    }
+}
+
+impl<T: ChromName, D: GffDialect> WithBlocks<T> for GffRecord<T, D> {}
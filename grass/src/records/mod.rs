@@ -1,8 +1,57 @@
+use std::str::FromStr;
+
+use crate::properties::ParseFailure;
+
+/// Take the tab separated field after the previous field, which ends at the byte offset `end`.
+/// The field is the 1-based `column` of the line, it's returned with its own end offset.
+pub(crate) fn next_field(
+    line: &str,
+    end: usize,
+    column: usize,
+) -> Result<(&str, usize), ParseFailure> {
+    let begin = end + 1;
+    if begin > line.len() {
+        return Err(missing_field(column));
+    }
+    let end = line[begin..]
+        .find('\t')
+        .map_or(line.len(), |size| begin + size);
+    Ok((&line[begin..end], end))
+}
+
+pub(crate) fn parse_field<T: FromStr>(
+    field: &str,
+    column: usize,
+    what: &str,
+) -> Result<T, ParseFailure> {
+    field
+        .parse()
+        .map_err(|_| invalid_field(column, what, field))
+}
+
+pub(crate) fn missing_field(column: usize) -> ParseFailure {
+    (
+        column,
+        format!(
+            "expecting at least {} columns, but {} found",
+            column,
+            column - 1
+        ),
+    )
+}
+
+pub(crate) fn invalid_field(column: usize, what: &str, field: &str) -> ParseFailure {
+    (column, format!("invalid {} '{}'", what, field))
+}
+
 #[cfg(feature = "hts")]
 mod bam;
 
 #[cfg(feature = "hts")]
-pub use bam::{BAMRecord, BamFile};
+pub use bam::{
+    BAMRecord, BAMRegionIter, BamErrorSlot, BamFile, BamFlag, Cigar, CigarIter, CigarOps,
+    TypedValue,
+};
 
 #[cfg(feature = "hts")]
 mod vcf;
@@ -18,3 +67,27 @@ pub use bed4::Bed4;
 
 mod bed5;
 pub use bed5::Bed5;
+
+mod bed6;
+pub use bed6::Bed6;
+
+mod bed12;
+pub use bed12::Bed12;
+
+mod bedn;
+pub use bedn::BedN;
+
+mod bedgraph;
+pub use bedgraph::BedGraph;
+
+mod gff;
+pub use gff::{Gff3, Gff3Record, GffDialect, GffRecord, Gtf, GtfRecord};
+
+mod peak;
+pub use peak::{BroadPeak, NarrowPeak, PeakStats};
+
+mod vcfline;
+pub use vcfline::VcfLine;
+
+mod fasta;
+pub use fasta::{ChromSequence, FaiEntry, IndexedFasta, Sequence};
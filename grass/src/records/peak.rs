@@ -0,0 +1,206 @@
+use super::{invalid_field, next_field, parse_field, Bed6};
+use std::convert::TryFrom;
+use std::io::{Result, Write};
+
+use crate::{
+    chromset::LexicalChromRef,
+    properties::{
+        Parsable, ParseFailure, Serializable, Strand, WithBlocks, WithName, WithRegionCore,
+        WithScore, WithStrand,
+    },
+    ChromName, ChromSetHandle, WithChromSet,
+};
+
+/// Split the tab separated columns after the BED6 columns, which end at the offset. Returns the
+/// end offset of the last column.
+fn parse_columns<const N: usize>(
+    s: &str,
+    mut end: usize,
+) -> std::result::Result<([&str; N], usize), ParseFailure> {
+    let line = s.trim_end_matches(&['\r', '\n'][..]);
+    let mut fields = [""; N];
+    for (idx, field) in fields.iter_mut().enumerate() {
+        let (value, value_end) = next_field(line, end, idx + 7)?;
+        *field = value;
+        end = value_end;
+    }
+    Ok((fields, end))
+}
+
+/// The statistics shared by the narrowPeak and broadPeak records, -1 means not assigned
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PeakStats {
+    pub signal_value: f64,
+    pub p_value: f64,
+    pub q_value: f64,
+}
+
+impl PeakStats {
+    fn parse(fields: &[&str]) -> std::result::Result<Self, ParseFailure> {
+        Ok(Self {
+            signal_value: parse_field(fields[0], 7, "signal value")?,
+            p_value: parse_field(fields[1], 8, "p-value")?,
+            q_value: parse_field(fields[2], 9, "q-value")?,
+        })
+    }
+
+    fn dump<W: Write>(&self, mut fp: W) -> Result<()> {
+        for value in [self.signal_value, self.p_value, self.q_value].iter() {
+            fp.write_all(b"\t")?;
+            crate::ioutils::write_score(&mut fp, value)?;
+        }
+        Ok(())
+    }
+}
+
+macro_rules! impl_peak_record {
+    ($rec_ty:ident) => {
+        impl<T: ChromName, H: ChromSetHandle> WithChromSet<H> for $rec_ty<T> {
+            type Result = $rec_ty<H::RefType>;
+            fn with_chrom_set(self, handle: &mut H) -> Self::Result {
+                self.with_chrom_list(handle)
+            }
+        }
+
+        impl<T: ChromName> $rec_ty<T> {
+            /// The overall enrichment of the peak
+            pub fn signal_value(&self) -> f64 {
+                self.stats.signal_value
+            }
+
+            /// The -log10 p-value of the peak, -1 if it's not assigned
+            pub fn p_value(&self) -> f64 {
+                self.stats.p_value
+            }
+
+            /// The -log10 q-value of the peak, -1 if it's not assigned
+            pub fn q_value(&self) -> f64 {
+                self.stats.q_value
+            }
+        }
+
+        impl<T: ChromName> WithRegionCore<T> for $rec_ty<T> {
+            fn begin(&self) -> u32 {
+                self.core.begin()
+            }
+
+            fn end(&self) -> u32 {
+                self.core.end()
+            }
+
+            fn chrom(&self) -> &T {
+                self.core.chrom()
+            }
+        }
+
+        impl<T: ChromName> WithName for $rec_ty<T> {
+            fn name(&self) -> &str {
+                self.core.name()
+            }
+        }
+
+        impl<T: ChromName> WithScore<f64> for $rec_ty<T> {
+            fn score(&self) -> Option<f64> {
+                self.core.score()
+            }
+        }
+
+        impl<T: ChromName> WithStrand for $rec_ty<T> {
+            fn strand(&self) -> Option<Strand> {
+                self.core.strand()
+            }
+        }
+
+        impl<T: ChromName> WithBlocks<T> for $rec_ty<T> {}
+    };
+}
+
+/// An ENCODE narrowPeak record, which is BED6 with the statistics and the summit of the peak
+#[derive(Clone)]
+pub struct NarrowPeak<T: ChromName = LexicalChromRef> {
+    pub core: Bed6<T, f64>,
+    pub stats: PeakStats,
+    /// The offset of the summit from the begin of the peak, `None` if it's written as -1
+    pub peak: Option<u32>,
+}
+
+impl<'a> Parsable<'a> for NarrowPeak<&'a str> {
+    fn try_parse(s: &'a str) -> std::result::Result<(Self, usize), ParseFailure> {
+        let (core, end) = Bed6::try_parse(s)?;
+        let (fields, end) = parse_columns::<4>(s, end)?;
+        let stats = PeakStats::parse(&fields)?;
+        let invalid_peak = || invalid_field(10, "peak offset", fields[3]);
+        let peak = match parse_field::<i64>(fields[3], 10, "peak offset")? {
+            -1 => None,
+            offset => Some(u32::try_from(offset).map_err(|_| invalid_peak())?),
+        };
+        Ok((Self { core, stats, peak }, end))
+    }
+}
+
+impl<T: ChromName> NarrowPeak<T> {
+    pub fn with_chrom_list<H: ChromSetHandle>(self, chrom_list: &mut H) -> NarrowPeak<H::RefType> {
+        NarrowPeak {
+            core: self.core.with_chrom_list(chrom_list),
+            stats: self.stats,
+            peak: self.peak,
+        }
+    }
+
+    /// The offset of the summit from the begin of the peak
+    pub fn peak_offset(&self) -> Option<u32> {
+        self.peak
+    }
+
+    /// The position of the summit on the chromosome
+    pub fn summit(&self) -> Option<u32> {
+        self.peak.map(|offset| self.begin() + offset)
+    }
+}
+
+impl<T: ChromName> Serializable for NarrowPeak<T> {
+    fn dump<W: Write>(&self, mut fp: W) -> Result<()> {
+        self.core.dump(&mut fp)?;
+        self.stats.dump(&mut fp)?;
+        match self.peak {
+            Some(offset) => write!(fp, "\t{}", offset),
+            None => fp.write_all(b"\t-1"),
+        }
+    }
+}
+
+impl_peak_record!(NarrowPeak);
+
+/// An ENCODE broadPeak record, which is BED6 with the statistics of the region
+#[derive(Clone)]
+pub struct BroadPeak<T: ChromName = LexicalChromRef> {
+    pub core: Bed6<T, f64>,
+    pub stats: PeakStats,
+}
+
+impl<'a> Parsable<'a> for BroadPeak<&'a str> {
+    fn try_parse(s: &'a str) -> std::result::Result<(Self, usize), ParseFailure> {
+        let (core, end) = Bed6::try_parse(s)?;
+        let (fields, end) = parse_columns::<3>(s, end)?;
+        let stats = PeakStats::parse(&fields)?;
+        Ok((Self { core, stats }, end))
+    }
+}
+
+impl<T: ChromName> BroadPeak<T> {
+    pub fn with_chrom_list<H: ChromSetHandle>(self, chrom_list: &mut H) -> BroadPeak<H::RefType> {
+        BroadPeak {
+            core: self.core.with_chrom_list(chrom_list),
+            stats: self.stats,
+        }
+    }
+}
+
+impl<T: ChromName> Serializable for BroadPeak<T> {
+    fn dump<W: Write>(&self, mut fp: W) -> Result<()> {
+        self.core.dump(&mut fp)?;
+        self.stats.dump(&mut fp)
+    }
+}
+
+impl_peak_record!(BroadPeak);
@@ -0,0 +1,146 @@
+use super::{invalid_field, missing_field, Bed3};
+use std::io::{Result, Write};
+use std::sync::Arc;
+
+use crate::{
+    chromset::LexicalChromRef,
+    properties::{
+        Parsable, ParseFailure, Serializable, WithBlocks, WithName, WithRegionCore, WithScore,
+        WithStrand,
+    },
+    ChromName, ChromSetHandle, WithChromSet,
+};
+
+/// A data line of a VCF file, which is parsed from the text so that it can be read from a tabix
+/// indexed `.vcf.gz` without htslib.
+///
+/// The record covers the bases of REF, like tabix does, thus the `begin` is one less than POS
+/// and the `end` is `begin` plus the length of REF. The columns after ALT are kept as they are.
+#[derive(Clone)]
+pub struct VcfLine<T: ChromName = LexicalChromRef> {
+    pub core: Bed3<T>,
+    pub id: Arc<str>,
+    pub reference: Arc<str>,
+    pub alternative: Arc<str>,
+    /// QUAL, FILTER, INFO and the genotype columns
+    rest: Arc<str>,
+}
+
+impl<T: ChromName, H: ChromSetHandle> WithChromSet<H> for VcfLine<T> {
+    type Result = VcfLine<H::RefType>;
+    fn with_chrom_set(self, handle: &mut H) -> Self::Result {
+        self.with_chrom_list(handle)
+    }
+}
+
+impl<'a> Parsable<'a> for VcfLine<&'a str> {
+    fn try_parse(s: &'a str) -> std::result::Result<(Self, usize), ParseFailure> {
+        let line = s.trim_end_matches(&['\r', '\n'][..]);
+        let mut fields = [""; 6];
+        let mut tokens = line.splitn(6, '\t');
+        for (idx, field) in fields.iter_mut().enumerate() {
+            *field = tokens.next().ok_or_else(|| missing_field(idx + 1))?;
+        }
+        let [chrom, pos, id, reference, alternative, rest] = fields;
+
+        // QUAL, FILTER and INFO are required
+        let rest_columns = rest.split('\t').count();
+        if rest_columns < 3 {
+            return Err(missing_field(6 + rest_columns));
+        }
+        let begin = match pos.parse::<u32>() {
+            Ok(pos) if pos > 0 => pos - 1,
+            _ => return Err(invalid_field(2, "position", pos)),
+        };
+
+        Ok((
+            Self {
+                core: Bed3 {
+                    chrom,
+                    begin,
+                    end: begin + (reference.len() as u32).max(1),
+                },
+                id: id.into(),
+                reference: reference.into(),
+                alternative: alternative.into(),
+                rest: rest.into(),
+            },
+            line.len(),
+        ))
+    }
+}
+
+impl<T: ChromName> VcfLine<T> {
+    pub fn with_chrom_list<H: ChromSetHandle>(self, chrom_list: &mut H) -> VcfLine<H::RefType> {
+        VcfLine {
+            core: self.core.with_chrom_list(chrom_list),
+            id: self.id,
+            reference: self.reference,
+            alternative: self.alternative,
+            rest: self.rest,
+        }
+    }
+
+    /// The QUAL column, `None` if it's missing
+    pub fn quality(&self) -> Option<f64> {
+        self.column(0).and_then(|qual| qual.parse().ok())
+    }
+
+    /// The FILTER column
+    pub fn filter(&self) -> &str {
+        self.column(1).unwrap_or(".")
+    }
+
+    /// The INFO column, which is not parsed
+    pub fn info(&self) -> &str {
+        self.column(2).unwrap_or(".")
+    }
+
+    fn column(&self, idx: usize) -> Option<&str> {
+        self.rest.split('\t').nth(idx)
+    }
+}
+
+impl<T: ChromName> WithRegionCore<T> for VcfLine<T> {
+    fn begin(&self) -> u32 {
+        self.core.begin()
+    }
+
+    fn end(&self) -> u32 {
+        self.core.end()
+    }
+
+    fn chrom(&self) -> &T {
+        self.core.chrom()
+    }
+}
+
+impl<T: ChromName> WithName for VcfLine<T> {
+    fn name(&self) -> &str {
+        &self.id
+    }
+}
+
+impl<T: ChromName> Serializable for VcfLine<T> {
+    fn dump<W: Write>(&self, mut fp: W) -> Result<()> {
+        self.chrom().write(&mut fp)?;
+        write!(
+            fp,
+            "\t{}\t{}\t{}\t{}\t{}",
+            self.begin() + 1,
+            self.id,
+            self.reference,
+            self.alternative,
+            self.rest
+        )
+    }
+}
+
+impl<T: ChromName> WithScore<f64> for VcfLine<T> {
+    fn score(&self) -> Option<f64> {
+        self.quality()
+    }
+}
+
+impl<T: ChromName> WithStrand for VcfLine<T> {}
+impl<T: ChromName> WithBlocks<T> for VcfLine<T> {}
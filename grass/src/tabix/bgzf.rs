@@ -0,0 +1,276 @@
+use std::io::{BufRead, Error, ErrorKind, Read, Result, Seek, SeekFrom, Write};
+
+use libflate::deflate::{Decoder, Encoder};
+
+/// The largest uncompressed size of a block, the same as htslib uses
+const MAX_BLOCK_DATA: usize = 0xff00;
+/// The largest size of a compressed block
+const MAX_BLOCK_SIZE: usize = 0x10000;
+/// The size of the block header with only the BC extra field, and the size of the CRC32 and
+/// ISIZE trailer
+const HEADER_SIZE: usize = 18;
+const TRAILER_SIZE: usize = 8;
+
+/// The empty block that marks the end of a BGZF file
+pub const EOF_BLOCK: [u8; 28] = [
+    0x1f, 0x8b, 0x08, 0x04, 0x00, 0x00, 0x00, 0x00, 0x00, 0xff, 0x06, 0x00, 0x42, 0x43, 0x02, 0x00,
+    0x1b, 0x00, 0x03, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+];
+
+fn invalid_data(message: &str) -> Error {
+    Error::new(ErrorKind::InvalidData, format!("BGZF: {}", message))
+}
+
+fn crc32(data: &[u8]) -> u32 {
+    let mut hasher = crc32fast::Hasher::new();
+    hasher.update(data);
+    hasher.finalize()
+}
+
+/// A position in a BGZF file, the file offset of the compressed block in the upper 48 bits and
+/// the offset in the uncompressed block in the lower 16 bits
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct VirtualOffset(pub u64);
+
+impl VirtualOffset {
+    pub fn new(block_offset: u64, data_offset: u16) -> Self {
+        Self((block_offset << 16) | data_offset as u64)
+    }
+
+    /// The file offset of the compressed block
+    pub fn block_offset(&self) -> u64 {
+        self.0 >> 16
+    }
+
+    /// The offset in the uncompressed block
+    pub fn data_offset(&self) -> u16 {
+        self.0 as u16
+    }
+}
+
+/// Read a BGZF file, which is a series of small gzip members that can be decompressed
+/// independently, so the reader can seek to a virtual offset
+pub struct BgzfReader<R> {
+    inner: R,
+    /// The file offset of the block in the buffer
+    block_offset: u64,
+    /// The file offset of the block after it
+    next_block_offset: u64,
+    data: Vec<u8>,
+    pos: usize,
+}
+
+impl<R: Read> BgzfReader<R> {
+    pub fn new(inner: R) -> Self {
+        Self {
+            inner,
+            block_offset: 0,
+            next_block_offset: 0,
+            data: vec![],
+            pos: 0,
+        }
+    }
+
+    /// The virtual offset of the next byte to read
+    pub fn virtual_offset(&self) -> VirtualOffset {
+        if self.pos < self.data.len() {
+            VirtualOffset::new(self.block_offset, self.pos as u16)
+        } else {
+            VirtualOffset::new(self.next_block_offset, 0)
+        }
+    }
+
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+
+    /// Read the next block into the buffer, returns false at the end of the file
+    fn read_block(&mut self) -> Result<bool> {
+        let mut header = [0u8; 12];
+        let mut size = 0;
+        while size < header.len() {
+            match self.inner.read(&mut header[size..])? {
+                0 if size == 0 => return Ok(false),
+                0 => return Err(invalid_data("truncated block header")),
+                n => size += n,
+            }
+        }
+        if header[..4] != [0x1f, 0x8b, 0x08, 0x04] {
+            return Err(invalid_data("not a BGZF block"));
+        }
+
+        let extra_size = u16::from_le_bytes([header[10], header[11]]) as usize;
+        let mut extra = vec![0; extra_size];
+        self.inner.read_exact(&mut extra)?;
+        let mut block_size = None;
+        let mut fields = &extra[..];
+        while fields.len() >= 4 {
+            let field_size = u16::from_le_bytes([fields[2], fields[3]]) as usize;
+            if fields[..2] == *b"BC" && field_size == 2 && fields.len() >= 6 {
+                block_size = Some(u16::from_le_bytes([fields[4], fields[5]]) as usize + 1);
+            }
+            fields = &fields[(4 + field_size).min(fields.len())..];
+        }
+        let block_size = block_size.ok_or_else(|| invalid_data("missing block size"))?;
+        if block_size < header.len() + extra_size + TRAILER_SIZE {
+            return Err(invalid_data("invalid block size"));
+        }
+
+        let mut body = vec![0; block_size - header.len() - extra_size];
+        self.inner.read_exact(&mut body)?;
+        let (compressed, trailer) = body.split_at(body.len() - TRAILER_SIZE);
+        let crc = u32::from_le_bytes([trailer[0], trailer[1], trailer[2], trailer[3]]);
+        let data_size = u32::from_le_bytes([trailer[4], trailer[5], trailer[6], trailer[7]]);
+
+        self.data.clear();
+        Decoder::new(compressed).read_to_end(&mut self.data)?;
+        if self.data.len() != data_size as usize || crc32(&self.data) != crc {
+            return Err(invalid_data("corrupted block"));
+        }
+
+        self.block_offset = self.next_block_offset;
+        self.next_block_offset += block_size as u64;
+        self.pos = 0;
+        Ok(true)
+    }
+}
+
+impl<R: Read + Seek> BgzfReader<R> {
+    /// Move to the virtual offset, which is from the index or `virtual_offset`
+    pub fn seek_virtual(&mut self, offset: VirtualOffset) -> Result<()> {
+        if offset.block_offset() != self.block_offset || self.data.is_empty() {
+            self.inner.seek(SeekFrom::Start(offset.block_offset()))?;
+            self.next_block_offset = offset.block_offset();
+            self.data.clear();
+            self.pos = 0;
+            // The offset at the end of the file doesn't have a block to read
+            self.read_block()?;
+        }
+        if offset.data_offset() as usize > self.data.len() {
+            return Err(invalid_data("virtual offset out of the block"));
+        }
+        self.pos = offset.data_offset() as usize;
+        Ok(())
+    }
+}
+
+impl<R: Read> Read for BgzfReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+        let data = self.fill_buf()?;
+        let size = data.len().min(buf.len());
+        buf[..size].copy_from_slice(&data[..size]);
+        self.consume(size);
+        Ok(size)
+    }
+}
+
+impl<R: Read> BufRead for BgzfReader<R> {
+    fn fill_buf(&mut self) -> Result<&[u8]> {
+        // The empty blocks, such as the EOF marker, are skipped
+        while self.pos >= self.data.len() {
+            if !self.read_block()? {
+                break;
+            }
+        }
+        Ok(&self.data[self.pos..])
+    }
+
+    fn consume(&mut self, amt: usize) {
+        self.pos = (self.pos + amt).min(self.data.len());
+    }
+}
+
+/// Write a BGZF file, `finish` must be called to write the EOF marker
+pub struct BgzfWriter<W: Write> {
+    inner: Option<W>,
+    /// The file offset of the block being filled
+    block_offset: u64,
+    data: Vec<u8>,
+}
+
+impl<W: Write> BgzfWriter<W> {
+    pub fn new(inner: W) -> Self {
+        Self {
+            inner: Some(inner),
+            block_offset: 0,
+            data: Vec::with_capacity(MAX_BLOCK_DATA),
+        }
+    }
+
+    /// The virtual offset of the next byte to write
+    pub fn virtual_offset(&self) -> VirtualOffset {
+        VirtualOffset::new(self.block_offset, self.data.len() as u16)
+    }
+
+    fn write_block(&mut self, data: &[u8]) -> Result<()> {
+        let mut encoder = Encoder::new(Vec::with_capacity(data.len()));
+        encoder.write_all(data)?;
+        let compressed = encoder.finish().into_result()?;
+        let block_size = HEADER_SIZE + compressed.len() + TRAILER_SIZE;
+        if block_size > MAX_BLOCK_SIZE {
+            // Rarely the data can't be compressed enough, which is written as two blocks
+            let (first, second) = data.split_at(data.len() / 2);
+            self.write_block(first)?;
+            return self.write_block(second);
+        }
+
+        let fp = self.inner.as_mut().unwrap();
+        let mut header = EOF_BLOCK[..HEADER_SIZE].to_vec();
+        header[16..18].copy_from_slice(&((block_size - 1) as u16).to_le_bytes());
+        fp.write_all(&header)?;
+        fp.write_all(&compressed)?;
+        fp.write_all(&crc32(data).to_le_bytes())?;
+        fp.write_all(&(data.len() as u32).to_le_bytes())?;
+        self.block_offset += block_size as u64;
+        Ok(())
+    }
+
+    /// Compress the pending data as a block, so that the next byte begins a new block
+    pub fn flush_block(&mut self) -> Result<()> {
+        if !self.data.is_empty() {
+            let data = std::mem::take(&mut self.data);
+            self.write_block(&data)?;
+            self.data = data;
+            self.data.clear();
+        }
+        Ok(())
+    }
+
+    /// Write the remaining data and the EOF marker, and return the underlying writer
+    pub fn finish(mut self) -> Result<W> {
+        self.flush_block()?;
+        let mut fp = self.inner.take().unwrap();
+        fp.write_all(&EOF_BLOCK)?;
+        fp.flush()?;
+        Ok(fp)
+    }
+}
+
+impl<W: Write> Write for BgzfWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> Result<usize> {
+        let size = buf.len().min(MAX_BLOCK_DATA - self.data.len());
+        self.data.extend_from_slice(&buf[..size]);
+        // A full block is written at once, so the virtual offset always points to a byte in
+        // the block being filled
+        if self.data.len() == MAX_BLOCK_DATA {
+            self.flush_block()?;
+        }
+        Ok(size)
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        self.flush_block()?;
+        self.inner.as_mut().unwrap().flush()
+    }
+}
+
+impl<W: Write> Drop for BgzfWriter<W> {
+    fn drop(&mut self) {
+        if self.inner.is_some() {
+            let _ = self.flush_block();
+            if let Some(fp) = self.inner.as_mut() {
+                let _ = fp.write_all(&EOF_BLOCK);
+            }
+        }
+    }
+}
@@ -0,0 +1,617 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    fs::File,
+    io::{BufRead, Error, ErrorKind, Read, Result, Write},
+    path::{Path, PathBuf},
+};
+
+use super::bgzf::{BgzfReader, BgzfWriter, VirtualOffset};
+
+/// The bin that htslib uses to store the metadata of a reference, which is not a real bin
+const META_BIN: u32 = 37450;
+/// The binning scheme of the `.tbi` index, each bin of the finest level is 16kb
+const TBI_MIN_SHIFT: u32 = 14;
+const TBI_DEPTH: u32 = 5;
+
+fn invalid_data(message: String) -> Error {
+    Error::new(ErrorKind::InvalidData, message)
+}
+
+/// The bin of the smallest level that contains the region
+fn reg_to_bin(begin: u64, end: u64, min_shift: u32, depth: u32) -> u32 {
+    let end = end.max(begin + 1) - 1;
+    let mut shift = min_shift;
+    let mut offset = ((1u64 << (depth * 3)) - 1) / 7;
+    for level in (1..=depth).rev() {
+        if begin >> shift == end >> shift {
+            return (offset + (begin >> shift)) as u32;
+        }
+        shift += 3;
+        offset -= 1 << ((level - 1) * 3);
+    }
+    0
+}
+
+/// All the bins that may contain records overlapping the region
+fn reg_to_bins(begin: u64, end: u64, min_shift: u32, depth: u32) -> Vec<u32> {
+    let max_shift = min_shift + depth * 3;
+    let end = end.min(1 << max_shift);
+    if begin >= end {
+        return vec![];
+    }
+    let end = end - 1;
+    let mut bins = vec![];
+    let mut offset = 0;
+    for level in 0..=depth {
+        let shift = max_shift - level * 3;
+        bins.extend((offset + (begin >> shift))..=(offset + (end >> shift)));
+        offset += 1 << (level * 3);
+    }
+    bins.into_iter().map(|bin| bin as u32).collect()
+}
+
+/// A range of the BGZF file that contains the records of a bin
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Chunk {
+    pub begin: VirtualOffset,
+    pub end: VirtualOffset,
+}
+
+/// Which columns of the text file have the region, the same as the header of the tabix index
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TabixConfig {
+    /// 0 for generic, 1 for SAM and 2 for VCF, with 0x10000 set if the positions are 0-based
+    pub format: i32,
+    /// The 1-based columns of the chromosome, the begin and the end, the end column is 0 if
+    /// the record covers only one base
+    pub col_seq: i32,
+    pub col_beg: i32,
+    pub col_end: i32,
+    /// The lines beginning with the character are comments
+    pub meta: u8,
+    /// The number of header lines to skip at the beginning of the file
+    pub skip: i32,
+}
+
+impl TabixConfig {
+    const ZERO_BASED: i32 = 0x10000;
+    const VCF: i32 = 2;
+
+    /// The BED like files, such as BedGraph and narrowPeak, like `tabix -p bed`
+    pub fn bed() -> Self {
+        Self {
+            format: Self::ZERO_BASED,
+            col_seq: 1,
+            col_beg: 2,
+            col_end: 3,
+            meta: b'#',
+            skip: 0,
+        }
+    }
+
+    /// Like `tabix -p gff`
+    pub fn gff() -> Self {
+        Self {
+            format: 0,
+            col_seq: 1,
+            col_beg: 4,
+            col_end: 5,
+            meta: b'#',
+            skip: 0,
+        }
+    }
+
+    /// Like `tabix -p vcf`, the end of a record is computed from the length of REF
+    pub fn vcf() -> Self {
+        Self {
+            format: Self::VCF,
+            col_seq: 1,
+            col_beg: 2,
+            col_end: 0,
+            meta: b'#',
+            skip: 0,
+        }
+    }
+
+    /// The chromosome and the 0-based, half open region of a line, `None` if the line can't
+    /// be parsed
+    pub fn parse_line<'a>(&self, line: &'a [u8]) -> Option<(&'a [u8], u64, u64)> {
+        let line = line.strip_suffix(b"\n").unwrap_or(line);
+        let line = line.strip_suffix(b"\r").unwrap_or(line);
+        let fields: Vec<_> = line.split(|&c| c == b'\t').collect();
+        let column = |col: i32| fields.get((col as usize).checked_sub(1)?).copied();
+        let number =
+            |col: i32| -> Option<u64> { std::str::from_utf8(column(col)?).ok()?.parse().ok() };
+
+        let chrom = column(self.col_seq)?;
+        let mut begin = number(self.col_beg)?;
+        if self.format & Self::ZERO_BASED == 0 {
+            begin = begin.checked_sub(1)?;
+        }
+        let end = if self.format & 0xffff == Self::VCF {
+            begin + column(4).map_or(1, |reference| reference.len() as u64)
+        } else if self.col_end > 0 {
+            number(self.col_end)?
+        } else {
+            begin + 1
+        };
+        Some((chrom, begin, end.max(begin + 1)))
+    }
+
+    fn read<R: Read>(reader: &mut R) -> Result<Self> {
+        Ok(Self {
+            format: read_i32(reader)?,
+            col_seq: read_i32(reader)?,
+            col_beg: read_i32(reader)?,
+            col_end: read_i32(reader)?,
+            meta: read_i32(reader)? as u8,
+            skip: read_i32(reader)?,
+        })
+    }
+
+    fn write<W: Write>(&self, mut fp: W) -> Result<()> {
+        for value in [
+            self.format,
+            self.col_seq,
+            self.col_beg,
+            self.col_end,
+            self.meta as i32,
+            self.skip,
+        ] {
+            fp.write_all(&value.to_le_bytes())?;
+        }
+        Ok(())
+    }
+}
+
+fn read_i32<R: Read>(reader: &mut R) -> Result<i32> {
+    let mut buf = [0; 4];
+    reader.read_exact(&mut buf)?;
+    Ok(i32::from_le_bytes(buf))
+}
+
+fn read_u64<R: Read>(reader: &mut R) -> Result<u64> {
+    let mut buf = [0; 8];
+    reader.read_exact(&mut buf)?;
+    Ok(u64::from_le_bytes(buf))
+}
+
+fn read_count<R: Read>(reader: &mut R) -> Result<usize> {
+    let count = read_i32(reader)?;
+    if count < 0 {
+        return Err(invalid_data(format!(
+            "negative count {} in the index",
+            count
+        )));
+    }
+    Ok(count as usize)
+}
+
+/// The index of a chromosome
+#[derive(Clone, Debug, Default)]
+struct RefIndex {
+    bins: BTreeMap<u32, Vec<Chunk>>,
+    /// The smallest offset of the records overlapping each 16kb window, only in `.tbi`
+    linear: Vec<VirtualOffset>,
+    /// The smallest offset of the records in each bin, only in `.csi`
+    bin_offsets: HashMap<u32, VirtualOffset>,
+}
+
+/// The tabix index of a bgzipped text file, which is read from a `.tbi` or `.csi` file or built
+/// by scanning the file
+#[derive(Clone, Debug)]
+pub struct TabixIndex {
+    pub config: TabixConfig,
+    min_shift: u32,
+    depth: u32,
+    names: Vec<String>,
+    lookup: HashMap<String, usize>,
+    refs: Vec<RefIndex>,
+    is_csi: bool,
+}
+
+impl TabixIndex {
+    /// The path of the `.tbi` index of the file
+    pub fn index_path<P: AsRef<Path>>(path: P) -> PathBuf {
+        let mut index_path = path.as_ref().as_os_str().to_owned();
+        index_path.push(".tbi");
+        index_path.into()
+    }
+
+    /// The path of the `.csi` index of the file
+    pub fn csi_path<P: AsRef<Path>>(path: P) -> PathBuf {
+        let mut index_path = path.as_ref().as_os_str().to_owned();
+        index_path.push(".csi");
+        index_path.into()
+    }
+
+    /// Load the index of the data file from `<path>.tbi` or `<path>.csi`
+    pub fn load_for<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let tbi = Self::index_path(path);
+        if tbi.exists() {
+            return Self::load(tbi);
+        }
+        let csi = Self::csi_path(path);
+        if csi.exists() {
+            return Self::load(csi);
+        }
+        Err(Error::new(
+            ErrorKind::NotFound,
+            format!("{}: no .tbi or .csi index", path.display()),
+        ))
+    }
+
+    /// Load a `.tbi` or `.csi` index file
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let mut reader = BgzfReader::new(File::open(path)?);
+        let mut magic = [0; 4];
+        reader.read_exact(&mut magic)?;
+        match &magic {
+            b"TBI\x01" => Self::read_tbi(&mut reader),
+            b"CSI\x01" => Self::read_csi(&mut reader),
+            _ => Err(invalid_data(format!(
+                "{}: not a tabix index",
+                path.display()
+            ))),
+        }
+    }
+
+    fn read_names<R: Read>(reader: &mut R) -> Result<Vec<String>> {
+        let mut names = vec![0; read_count(reader)?];
+        reader.read_exact(&mut names)?;
+        Ok(names
+            .split(|&c| c == 0)
+            .filter(|name| !name.is_empty())
+            .map(|name| String::from_utf8_lossy(name).into_owned())
+            .collect())
+    }
+
+    fn read_chunks<R: Read>(reader: &mut R) -> Result<Vec<Chunk>> {
+        (0..read_count(reader)?)
+            .map(|_| {
+                Ok(Chunk {
+                    begin: VirtualOffset(read_u64(reader)?),
+                    end: VirtualOffset(read_u64(reader)?),
+                })
+            })
+            .collect()
+    }
+
+    fn read_tbi<R: Read>(reader: &mut R) -> Result<Self> {
+        let n_ref = read_count(reader)?;
+        let config = TabixConfig::read(reader)?;
+        let names = Self::read_names(reader)?;
+        let mut refs = vec![];
+        for _ in 0..n_ref {
+            let mut index = RefIndex::default();
+            for _ in 0..read_count(reader)? {
+                let bin = read_i32(reader)? as u32;
+                index.bins.insert(bin, Self::read_chunks(reader)?);
+            }
+            index.linear = (0..read_count(reader)?)
+                .map(|_| read_u64(reader).map(VirtualOffset))
+                .collect::<Result<_>>()?;
+            refs.push(index);
+        }
+        Ok(Self::from_parts(
+            config,
+            TBI_MIN_SHIFT,
+            TBI_DEPTH,
+            names,
+            refs,
+            false,
+        ))
+    }
+
+    fn read_csi<R: Read>(reader: &mut R) -> Result<Self> {
+        let min_shift = read_i32(reader)? as u32;
+        let depth = read_i32(reader)? as u32;
+        let mut aux = vec![0; read_count(reader)?];
+        reader.read_exact(&mut aux)?;
+        // The index of a text file has the tabix header as the auxiliary data
+        let mut aux = &aux[..];
+        let (config, names) = if aux.len() >= 28 {
+            (TabixConfig::read(&mut aux)?, Self::read_names(&mut aux)?)
+        } else {
+            (TabixConfig::bed(), vec![])
+        };
+        let mut refs = vec![];
+        for _ in 0..read_count(reader)? {
+            let mut index = RefIndex::default();
+            for _ in 0..read_count(reader)? {
+                let bin = read_i32(reader)? as u32;
+                index
+                    .bin_offsets
+                    .insert(bin, VirtualOffset(read_u64(reader)?));
+                index.bins.insert(bin, Self::read_chunks(reader)?);
+            }
+            refs.push(index);
+        }
+        Ok(Self::from_parts(
+            config, min_shift, depth, names, refs, true,
+        ))
+    }
+
+    fn from_parts(
+        config: TabixConfig,
+        min_shift: u32,
+        depth: u32,
+        names: Vec<String>,
+        refs: Vec<RefIndex>,
+        is_csi: bool,
+    ) -> Self {
+        let lookup = names
+            .iter()
+            .enumerate()
+            .map(|(idx, name)| (name.clone(), idx))
+            .collect();
+        Self {
+            config,
+            min_shift,
+            depth,
+            names,
+            lookup,
+            refs,
+            is_csi,
+        }
+    }
+
+    /// Scan the bgzipped file and build the index, like the `tabix` command does. The records
+    /// must be sorted by the chromosome and then the begin position.
+    pub fn build<R: Read>(reader: R, config: TabixConfig) -> Result<Self> {
+        let mut reader = BgzfReader::new(reader);
+        let mut builder = TabixIndexBuilder::new(config);
+        let mut line = vec![];
+        loop {
+            let begin = reader.virtual_offset();
+            line.clear();
+            if reader.read_until(b'\n', &mut line)? == 0 {
+                break;
+            }
+            builder.push(&line, begin, reader.virtual_offset())?;
+        }
+        Ok(builder.finish())
+    }
+
+    /// Build the index of the bgzipped file and write it to `<path>.tbi`
+    pub fn build_for<P: AsRef<Path>>(path: P, config: TabixConfig) -> Result<Self> {
+        let path = path.as_ref();
+        let index = Self::build(File::open(path)?, config)
+            .map_err(|e| Error::new(e.kind(), format!("{}: {}", path.display(), e)))?;
+        index.write_tbi(Self::index_path(path))?;
+        Ok(index)
+    }
+
+    /// Write the index in the `.tbi` format
+    pub fn write_tbi<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        if self.is_csi {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                "a .csi index can't be written as .tbi",
+            ));
+        }
+        let mut fp = BgzfWriter::new(File::create(path)?);
+        fp.write_all(b"TBI\x01")?;
+        fp.write_all(&(self.refs.len() as i32).to_le_bytes())?;
+        self.config.write(&mut fp)?;
+        let names: Vec<u8> = self
+            .names
+            .iter()
+            .flat_map(|name| name.bytes().chain(Some(0)))
+            .collect();
+        fp.write_all(&(names.len() as i32).to_le_bytes())?;
+        fp.write_all(&names)?;
+        for index in self.refs.iter() {
+            fp.write_all(&(index.bins.len() as i32).to_le_bytes())?;
+            for (bin, chunks) in index.bins.iter() {
+                fp.write_all(&bin.to_le_bytes())?;
+                fp.write_all(&(chunks.len() as i32).to_le_bytes())?;
+                for chunk in chunks {
+                    fp.write_all(&chunk.begin.0.to_le_bytes())?;
+                    fp.write_all(&chunk.end.0.to_le_bytes())?;
+                }
+            }
+            fp.write_all(&(index.linear.len() as i32).to_le_bytes())?;
+            for offset in index.linear.iter() {
+                fp.write_all(&offset.0.to_le_bytes())?;
+            }
+        }
+        fp.finish()?;
+        Ok(())
+    }
+
+    /// The chromosomes in the index
+    pub fn names(&self) -> &[String] {
+        &self.names
+    }
+
+    /// The smallest offset that a record overlapping the position can begin
+    fn min_offset(&self, index: &RefIndex, begin: u64) -> VirtualOffset {
+        if !self.is_csi {
+            let window = (begin >> self.min_shift) as usize;
+            return index
+                .linear
+                .get(window)
+                .or_else(|| index.linear.last())
+                .copied()
+                .unwrap_or_default();
+        }
+        let mut bin = reg_to_bin(begin, begin + 1, self.min_shift, self.depth);
+        loop {
+            if let Some(offset) = index.bin_offsets.get(&bin) {
+                return *offset;
+            }
+            if bin == 0 {
+                return VirtualOffset::default();
+            }
+            bin = (bin - 1) >> 3;
+        }
+    }
+
+    /// The merged chunks that may contain the records overlapping the region
+    pub fn query(&self, chrom: &str, begin: u64, end: u64) -> Vec<Chunk> {
+        let index = match self.lookup.get(chrom) {
+            Some(&idx) => &self.refs[idx],
+            None => return vec![],
+        };
+        let min_offset = self.min_offset(index, begin);
+        let mut chunks: Vec<Chunk> = reg_to_bins(begin, end, self.min_shift, self.depth)
+            .into_iter()
+            .filter(|bin| *bin != META_BIN)
+            .filter_map(|bin| index.bins.get(&bin))
+            .flatten()
+            .filter(|chunk| chunk.end > min_offset)
+            .copied()
+            .collect();
+        chunks.sort();
+
+        let mut merged: Vec<Chunk> = vec![];
+        for chunk in chunks {
+            match merged.last_mut() {
+                Some(last) if chunk.begin <= last.end => last.end = last.end.max(chunk.end),
+                _ => merged.push(chunk),
+            }
+        }
+        merged
+    }
+}
+
+/// Build the index from the lines of a bgzipped file in the file order, such as the lines
+/// being written
+pub struct TabixIndexBuilder {
+    config: TabixConfig,
+    names: Vec<String>,
+    refs: Vec<RefIndex>,
+    line_no: usize,
+    last_begin: u64,
+    /// The bin of the chunk being built, along with the offset it begins
+    current: Option<(u32, VirtualOffset)>,
+    last_end: VirtualOffset,
+}
+
+impl TabixIndexBuilder {
+    pub fn new(config: TabixConfig) -> Self {
+        Self {
+            config,
+            names: vec![],
+            refs: vec![],
+            line_no: 0,
+            last_begin: 0,
+            current: None,
+            last_end: VirtualOffset::default(),
+        }
+    }
+
+    fn finish_chunk(&mut self) {
+        if let (Some((bin, begin)), Some(index)) = (self.current.take(), self.refs.last_mut()) {
+            push_chunk(index, bin, begin, self.last_end);
+        }
+    }
+
+    /// Add the line between the two virtual offsets, the header lines and the comments are
+    /// skipped. The lines must be sorted by the chromosome and then the begin position.
+    pub fn push(&mut self, line: &[u8], begin: VirtualOffset, end: VirtualOffset) -> Result<()> {
+        let config = self.config;
+        self.line_no += 1;
+        if self.line_no <= config.skip as usize
+            || line.first() == Some(&config.meta)
+            || line.iter().all(u8::is_ascii_whitespace)
+        {
+            return Ok(());
+        }
+        let line_no = self.line_no;
+        let (chrom, record_begin, record_end) = config
+            .parse_line(line)
+            .ok_or_else(|| invalid_data(format!("line {}: unable to parse the region", line_no)))?;
+
+        if self.names.last().map(|last| last.as_bytes()) != Some(chrom) {
+            let chrom = String::from_utf8_lossy(chrom).into_owned();
+            if self.names.contains(&chrom) {
+                return Err(invalid_data(format!(
+                    "line {}: the file is not sorted, {} appears again",
+                    line_no, chrom
+                )));
+            }
+            self.finish_chunk();
+            self.names.push(chrom);
+            self.refs.push(RefIndex::default());
+        } else if record_begin < self.last_begin {
+            return Err(invalid_data(format!(
+                "line {}: the file is not sorted by the begin position",
+                line_no
+            )));
+        }
+        self.last_begin = record_begin;
+
+        let bin = reg_to_bin(record_begin, record_end, TBI_MIN_SHIFT, TBI_DEPTH);
+        if !matches!(self.current, Some((current_bin, _)) if current_bin == bin) {
+            self.finish_chunk();
+            self.current = Some((bin, begin));
+        }
+        self.last_end = end;
+
+        let index = self.refs.last_mut().unwrap();
+        let first_window = (record_begin >> TBI_MIN_SHIFT) as usize;
+        let last_window = ((record_end - 1) >> TBI_MIN_SHIFT) as usize;
+        if index.linear.len() <= last_window {
+            index
+                .linear
+                .resize(last_window + 1, VirtualOffset::default());
+        }
+        for offset in index.linear[first_window..=last_window].iter_mut() {
+            if *offset == VirtualOffset::default() {
+                *offset = begin;
+            }
+        }
+        Ok(())
+    }
+
+    pub fn finish(mut self) -> TabixIndex {
+        self.finish_chunk();
+        // The windows without any record begin where the previous window does
+        for index in self.refs.iter_mut() {
+            for idx in 1..index.linear.len() {
+                if index.linear[idx] == VirtualOffset::default() {
+                    index.linear[idx] = index.linear[idx - 1];
+                }
+            }
+        }
+        TabixIndex::from_parts(
+            self.config,
+            TBI_MIN_SHIFT,
+            TBI_DEPTH,
+            self.names,
+            self.refs,
+            false,
+        )
+    }
+}
+
+fn push_chunk(index: &mut RefIndex, bin: u32, begin: VirtualOffset, end: VirtualOffset) {
+    let chunks = index.bins.entry(bin).or_default();
+    // The chunks of the same bin are merged if there's nothing between them
+    match chunks.last_mut() {
+        Some(last) if last.end == begin => last.end = end,
+        _ => chunks.push(Chunk { begin, end }),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_binning() {
+        // The values from the SAM specification
+        assert_eq!(reg_to_bin(0, 1, TBI_MIN_SHIFT, TBI_DEPTH), 4681);
+        assert_eq!(reg_to_bin(0, 1 << 14, TBI_MIN_SHIFT, TBI_DEPTH), 4681);
+        assert_eq!(reg_to_bin(0, (1 << 14) + 1, TBI_MIN_SHIFT, TBI_DEPTH), 585);
+        assert_eq!(reg_to_bin(0, 1 << 29, TBI_MIN_SHIFT, TBI_DEPTH), 0);
+        assert_eq!(
+            reg_to_bins(0, 1, TBI_MIN_SHIFT, TBI_DEPTH),
+            vec![0, 1, 9, 73, 585, 4681]
+        );
+    }
+}
@@ -0,0 +1,365 @@
+//! Random access to the bgzipped text files, such as `.bed.gz`, with the tabix index.
+//!
+//! The BGZF reader and writer and the `.tbi` index builder are implemented here, so the files
+//! can be indexed without the `tabix` command of htslib.
+mod bgzf;
+mod index;
+
+pub use bgzf::{BgzfReader, BgzfWriter, VirtualOffset};
+pub use index::{Chunk, TabixConfig, TabixIndex, TabixIndexBuilder};
+
+use std::{
+    fs::File,
+    io::{BufRead, Error, ErrorKind, Read, Result, Seek},
+    iter::Peekable,
+    path::Path,
+    vec::IntoIter,
+};
+
+use crate::{algorithm::Sorted, properties::WithRegionCore, records::Bed3, ChromName};
+
+/// A bgzipped text file with its tabix index
+pub struct TabixReader<R = File> {
+    reader: BgzfReader<R>,
+    index: TabixIndex,
+}
+
+impl TabixReader<File> {
+    /// Open the bgzipped file, the index is loaded from `<path>.tbi` or `<path>.csi`
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let index = TabixIndex::load_for(&path)?;
+        Ok(Self::new(File::open(path)?, index))
+    }
+}
+
+impl<R: Read + Seek> TabixReader<R> {
+    pub fn new(reader: R, index: TabixIndex) -> Self {
+        Self {
+            reader: BgzfReader::new(reader),
+            index,
+        }
+    }
+
+    pub fn index(&self) -> &TabixIndex {
+        &self.index
+    }
+
+    /// Read the lines overlapping a region like `chr1:1000-2000`, see `Bed3::parse_region` for
+    /// the region syntax
+    pub fn fetch(self, region: &str) -> Result<TabixRegion<R>> {
+        let parsed = Bed3::parse_region(region).ok_or_else(|| {
+            Error::new(
+                ErrorKind::InvalidInput,
+                format!("malformed region {}", region),
+            )
+        })?;
+        Ok(self.query(parsed.chrom, parsed.begin as u64, parsed.end as u64))
+    }
+
+    /// Read the lines overlapping the 0-based, half open region, the lines are in the file
+    /// order so they are sorted as well
+    pub fn query(self, chrom: &str, begin: u64, end: u64) -> TabixRegion<R> {
+        let chunks = self.index.query(chrom, begin, end);
+        TabixRegion {
+            reader: self,
+            chrom: chrom.to_string(),
+            begin,
+            end,
+            skip_before: 0,
+            chunks: chunks.into_iter(),
+            chunk_end: None,
+            line: vec![],
+            pos: 0,
+        }
+    }
+
+    /// Read the lines overlapping the sorted regions, the overlapping regions are merged and a
+    /// line overlapping more than one region is read only once, so the lines are still sorted
+    pub fn query_regions<C, I>(self, regions: I) -> TabixRegions<R>
+    where
+        C: ChromName,
+        I: IntoIterator,
+        I::IntoIter: Sorted + 'static,
+        I::Item: WithRegionCore<C>,
+    {
+        let regions = regions.into_iter().map(|region| {
+            let chrom = ChromName::to_string(region.chrom()).into_owned();
+            (chrom, region.begin() as u64, region.end() as u64)
+        });
+        TabixRegions {
+            regions: (Box::new(regions) as QueryRegions).peekable(),
+            reader: Some(self),
+            region: None,
+            last_region: None,
+        }
+    }
+}
+
+/// The lines of a tabix indexed file that overlap a region, which can be read as a text file,
+/// such as by `LineRecordStreamExt::into_record_iter`
+pub struct TabixRegion<R = File> {
+    reader: TabixReader<R>,
+    chrom: String,
+    begin: u64,
+    end: u64,
+    /// The lines beginning before it are already read from the previous region
+    skip_before: u64,
+    chunks: IntoIter<Chunk>,
+    /// The end of the chunk being read, `None` if no more lines can overlap the region
+    chunk_end: Option<VirtualOffset>,
+    line: Vec<u8>,
+    pos: usize,
+}
+
+impl<R: Read + Seek> TabixRegion<R> {
+    /// Get the reader back, so that it can be used for another region
+    pub fn into_reader(self) -> TabixReader<R> {
+        self.reader
+    }
+
+    /// Read the next line overlapping the region into the buffer, which is left empty at the end
+    fn next_line(&mut self) -> Result<()> {
+        self.line.clear();
+        self.pos = 0;
+        let reader = &mut self.reader.reader;
+        let config = self.reader.index.config;
+        loop {
+            match self.chunk_end {
+                Some(end) if reader.virtual_offset() < end => {}
+                _ => match self.chunks.next() {
+                    Some(chunk) => {
+                        reader.seek_virtual(chunk.begin)?;
+                        self.chunk_end = Some(chunk.end);
+                        continue;
+                    }
+                    None => return Ok(()),
+                },
+            }
+
+            if reader.read_until(b'\n', &mut self.line)? == 0 {
+                self.chunk_end = None;
+                continue;
+            }
+            match config.parse_line(&self.line) {
+                Some((chrom, begin, _)) if chrom == self.chrom.as_bytes() && begin >= self.end => {
+                    // The lines are sorted, the remaining lines are all after the region
+                    self.chunks = vec![].into_iter();
+                    self.chunk_end = None;
+                    self.line.clear();
+                    return Ok(());
+                }
+                Some((chrom, begin, end))
+                    if chrom == self.chrom.as_bytes()
+                        && end > self.begin
+                        && begin >= self.skip_before =>
+                {
+                    if !self.line.ends_with(b"\n") {
+                        self.line.push(b'\n');
+                    }
+                    return Ok(());
+                }
+                _ => self.line.clear(),
+            }
+        }
+    }
+}
+
+impl<R: Read + Seek> Read for TabixRegion<R> {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+        let data = self.fill_buf()?;
+        let size = data.len().min(buf.len());
+        buf[..size].copy_from_slice(&data[..size]);
+        self.consume(size);
+        Ok(size)
+    }
+}
+
+impl<R: Read + Seek> BufRead for TabixRegion<R> {
+    fn fill_buf(&mut self) -> Result<&[u8]> {
+        if self.pos >= self.line.len() {
+            self.next_line()?;
+        }
+        Ok(&self.line[self.pos..])
+    }
+
+    fn consume(&mut self, amt: usize) {
+        self.pos = (self.pos + amt).min(self.line.len());
+    }
+}
+
+type QueryRegions = Box<dyn Iterator<Item = (String, u64, u64)>>;
+
+/// The lines of a tabix indexed file that overlap a sorted list of regions, see
+/// `TabixReader::query_regions`
+pub struct TabixRegions<R = File> {
+    regions: Peekable<QueryRegions>,
+    /// The reader between two regions
+    reader: Option<TabixReader<R>>,
+    region: Option<TabixRegion<R>>,
+    last_region: Option<(String, u64)>,
+}
+
+impl<R: Read + Seek> TabixRegions<R> {
+    fn next_region(&mut self) -> Option<(String, u64, u64)> {
+        let (chrom, begin, mut end) = self.regions.next()?;
+        while let Some((next_chrom, next_begin, next_end)) = self.regions.peek() {
+            if *next_chrom != chrom || *next_begin > end {
+                break;
+            }
+            end = end.max(*next_end);
+            self.regions.next();
+        }
+        Some((chrom, begin, end))
+    }
+}
+
+impl<R: Read + Seek> Read for TabixRegions<R> {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+        let data = self.fill_buf()?;
+        let size = data.len().min(buf.len());
+        buf[..size].copy_from_slice(&data[..size]);
+        self.consume(size);
+        Ok(size)
+    }
+}
+
+impl<R: Read + Seek> BufRead for TabixRegions<R> {
+    fn fill_buf(&mut self) -> Result<&[u8]> {
+        loop {
+            if let Some(region) = self.region.as_mut() {
+                if !region.fill_buf()?.is_empty() {
+                    break;
+                }
+                self.reader = self.region.take().map(TabixRegion::into_reader);
+            }
+            let (chrom, begin, end) = match self.next_region() {
+                Some(region) => region,
+                None => return Ok(&[]),
+            };
+            let skip_before = match &self.last_region {
+                Some((last_chrom, last_end)) if *last_chrom == chrom => *last_end,
+                _ => 0,
+            };
+            let reader = self.reader.take().expect("the reader is always kept");
+            let mut region = reader.query(&chrom, begin, end);
+            region.skip_before = skip_before;
+            self.region = Some(region);
+            self.last_region = Some((chrom, end));
+        }
+        match self.region.as_mut() {
+            Some(region) => region.fill_buf(),
+            None => Ok(&[]),
+        }
+    }
+
+    fn consume(&mut self, amt: usize) {
+        if let Some(region) = self.region.as_mut() {
+            region.consume(amt);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::algorithm::AssumeSorted;
+    use std::io::{Cursor, Write};
+
+    #[test]
+    fn test_tabix_query() {
+        // Small blocks, so that the records span many blocks and windows
+        let mut writer = BgzfWriter::new(vec![]);
+        let mut lines = vec![];
+        writeln!(writer, "#chrom\tbegin\tend").unwrap();
+        for chrom in ["chr1", "chr2"].iter() {
+            for idx in 0..2000u64 {
+                let line = format!("{}\t{}\t{}\tr{}\n", chrom, idx * 50, idx * 50 + 80, idx);
+                writer.write_all(line.as_bytes()).unwrap();
+                if idx % 37 == 0 {
+                    writer.flush_block().unwrap();
+                }
+                lines.push(line);
+            }
+        }
+        let data = writer.finish().unwrap();
+
+        let index = TabixIndex::build(Cursor::new(&data), TabixConfig::bed()).unwrap();
+        assert_eq!(index.names(), &["chr1".to_string(), "chr2".to_string()]);
+        let tbi = tempfile::NamedTempFile::new().unwrap();
+        index.write_tbi(tbi.path()).unwrap();
+        let index = TabixIndex::load(tbi.path()).unwrap();
+
+        let mut reader = TabixReader::new(Cursor::new(&data), index);
+        for &(chrom, begin, end) in [
+            ("chr1", 0, 10),
+            ("chr1", 33_000, 51_000),
+            ("chr2", 99_900, 200_000),
+            ("chr2", 1_000, 1_001),
+            ("chr3", 0, 100),
+        ]
+        .iter()
+        {
+            let mut region = reader.query(chrom, begin, end);
+            let mut fetched = String::new();
+            region.read_to_string(&mut fetched).unwrap();
+            let expected: String = lines
+                .iter()
+                .filter(|line| {
+                    let (line_chrom, begin_line, end_line) =
+                        TabixConfig::bed().parse_line(line.as_bytes()).unwrap();
+                    line_chrom == chrom.as_bytes() && begin_line < end && end_line > begin
+                })
+                .cloned()
+                .collect();
+            assert_eq!(fetched, expected);
+            reader = region.into_reader();
+        }
+
+        let mut fetched = String::new();
+        let mut region = reader.fetch("chr1:101-150").unwrap();
+        region.read_to_string(&mut fetched).unwrap();
+        let reader = region.into_reader();
+        assert_eq!(fetched, "chr1\t50\t130\tr1\nchr1\t100\t180\tr2\n");
+
+        // The lines overlapping the first two regions are read once, so the output is sorted
+        let regions = vec![
+            Bed3 {
+                chrom: "chr1",
+                begin: 100,
+                end: 150,
+            },
+            Bed3 {
+                chrom: "chr1",
+                begin: 140,
+                end: 200,
+            },
+            Bed3 {
+                chrom: "chr1",
+                begin: 210,
+                end: 260,
+            },
+            Bed3 {
+                chrom: "chr2",
+                begin: 0,
+                end: 10,
+            },
+            Bed3 {
+                chrom: "chr3",
+                begin: 0,
+                end: 10,
+            },
+        ];
+        let mut fetched = String::new();
+        reader
+            .query_regions(regions.into_iter().assume_sorted())
+            .read_to_string(&mut fetched)
+            .unwrap();
+        let expected: Vec<_> = [(1, 1), (2, 1), (3, 1), (4, 1), (5, 1), (0, 2)]
+            .iter()
+            .map(|(idx, chrom)| {
+                format!("chr{}\t{}\t{}\tr{}\n", chrom, idx * 50, idx * 50 + 80, idx)
+            })
+            .collect();
+        assert_eq!(fetched, expected.concat());
+    }
+}
@@ -0,0 +1,60 @@
+//! The queries written in the `grass_query!` DSL. The macro inspects the input files when it's
+//! expanded, which happens in the workspace root, so the queries run from there as well.
+#![cfg(feature = "grass-macros")]
+
+use std::io::Read;
+
+#[test]
+fn test_bgzf_round_trip() {
+    std::env::set_current_dir(concat!(env!("CARGO_MANIFEST_DIR"), "/..")).unwrap();
+    let dir = tempfile::tempdir().unwrap();
+    let saved = dir.path().join("large.bed.gz");
+
+    // The input is larger than a BGZF block, so it has more than one gzip member
+    grass::grass_query_block! {
+        let large = open("data/large.bed.gz");
+        let result = large | save(&saved);
+    }
+    result.unwrap();
+
+    let read_all = |path| {
+        let mut text = String::new();
+        grass::high_level_api::open_gzip(path)
+            .unwrap()
+            .read_to_string(&mut text)
+            .unwrap();
+        text
+    };
+    let text = read_all(saved.as_path());
+    assert!(text.len() > 1 << 16);
+    assert_eq!(text.lines().count(), 5000);
+    assert_eq!(text, read_all("data/large.bed.gz".as_ref()));
+}
+
+#[test]
+fn test_vcf_region() {
+    use grass::properties::{WithName, WithRegionCore};
+
+    std::env::set_current_dir(concat!(env!("CARGO_MANIFEST_DIR"), "/..")).unwrap();
+    grass::grass_query_block! {
+        let variants = open("data/sample.vcf.gz", "1:714000-716000");
+    }
+    let variants: Vec<_> = variants
+        .map(|variant| {
+            let alleles = format!("{}>{}", variant.reference, variant.alternative);
+            (
+                variant.begin(),
+                variant.end(),
+                variant.name().to_string(),
+                alleles,
+            )
+        })
+        .collect();
+    assert_eq!(
+        variants,
+        [
+            (714426, 714427, ".".to_string(), "G>A".to_string()),
+            (715347, 715348, ".".to_string(), "T>G".to_string()),
+        ]
+    );
+}